@@ -0,0 +1,137 @@
+// compares the flood fill light engine against the old lighting (16 sweeps of a max minus 25 filter)
+// run with `cargo bench --bench lighting`
+
+use voxel_game::game_manager::world::tile_map::light_engine::{self, LightMap, LIGHT_FALLOFF_AIR, LIGHT_FALLOFF_SOLID};
+use std::time::Instant;
+
+const MAP_WIDTH: usize = 4095;
+const MAP_HEIGHT: usize = 1024;
+const LIGHT_COLOR: [u8; 3] = [255, 255, 128];
+
+/// A stand in for the tile map with a rough terrain of solid tiles and a scattering of lights
+struct BenchMap {
+    solid: Vec<Vec<bool>>,
+    lights: Vec<Vec<bool>>,
+    lighting: Vec<Vec<[u8; 3]>>,
+}
+
+impl BenchMap {
+    fn new() -> Self {
+        let mut solid = vec![vec![false; MAP_WIDTH]; MAP_HEIGHT];
+        let mut lights = vec![vec![false; MAP_WIDTH]; MAP_HEIGHT];
+        for y in 0..MAP_HEIGHT {
+            for x in 0..MAP_WIDTH {
+                // hills on the surface with bands of caves below
+                let surface = 300 + ((x as f32 * 0.02).sin() * 30.0) as usize;
+                solid[y][x] = y > surface && ((x * 7 + y * 13) % 23 > 6 || y % 40 < 30);
+                lights[y][x] = (x * 31 + y * 17) % 997 == 0;
+            }
+        }
+        BenchMap { solid, lights, lighting: vec![vec![[0; 3]; MAP_WIDTH]; MAP_HEIGHT] }
+    }
+}
+
+impl LightMap for BenchMap {
+    fn light_size(&self) -> (usize, usize) {
+        (MAP_WIDTH, MAP_HEIGHT)
+    }
+
+    fn get_light(&self, x: usize, y: usize) -> [u8; 3] {
+        self.lighting[y][x]
+    }
+
+    fn set_light(&mut self, x: usize, y: usize, light: [u8; 3]) {
+        self.lighting[y][x] = light;
+    }
+
+    fn light_falloff(&self, x: usize, y: usize) -> u8 {
+        match self.solid[y][x] {
+            true => LIGHT_FALLOFF_SOLID,
+            false => LIGHT_FALLOFF_AIR,
+        }
+    }
+
+    fn light_emission(&self, x: usize, y: usize) -> [u8; 3] {
+        match self.lights[y][x] {
+            true => LIGHT_COLOR,
+            false => [0, 0, 0],
+        }
+    }
+}
+
+// the old approach, copied over from before the light engine for comparison
+fn sweep_lighting(map: &mut BenchMap, start: (usize, usize), end: (usize, usize)) {
+    for _ in 0..16 {
+        for x in start.0..end.0 {
+            for y in start.1..end.1 {
+                let left = map.lighting[y][x.saturating_sub(1)].map(|f| f.saturating_sub(25));
+                let right = map.lighting[y][(x + 1).min(MAP_WIDTH - 1)].map(|f| f.saturating_sub(25));
+                let up = map.lighting[y.saturating_sub(1)][x].map(|f| f.saturating_sub(25));
+                let down = map.lighting[(y + 1).min(MAP_HEIGHT - 1)][x].map(|f| f.saturating_sub(25));
+                let self_light = map.lighting[y][x];
+                map.lighting[y][x] = [0, 1, 2].map(|i| left[i].max(right[i].max(up[i].max(down[i].max(self_light[i])))));
+            }
+        }
+    }
+}
+
+fn sweep_full_map(map: &mut BenchMap) {
+    for y in 0..MAP_HEIGHT {
+        for x in 0..MAP_WIDTH {
+            map.lighting[y][x] = map.light_emission(x, y);
+        }
+    }
+    sweep_lighting(map, (0, 0), (MAP_WIDTH, MAP_HEIGHT));
+}
+
+fn sweep_remove_light(map: &mut BenchMap, x: usize, y: usize) {
+    // the old removal reset a fixed box and re-swept a slightly larger one
+    for x_index in x.saturating_sub(16)..(x + 16).min(MAP_WIDTH - 1) {
+        for y_index in y.saturating_sub(16)..(y + 16).min(MAP_HEIGHT) {
+            map.lighting[y_index][x_index] = map.light_emission(x_index, y_index);
+        }
+    }
+    sweep_lighting(map, (x.saturating_sub(24), y.saturating_sub(24)), ((x + 24).min(MAP_WIDTH - 1), (y + 24).min(MAP_HEIGHT)));
+}
+
+fn time<F: FnMut()>(name: &str, iterations: u32, mut action: F) {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        action();
+    }
+    let average = start.elapsed().as_secs_f64() / iterations as f64;
+    println!("{:<36} {:>12.3} ms", name, average * 1000.0);
+}
+
+fn main() {
+    let mut map = BenchMap::new();
+    println!("lighting a {}x{} map", MAP_WIDTH, MAP_HEIGHT);
+
+    time("full map (16 sweeps)", 1, || sweep_full_map(&mut map));
+    time("full map (flood fill)", 3, || light_engine::flood_all_lights(&mut map));
+
+    // toggling a light in an open area near the surface
+    let (light_x, light_y) = (2000, 250);
+    time("place + remove light (16 sweeps)", 20, || {
+        map.lights[light_y][light_x] = true;
+        map.lighting[light_y][light_x] = LIGHT_COLOR;
+        sweep_lighting(&mut map, (light_x - 16, light_y - 16), (light_x + 16, light_y + 16));
+        map.lights[light_y][light_x] = false;
+        sweep_remove_light(&mut map, light_x, light_y);
+    });
+    light_engine::flood_all_lights(&mut map);
+    time("place + remove light (flood fill)", 20, || {
+        map.lights[light_y][light_x] = true;
+        light_engine::refresh_lights(&mut map, &[(light_x, light_y)]);
+        map.lights[light_y][light_x] = false;
+        light_engine::refresh_lights(&mut map, &[(light_x, light_y)]);
+    });
+
+    // placing and digging out a wall next to a light
+    map.lights[light_y][light_x] = true;
+    light_engine::refresh_lights(&mut map, &[(light_x, light_y)]);
+    time("toggle a wall by a light (flood fill)", 20, || {
+        map.solid[light_y][light_x + 2] = !map.solid[light_y][light_x + 2];
+        light_engine::refresh_lights(&mut map, &[(light_x + 2, light_y)]);
+    });
+}
@@ -15,6 +15,26 @@ float3 ToColor(ulong value) {
     );
 }
 
+// the damage stage (1 through 4) at which each pixel of a tile cracks (0 never cracks)
+constant uchar CRACK_PATTERN[64] = {
+    0, 0, 0, 4, 0, 0, 0, 0,
+    0, 3, 0, 3, 0, 0, 4, 0,
+    0, 0, 2, 2, 0, 3, 0, 0,
+    4, 0, 0, 1, 1, 2, 0, 0,
+    0, 3, 2, 1, 0, 0, 0, 0,
+    0, 0, 0, 2, 0, 0, 3, 0,
+    0, 0, 3, 0, 2, 0, 0, 4,
+    0, 4, 0, 0, 0, 3, 0, 0,
+};
+
+// the color of each wire in the overlay (red, blue, green, yellow; matching the wiring bits in tile_data.rs)
+constant float3 WIRE_COLORS[4] = {
+    float3(0.9, 0.15, 0.15),
+    float3(0.2, 0.4, 1.0),
+    float3(0.2, 0.85, 0.25),
+    float3(0.95, 0.85, 0.15),
+};
+
 struct Text {
     uchar characters[32];
     ulong2 info;
@@ -32,7 +52,7 @@ kernel void ComputeShader (
     constant uint&   tile_map_width    [[ buffer(6 ) ]],  // size of the tile map in tiles (width, height)
     constant uint&   tile_map_height   [[ buffer(7 ) ]],  // size of the tile map in tiles (width, height)
     constant ulong*  tile_map          [[ buffer(8 ) ]],  // tile map data (screen space, not all global tiles)
-    constant float4& camera_position   [[ buffer(9 ) ]],  // camera position (screen space offset) and scale and rotation
+    constant float4& camera_position   [[ buffer(9 ) ]],  // camera position (screen space offset) and scale, with w > 0.5 showing the wires
 
     constant uint&   num_entities      [[ buffer(10) ]],  // number of entities
     constant ulong2* entity_data       [[ buffer(11) ]],  // entity data
@@ -47,6 +67,7 @@ kernel void ComputeShader (
     constant uint&   default_font_size [[ buffer(17) ]],  // font size
 
     device   uchar* pixels [[ buffer(18) ]],
+    constant uint*   row_backgrounds   [[ buffer(19) ]],  // background color of each visible tile row (changes with the world layer)
     uint2 gid [[ thread_position_in_grid ]]
 ) {
     if (gid.x >= width || gid.y >= height) return;
@@ -55,7 +76,8 @@ kernel void ComputeShader (
     //    the tile map will have 3 layers: background (walls like in terraria), tiles, and annything forground
     //      related (mostly to make sure the layer is reserved incase it's later needed)    plus one layer for lighting (rgb strength)
     //    tiles are a 64 bit value, stored in an array buffer, with the first 32 being the tile id (aka texture index),
-    //      and the others being tile data (would very by tile probably)
+    //      and the others being tile data (break damage, orientation, wiring, shape; see tile_data.rs)
+    //    the lighting value uses the lower 24 bits for rgb, with bits 32-47 being the texture of any liquid in the tile and 48-55 its fill level
     //    
     //    the entities are a 128 bit value, with the first 32 being the texture id (similar to tiles),
     //      the next 16 being rotation ( f16 of [0, pi) ), and 16 for x + 16 for y (screen space offsets, uint), with 44 bits for applicable data, and the
@@ -109,6 +131,7 @@ kernel void ComputeShader (
         if (x_coord < tile_map_width && y_coord < tile_map_height) {
             uint tile_index = x_coord + y_coord * tile_map_width;
             uint offset = (uint(px_zoomed) % 8) + (uint(py_zoomed) % 8) * 8;
+            color = ToColor(row_backgrounds[y_coord]);
             
             // interpolating the light (if defined)
             #define INTERPOLATE_LIGHT
@@ -145,15 +168,93 @@ kernel void ComputeShader (
             
             // going through the 3 layers ( the first is the forground )
             for (int i = 2; i >= 0; i--) {
-                uint tile_value = tile_map[tile_index * 4 + i];
-                // casting tile_value to uint from ulong should just cut off the extra bits of info, which isn't necessary here at least for now
-                uint tile_text_index = tile_value * 64 + offset;
+                ulong tile_word = tile_map[tile_index * 4 + i];
+                // casting tile_value to uint from ulong should just cut off the extra bits of info
+                uint tile_value = tile_word;
+                // the upper 32 bits are the tile data (layout is in tile_data.rs)
+                uint tile_data = tile_word >> 32;
+                uint orientation = (tile_data >> 8) & 0x3;
+                uint pixel_x = uint(px_zoomed) % 8;
+                uint pixel_y = uint(py_zoomed) % 8;
+                if (orientation & 0x1) pixel_x = 7 - pixel_x;
+                if (orientation & 0x2) pixel_y = 7 - pixel_y;
+                uint tile_text_index = tile_value * 64 + pixel_x + pixel_y * 8;
                 float alpha = tile_textures[tile_text_index].w * 0.00392156862;
+
+                // cutting out the empty part of hammered tiles (half blocks and slopes; the order matches TileShape in collision.rs)
+                if (i == 0) {
+                    uint shape = (tile_data >> 14) & 0x7;
+                    uint screen_x = uint(px_zoomed) % 8;
+                    uint screen_y = uint(py_zoomed) % 8;
+                    if ((shape == 1 && screen_y < 4) ||
+                        (shape == 2 && screen_x + screen_y < 7) ||
+                        (shape == 3 && screen_x > screen_y) ||
+                        (shape == 4 && screen_y > screen_x) ||
+                        (shape == 5 && screen_x + screen_y > 7)) {
+                        alpha = 0.0;
+                    }
+                }
                 color = float3(
                     lerp(color.x, tile_textures[tile_text_index].x * 0.00392156862 * light_color.x, alpha),
                     lerp(color.y, tile_textures[tile_text_index].y * 0.00392156862 * light_color.y, alpha),
                     lerp(color.z, tile_textures[tile_text_index].z * 0.00392156862 * light_color.z, alpha)
                 );
+
+                // cracks on tiles being mined (the break damage is the lowest 8 bits)
+                uint break_damage = tile_data & 0xFF;
+                if (i == 0 && break_damage > 0 && alpha > 0.0) {
+                    uint crack_stage = break_damage * 4 / 256 + 1;
+                    uint crack = CRACK_PATTERN[uint(px_zoomed) % 8 + (uint(py_zoomed) % 8) * 8];
+                    if (crack > 0 && crack <= crack_stage) {
+                        color *= 0.35;
+                    }
+                }
+            }
+
+            // drawing any liquid over the tile (packed into the upper bits of the lighting value)
+            ulong liquid_value = tile_map[tile_index * 4 + 3];
+            uint liquid_texture = (liquid_value >> 32) & 0xFFFF;
+            uint liquid_level   = (liquid_value >> 48) & 0xFF;
+            if (liquid_level > 0) {
+                // only filling the bottom rows of the tile based on how full it is
+                uint filled_rows = (liquid_level * 8 + 254) / 255;
+                if ((uint(py_zoomed) % 8) >= 8 - filled_rows) {
+                    uint liquid_text_index = liquid_texture * 64 + offset;
+                    float alpha = tile_textures[liquid_text_index].w * 0.00392156862;
+                    color = float3(
+                        lerp(color.x, tile_textures[liquid_text_index].x * 0.00392156862 * light_color.x, alpha),
+                        lerp(color.y, tile_textures[liquid_text_index].y * 0.00392156862 * light_color.y, alpha),
+                        lerp(color.z, tile_textures[liquid_text_index].z * 0.00392156862 * light_color.z, alpha)
+                    );
+                }
+            }
+
+            // the wire overlay (only while holding a wrench); each color runs along its own lane so overlapping wires stay readable
+            //    a wire is drawn as a dot in the tile, with a line out to every neighbour carrying the same color
+            if (camera_position.w > 0.5) {
+                uint wires = (uint(tile_map[tile_index * 4] >> 32) >> 10) & 0xF;
+                if (wires != 0) {
+                    uint pixel_x = uint(px_zoomed) % 8;
+                    uint pixel_y = uint(py_zoomed) % 8;
+                    uint wires_left  = x_coord > 0 ? (uint(tile_map[(tile_index - 1) * 4] >> 32) >> 10) & 0xF : 0;
+                    uint wires_right = x_coord + 1 < tile_map_width  ? (uint(tile_map[(tile_index + 1) * 4] >> 32) >> 10) & 0xF : 0;
+                    uint wires_up    = y_coord > 0 ? (uint(tile_map[(tile_index - tile_map_width) * 4] >> 32) >> 10) & 0xF : 0;
+                    uint wires_down  = y_coord + 1 < tile_map_height ? (uint(tile_map[(tile_index + tile_map_width) * 4] >> 32) >> 10) & 0xF : 0;
+                    for (uint c = 0; c < 4; c++) {
+                        uint bit = 1 << c;
+                        if (!(wires & bit)) continue;
+                        uint lane = 2 + c;
+                        bool on_wire = (pixel_x == lane && pixel_y == lane) ||
+                            (pixel_y == lane && pixel_x < lane && (wires_left  & bit)) ||
+                            (pixel_y == lane && pixel_x > lane && (wires_right & bit)) ||
+                            (pixel_x == lane && pixel_y < lane && (wires_up    & bit)) ||
+                            (pixel_x == lane && pixel_y > lane && (wires_down  & bit));
+                        if (on_wire) {
+                            // not lit, so the wires can still be traced through dark caves
+                            color = lerp_f3(color, WIRE_COLORS[c], 0.85);
+                        }
+                    }
+                }
             }
         }
     }
@@ -0,0 +1,234 @@
+/*
+  Generates a world without launching the game and exports it as a png map (mostly for tuning the world generation)
+
+  cargo run --bin worldgen -- [options]
+    --seed <number>               the world seed (defaults to the same seed new worlds currently use)
+    --size <width>x<height>       the size of the map in tiles (defaults to the dimension's normal size)
+    --dimension <name>            overworld or sky_islands
+    --out <path.png>              where the map is written (the biom overlay goes next to it as <path>_bioms.png)
+    --textured                    draws each tile with its 4x4 mini-map icon rather than a flat color
+    --save <world name>           also saves the world into world_saves/ so it can be opened from the menu (overworld only)
+    --force                       lets --save replace a world that already has the name
+*/
+
+use voxel_game::core::{self, rendering::ui_state::menues::GameStateManager};
+use voxel_game::game_manager::game::{DEFAULT_SEED, Game};
+use voxel_game::game_manager::world::tile_map::{Dimension, TileMap};
+use voxel_game::game_manager::world::tile_map::liquids::get_liquid_info;
+use voxel_game::game_manager::world::tile_map::registry::get_tile_properties;
+use voxel_game::game_manager::world::world_gen::{Biom, WorldGenerator};
+use voxel_game::game_manager::world::world_info::WorldInfo;
+use voxel_game::logging::logging::{Logging, Logs};
+use voxel_game::textures::textures::get_texture_atlas;
+
+static MAX_MAP_TEXTURES: usize = u16::MAX as usize;
+
+/// The color each biom is tinted in the overlay
+static BIOM_COLORS: &[(Biom, [u8; 3])] = &[
+    (Biom::Forest, [40, 200, 60]),
+    (Biom::Desert, [240, 210, 80]),
+    (Biom::Jungle, [10, 120, 40]),
+    (Biom::Tundra, [170, 230, 255]),
+];
+
+struct Options {
+    seed: f32,
+    size: Option<(usize, usize)>,
+    dimension: Dimension,
+    out: String,
+    textured: bool,
+    save: Option<String>,
+    force: bool,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options {
+        seed: DEFAULT_SEED,
+        size: None,
+        dimension: Dimension::Overworld,
+        out: String::from("world_map.png"),
+        textured: false,
+        save: None,
+        force: false,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("Missing a value for {}", arg));
+        match arg.as_str() {
+            "--seed" => options.seed = value()?.parse().map_err(|e| format!("Invalid seed: {:?}", e))?,
+            "--size" => {
+                let size = value()?;
+                let (width, height) = size.split_once('x').ok_or_else(|| format!("Invalid size (expected <width>x<height>): {}", size))?;
+                options.size = Some((
+                    width.parse().map_err(|e| format!("Invalid width: {:?}", e))?,
+                    height.parse().map_err(|e| format!("Invalid height: {:?}", e))?,
+                ));
+            },
+            "--dimension" => {
+                let name = value()?;
+                options.dimension = *Dimension::ALL.iter()
+                    .find(|dimension| dimension.get_name() == name)
+                    .ok_or_else(|| format!("Unknown dimension: {}", name))?;
+            },
+            "--out" => options.out = value()?,
+            "--textured" => options.textured = true,
+            "--save" => options.save = Some(value()?),
+            "--force" => options.force = true,
+            _ => return Err(format!("Unknown option: {}", arg)),
+        }
+    }
+    if options.save.is_some() && options.dimension != Dimension::Overworld {
+        return Err(String::from("Only the overworld can be saved (new worlds always start there)"));
+    } Ok(options)
+}
+
+/// The color of the tile at the position, looking through empty tiles to the background wall and then the layer's background
+fn get_tile_color(tile_map: &TileMap, world_generator: &WorldGenerator, x: usize, y: usize) -> [u8; 3] {
+    let liquid = tile_map.get_liquid(x, y);
+    if let Some(info) = get_liquid_info(liquid.liquid) && !liquid.is_empty() {
+        return info.map_color;
+    }
+    let tile = tile_map.get_tile(x, y, 0);
+    if tile != 0 { return get_tile_properties(tile).map_color; }
+    let wall = tile_map.get_tile(x, y, 1);
+    if wall != 0 {
+        // darkened so the walls stand apart from the foreground
+        return get_tile_properties(wall).map_color.map(|channel| channel / 2);
+    }
+    world_generator.get_layer(y, tile_map.get_map_height()).background_color
+}
+
+/// Draws the whole map, either as a flat color per tile or with each tile's 4x4 icon
+fn render_map(tile_map: &TileMap, world_generator: &WorldGenerator, map_textures: Option<&Vec<[u32; 16]>>) -> image::RgbImage {
+    let scale = if map_textures.is_some() { 4 } else { 1 };
+    let mut image = image::RgbImage::new((tile_map.get_map_width() * scale) as u32, (tile_map.get_map_height() * scale) as u32);
+    for y in 0..tile_map.get_map_height() {
+        for x in 0..tile_map.get_map_width() {
+            let color = get_tile_color(tile_map, world_generator, x, y);
+            for pixel_y in 0..scale {
+                for pixel_x in 0..scale {
+                    let mut pixel = color;
+                    if let Some(map_textures) = map_textures {
+                        let liquid = tile_map.get_liquid(x, y);
+                        let texture_id = match get_liquid_info(liquid.liquid) {
+                            Some(info) if !liquid.is_empty() => info.texture,
+                            _ => tile_map.get_tile(x, y, 0),
+                        };
+                        // tiles without an icon (or the see through parts of one) keep the flat color
+                        if let Some(texture) = map_textures.get(texture_id as usize) {
+                            let texel = texture[pixel_x + pixel_y * 4];
+                            if texel >> 24 != 0 {
+                                pixel = [(texel & 0xFF) as u8, ((texel >> 8) & 0xFF) as u8, ((texel >> 16) & 0xFF) as u8];
+                            }
+                        }
+                    }
+                    image.put_pixel((x * scale + pixel_x) as u32, (y * scale + pixel_y) as u32, image::Rgb(pixel));
+                }
+            }
+        }
+    } image
+}
+
+/// Tints every column of the map by the biom it's in
+fn render_biom_overlay(map: &image::RgbImage, world_generator: &WorldGenerator, scale: u32) -> image::RgbImage {
+    let mut overlay = map.clone();
+    for x in 0..map.width() {
+        let biom = world_generator.get_biom_at((x / scale) as usize);
+        let tint = BIOM_COLORS.iter().find(|(color_biom, _)| *color_biom == biom).map(|(_, color)| *color).unwrap_or([255, 255, 255]);
+        for y in 0..map.height() {
+            let pixel = overlay.get_pixel_mut(x, y);
+            for (channel, tint_channel) in pixel.0.iter_mut().zip(tint) {
+                *channel = ((*channel as u16 + tint_channel as u16) / 2) as u8;
+            }
+        }
+    } overlay
+}
+
+fn run(options: Options, logs: &mut Logs) -> Result<(), String> {
+    // checked before generating anything, so a typo doesn't cost a whole generation to find out
+    if let Some(name) = &options.save {
+        let world_path = format!("world_saves/{}", name);
+        if std::path::Path::new(&world_path).exists() && !options.force {
+            return Err(format!("{} already exists (pass --force to replace it)", world_path));
+        }
+    }
+    let size = options.size.unwrap_or(options.dimension.get_map_size());
+    let start = std::time::Instant::now();
+    println!("Generating the {} ({}x{}) with seed {}...", options.dimension.get_name(), size.0, size.1, options.seed);
+
+    // saving needs a whole game (player, entities, etc...), otherwise just the tile map is enough
+    let world_generator = WorldGenerator::new(options.seed, options.dimension);
+    let game = match &options.save {
+        Some(_) => {
+            let font_atlas = GameStateManager::load_font_atlas(logs)?;
+            Some(Game::generate(logs, font_atlas, options.seed, size)?)
+        },
+        None => None,
+    };
+    let generated_map;
+    let tile_map = match &game {
+        Some(game) => game.get_tilemap_manager_ref().get_current_map_ref(Dimension::Overworld)
+            .ok_or_else(|| String::from("The generated world is missing its overworld"))?,
+        None => {
+            generated_map = TileMap::new(size.0, size.1, Some(&world_generator), logs)?;
+            &generated_map
+        },
+    };
+    println!("Generated in {:.2} seconds", start.elapsed().as_secs_f64());
+    println!("Spawn point: {:?}", tile_map.get_spawn_point());
+
+    let map_textures = match options.textured {
+        true => {
+            let mut total_textures_loaded = 0;
+            Some(get_texture_atlas::<MAX_MAP_TEXTURES, 16>(
+                "textures/map_tiles/", (4, 4), vec![Default::default(); MAX_MAP_TEXTURES], &mut total_textures_loaded
+            ).map_err(|e| format!("Failed to load the map tile textures: {:?}", e))?)
+        },
+        false => None,
+    };
+    let map = render_map(tile_map, &world_generator, map_textures.as_ref());
+    map.save(&options.out).map_err(|e| format!("Failed to write {}: {:?}", options.out, e))?;
+    println!("Wrote the map to {}", options.out);
+
+    let overlay_path = match options.out.strip_suffix(".png") {
+        Some(stem) => format!("{}_bioms.png", stem),
+        None => format!("{}_bioms.png", options.out),
+    };
+    let scale = if options.textured { 4 } else { 1 };
+    render_biom_overlay(&map, &world_generator, scale).save(&overlay_path).map_err(|e| format!("Failed to write {}: {:?}", overlay_path, e))?;
+    println!("Wrote the biom overlay to {}", overlay_path);
+    for (biom, color) in BIOM_COLORS {
+        println!("    {:?}: rgb({}, {}, {})", biom, color[0], color[1], color[2]);
+    }
+
+    if let (Some(game), Some(name)) = (&game, &options.save) {
+        let world_path = format!("world_saves/{}", name);
+        // replacing the world outright, rather than leaving the old world's files mixed in with the new one
+        if options.force && std::path::Path::new(&world_path).exists() {
+            std::fs::remove_dir_all(&world_path).map_err(|e| format!("Failed to remove the old world: {:?}", e))?;
+        }
+        Game::create_save_directories(&world_path, core::GAME_VERSION).map_err(|e| format!("Failed to create the world directory: {:?}", e))?;
+        game.save(&world_path, core::GAME_VERSION, logs).map_err(|e| format!("Failed to save the world: {:?}", e))?;
+        WorldInfo::new(game, core::GAME_VERSION).save(&world_path).map_err(|e| format!("Failed to save the world info: {:?}", e))?;
+        println!("Saved the world to {}", world_path);
+    } Ok(())
+}
+
+fn main() {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        },
+    };
+    let (concluded_sender, concluded_receiver) = crossbeam::channel::bounded(1);
+    let mut logs = Logs::new(concluded_receiver, Logging::ErrorOnly);
+    let result = run(options, &mut logs);
+    let _ = concluded_sender.send(true);  // marks the run as having ended cleanly for the logs
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        drop(logs);
+        std::process::exit(1);
+    }
+}
@@ -17,7 +17,7 @@ use crate::shaders::*;
 pub(crate) mod timer;
 use timer::Timer;
 
-pub(crate) mod rendering;
+pub mod rendering;
 
 /// The starting width of the application window
 static WINDOW_START_WIDTH: u32 = 1200;
@@ -29,7 +29,8 @@ static MINIMUM_WINDOW_WIDTH: u32 = 1200;
 /// The minimum size of the window (mostly so ui doesn't get completely messed up)
 static MINIMUM_WINDOW_HEIGHT: u32 = 750;
 
-static GAME_VERSION: &'static str = "0.0.1-alpha";
+/// Saves are kept in a folder per version, so this is bumped whenever the layout or encoding of the save files changes
+pub static GAME_VERSION: &'static str = "0.0.19-alpha";
 
 pub fn start(logs: &mut Logs) -> Result<(), String> {
     // todo! temporary just to handle the game for now, no menues or anything (umm..... it does have some ui now... but we'll go with that)
@@ -163,9 +164,11 @@ pub fn start(logs: &mut Logs) -> Result<(), String> {
 
             let mut entities: Vec<Vec<(u32, u16, i16, i16, u16, u32)>> = vec![];
             game_manager.update_entities(&mut entities, window_size);
-            if entities.len() >= MAX_ENTITIES {
+            // counting every sprite being drawn (the player, entities and weather), not just the groups they come in
+            let entity_count = entities.iter().map(|group| group.len()).sum::<usize>();
+            if entity_count >= MAX_ENTITIES {
                 logs.push(Log {
-                    message: format!("[Memory Warning] Entities surpassed maximum GPU buffer size; length of {}", entities.len()),
+                    message: format!("[Memory Warning] Entities surpassed maximum GPU buffer size; length of {}", entity_count),
                     level: LoggingError::Warning
                 }, 14, logger::LogType::Memory);
             }
@@ -287,7 +290,7 @@ pub fn start(logs: &mut Logs) -> Result<(), String> {
             logs.push(Log {
                 message: format!("[Performance Warning] Frame took too long ( > {:.2}ms  i.e.  < {}fps ).\n{}\n * entity count: {}", logger::PERFORMANCE_LOG_THRESHOLD * 1000.0, 1. / logger::PERFORMANCE_LOG_THRESHOLD, text, {
                     match &game_manager.game {
-                        Some(game) => game.entity_manager.get_entity_count(),
+                        Some(game) => game.get_entity_manager().get_entity_count(),
                         None => 0,
                     }
                 }),
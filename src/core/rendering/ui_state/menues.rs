@@ -2,7 +2,12 @@ use crate::core::{event_handling::event_handler::{self, ButtonState, EventHandle
 use crate::game_manager::entities::player::font_rendering::render_font_unifont_colored;
 use crate::logging::logging::{Log, LogType, LoggingError, Logs};
 use crate::shaders::shader_handler::{self, ShaderError, Tuple};
-use crate::game_manager::game::{Game, GameError};
+use crate::shaders::shader_loader::MAX_VISIBLE_ROWS;
+use crate::game_manager::game::{DEFAULT_SEED, Game, GameError, Severity};
+use crate::game_manager::world::tile_map::Dimension;
+use crate::game_manager::world::world_gen::background::GenerationJob;
+use crate::game_manager::entities::player::player::GameMode;
+use crate::game_manager::world::world_info::WorldInfo;
 use crate::textures::textures::get_texture_atlas;
 use metal::{MTLSize, NSUInteger};
 use std::{fs::ReadDir, rc::Rc};
@@ -73,29 +78,36 @@ impl CreatorUi {
 }
 
 pub struct GameStateManager {
-    saved_worlds: Vec<String>,
+    saved_worlds: Vec<(String, Option<WorldInfo>)>,  // sorted with the most recently played first (worlds without any info go last)
     font_atlas: Rc<Vec<[u32; 256]>>,
     pub game: Option<Game>,
     game_world_name: Option<String>,
     creator_popup: Option<CreatorUi>,
+    new_world_mode: GameMode,  // the game mode picked in the creator (it can't be changed once the world exists)
+    load_error: Option<String>,  // why the last world clicked couldn't be opened (shown under the world list)
+    generation: Option<(GenerationJob, Option<String>, GameMode)>,  // a new world being generated in the background (with the name it'll be saved under and its mode)
+    session_start: Option<std::time::Instant>,  // when the playtime since the last save started counting
 }
 
 impl GameStateManager {
+    /// Loads the font used for all the cpu-side text rendering
+    pub fn load_font_atlas(logs: &mut Logs) -> Result<Rc<Vec<[u32; 256]>>, GameError> {
+        let mut total_textures_loaded = 0;
+        let atlas = get_texture_atlas::<MAX_FONT_CHARACTERS, 256>(
+            "textures/fonts/user_default/", (16, 16), vec![[0u32; 256]; MAX_FONT_CHARACTERS], &mut total_textures_loaded
+        ).map_err(|e| GameError {
+            message: format!("[Ui-Manager Game Error] Failed to load textures: {:?}", e),
+            severity: crate::game_manager::game::Severity::Fatal
+        })?;
+        logs.push(Log {
+            message: format!("Loaded {} font characters for cpu-side rendering.", total_textures_loaded - 1),
+            level: crate::logging::logging::LoggingError::Info
+        }, 20, LogType::Information);
+        Ok(Rc::new(atlas))
+    }
+
     pub fn new(logs: &mut Logs) -> Result<Self, GameError> {
-        let font_atlas = Rc::new({
-            let mut total_textures_loaded = 0;
-            let atlas = get_texture_atlas::<MAX_FONT_CHARACTERS, 256>(
-                "textures/fonts/user_default/", (16, 16), vec![[0u32; 256]; MAX_FONT_CHARACTERS], &mut total_textures_loaded
-            ).map_err(|e| GameError {
-                message: format!("[Ui-Manager Game Error] Failed to load textures: {:?}", e),
-                severity: crate::game_manager::game::Severity::Fatal
-            })?;
-            logs.push(Log {
-                message: format!("Loaded {} font characters for cpu-side rendering.", total_textures_loaded - 1),
-                level: crate::logging::logging::LoggingError::Info
-            }, 20, LogType::Information);
-            atlas
-        });
+        let font_atlas = Self::load_font_atlas(logs)?;
         // going through the game save file path to look for saved worlds
         let worlds_dir: Result<ReadDir, std::io::Error> = match std::fs::read_dir("world_saves/") {
             Ok(dir) => Ok(dir),
@@ -112,24 +124,57 @@ impl GameStateManager {
             severity: crate::game_manager::game::Severity::Fatal,
         })?;
         let mut saved_worlds = vec![];
-        for world in worlds_dir {
-            if let Ok(entry) = world {
-                if let Ok(file_type) = entry.file_type() {
-                    if file_type.is_dir() {
-                        saved_worlds.push(entry.file_name().to_string_lossy().into_owned());
-                    }
-                }
+        for entry in worlds_dir.flatten() {
+            if let Ok(file_type) = entry.file_type() && file_type.is_dir() {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let world_info = WorldInfo::load(&format!("world_saves/{}", name));
+                saved_worlds.push((name, world_info));
             }
         }
+        Self::sort_worlds(&mut saved_worlds);
         Ok(Self {
             saved_worlds,
             font_atlas,
             game: None,
             game_world_name: None,
             creator_popup: None,
+            new_world_mode: GameMode::Survival,
+            load_error: None,
+            generation: None,
+            session_start: None,
         })
     }
 
+    fn sort_worlds(saved_worlds: &mut [(String, Option<WorldInfo>)]) {
+        saved_worlds.sort_by_key(|(_, world_info)| std::cmp::Reverse(world_info.as_ref().map(|world_info| world_info.last_played)));
+    }
+
+    /// Saves the game along with its world info (adding on the playtime since the last save)
+    fn save_game(&mut self, game_version: &str, logs: &mut Logs) -> Result<(), std::io::Error> {
+        let (game, name) = match (self.game.as_ref(), self.game_world_name.as_ref()) {
+            (Some(game), Some(name)) => (game, name),
+            _ => return Ok(()),
+        };
+        let world_path = format!("world_saves/{}", name);
+        game.save(&world_path, game_version, logs)?;
+
+        let session_length = self.session_start.replace(std::time::Instant::now())
+            .map(|start| start.elapsed().as_secs_f64())
+            .unwrap_or(0.0);
+        // worlds from before the info file existed get a fresh one (so their creation date is when they were first saved with it)
+        let mut world_info = WorldInfo::load(&world_path).unwrap_or_else(|| WorldInfo::new(game, game_version));
+        world_info.update(game, game_version, session_length);
+        world_info.save(&world_path)?;
+
+        // keeping the world list up to date for when the menu is shown again
+        match self.saved_worlds.iter_mut().find(|(world_name, _)| world_name == name) {
+            Some(saved_world) => saved_world.1 = Some(world_info),
+            None => self.saved_worlds.push((name.clone(), Some(world_info))),
+        }
+        Self::sort_worlds(&mut self.saved_worlds);
+        Ok(())
+    }
+
     pub fn start_game(&mut self, world_name: Option<String>, logs: &mut Logs, game_version: &str) -> Result<(), GameError> {
         if let Some(world_name) = world_name {
             let game = Game::from_save(logs, &format!("world_saves/{}", world_name), game_version, self.font_atlas.clone())?;
@@ -139,6 +184,7 @@ impl GameStateManager {
             self.game = Some(Game::new(logs, self.font_atlas.clone())?);
             self.game_world_name = None;
         }
+        self.session_start = Some(std::time::Instant::now());
         Ok(())
     }
 
@@ -162,7 +208,7 @@ impl GameStateManager {
             255u32 | (255u32 << 8) | (255u32 << 16)
         );
         //rendering the currently available worlds
-        for (index, save_name) in self.saved_worlds.iter().enumerate() {
+        for (index, (save_name, world_info)) in self.saved_worlds.iter().enumerate() {
             render_font_unifont_colored::<16, 256, 8> (
                 &*self.font_atlas,
                 pixels,
@@ -172,15 +218,154 @@ impl GameStateManager {
                 save_name,
                 255u32 | (255u32 << 8) | (255u32 << 16)
             );
+            // the details go off to the side in a dimmer color
+            let summary = match world_info {
+                Some(world_info) => world_info.get_summary(),
+                None => String::from("no world info"),
+            };
+            // cut off at the edge of the window (the font rendering doesn't clip)
+            let max_characters = (window_size.0 as usize).saturating_sub(275) / 8;
+            let summary = summary.chars().take(max_characters).collect::<String>();
+            render_font_unifont_colored::<16, 256, 8> (
+                &*self.font_atlas,
+                pixels,
+                (250, 55 + index * 20),
+                window_size,
+                pitch,
+                &summary,
+                160u32 | (160u32 << 8) | (160u32 << 16)
+            );
+        }
+        
+        if let Some(load_error) = self.load_error.as_ref() {
+            let max_characters = (window_size.0 as usize).saturating_sub(75) / 8;
+            render_font_unifont_colored::<16, 256, 8> (
+                &*self.font_atlas,
+                pixels,
+                (50, window_size.1 as usize - 40),
+                window_size,
+                pitch,
+                &load_error.chars().take(max_characters).collect::<String>(),
+                255u32 | (96u32 << 8) | (96u32 << 16)
+            );
         }
         
         if let Some(creator) = self.creator_popup.as_ref() {
             creator.ui_element.render(pixels, window_size, pitch, &mut self.game_world_name)?;
+            render_font_unifont_colored::<16, 256, 8> (
+                &*self.font_atlas,
+                pixels,
+                (150, 175),
+                window_size,
+                pitch,
+                &format!("Game Mode: {:?} (click to change)", self.new_world_mode),
+                255u32 | (255u32 << 8) | (255u32 << 16)
+            );
+        }
+        if let Some((job, name, _)) = self.generation.as_ref() {
+            self.render_generation_progress(pixels, window_size, pitch, job, name.as_deref().unwrap_or("New World"));
         }
         Ok(())
     }
 
+    /// Draws a panel with a progress bar over the menu while a new world generates
+    fn render_generation_progress(&self, pixels: &mut [u8], window_size: (u32, u32), pitch: usize, job: &GenerationJob, name: &str) {
+        let pos = (125, 125);
+        let size = (window_size.0 as usize - 250, 120);
+        let bar_width = size.0 - 50;
+        let filled = (bar_width as f32 * job.get_total_progress()) as usize;
+        for x in pos.0..=pos.0 + size.0 {
+            for y in pos.1..=pos.1 + size.1 {
+                let on_edge = x == pos.0 || x == pos.0 + size.0 || y == pos.1 || y == pos.1 + size.1;
+                // the bar sits in the middle of the panel
+                let in_bar = x >= pos.0 + 25 && x < pos.0 + 25 + bar_width && y >= pos.1 + 55 && y < pos.1 + 75;
+                let color = if on_edge { 225 }
+                    else if in_bar && x < pos.0 + 25 + filled { 200 }
+                    else if in_bar { 40 }
+                    else { 75 };
+                pixels[x * 3 + y * pitch    ] = color;
+                pixels[x * 3 + y * pitch + 1] = color;
+                pixels[x * 3 + y * pitch + 2] = color;
+            }
+        }
+        render_font_unifont_colored::<16, 256, 8> (
+            &*self.font_atlas,
+            pixels,
+            (pos.0 + 25, pos.1 + 25),
+            window_size,
+            pitch,
+            &format!("Generating {}: {} ({}%)", name, job.get_pass().get_name(), (job.get_total_progress() * 100.0) as u32),
+            255u32 | (255u32 << 8) | (255u32 << 16)
+        );
+        render_font_unifont_colored::<16, 256, 8> (
+            &*self.font_atlas,
+            pixels,
+            (pos.0 + size.0 - 115, pos.1 + size.1 - 25),
+            window_size,
+            pitch,
+            "Cancel (esc)",
+            255u32 | (255u32 << 8) | (255u32 << 16)
+        );
+    }
+
+    /// Checks in on the world being generated, starting the game once it's done (or dropping it if cancelled)
+    fn update_generation(&mut self, event_handler: &EventHandler, logs: &mut Logs, window_size: (u32, u32), game_version: &str) -> Result<(), GameError> {
+        let (job, _, _) = match self.generation.as_mut() {
+            Some(generation) => generation,
+            None => return Ok(()),
+        };
+        let mouse_pos = event_handler.mouse.position;
+        let clicked_cancel = matches!(event_handler.mouse.left, ButtonState::Released) &&
+            mouse_pos.0 >= window_size.0 - 125 - 115 && mouse_pos.0 < window_size.0 - 125 &&
+            mouse_pos.1 >= 125 + 120 - 25 && mouse_pos.1 < 125 + 120;
+        if clicked_cancel || event_handler.keys_released.contains(&sdl2::keyboard::Keycode::ESCAPE) {
+            // the thread stops at its next progress report, and its result is dropped with the channel
+            job.cancel();
+            self.generation = None;
+            logs.push(Log {
+                message: String::from("Cancelled generating a new world"),
+                level: LoggingError::Info,
+            }, 27, LogType::Information);
+            return Ok(());
+        }
+
+        let result = match job.poll() {
+            Some(result) => result,
+            None => return Ok(()),
+        };
+        let (job, name, game_mode) = match self.generation.take() {
+            Some(generation) => generation,
+            None => return Ok(()),
+        };
+        let tile_map = result?;
+        // the world's folder is only made once there's a world to put in it (so cancelling doesn't leave an empty one behind)
+        if let Some(name) = name.as_ref() {
+            Game::create_save_directories(&format!("world_saves/{}", name), game_version).map_err(|e| GameError {
+                message: format!("[World Creation Error] Failed to create world directory: {:?}", e),
+                severity: crate::game_manager::game::Severity::Fatal
+            })?;
+        }
+        logs.push(Log {
+            message: format!("Generated a new world with seed {}", job.seed),
+            level: LoggingError::Info,
+        }, 26, LogType::Information);
+        let mut game = Game::from_generated(logs, self.font_atlas.clone(), job.seed, tile_map)?;
+        game.player.game_mode = game_mode;
+        self.game = Some(game);
+        self.game_world_name = name;
+        self.session_start = Some(std::time::Instant::now());
+        // saving the world (for one, this makes sure everything is correctly setup before the user actually gets invested into the world)
+        self.save_game(game_version, logs).map_err(|e| GameError {
+            message: format!("[World Creation Error] Failed to save the new world: {:?}", e),
+            severity: crate::game_manager::game::Severity::Fatal
+        })
+    }
+
     fn handle_menue_events(&mut self, event_handler: &mut EventHandler, _timer: &mut Timer, logs: &mut Logs, window_size: (u32, u32), game_version: &str) -> Result<(), GameError> {
+        // the rest of the menu is blocked out until the new world is ready
+        if self.generation.is_some() {
+            return self.update_generation(event_handler, logs, window_size, game_version);
+        }
         if matches!(event_handler.mouse.left, ButtonState::Released) {
             // checking what was clicked
             let mouse_pos = event_handler.mouse.position;
@@ -188,36 +373,36 @@ impl GameStateManager {
                 // clicked a world (unless the index is beyond the total count of worlds)
                 let index = (mouse_pos.1 - 55) as usize / 20;
                 if index < self.saved_worlds.len() {
-                    // opening the world
-                    self.start_game(Some(self.saved_worlds[index].clone()), logs, game_version)?;
+                    // opening the world (staying on the menu if it can't be, such as a save from an older version)
+                    match self.start_game(Some(self.saved_worlds[index].0.clone()), logs, game_version) {
+                        Err(error) if error.severity != Severity::Fatal => {
+                            logs.push(Log {
+                                message: error.message.clone(),
+                                level: LoggingError::Error,
+                            }, 31, LogType::Error);
+                            self.load_error = Some(error.message);
+                        },
+                        result => {
+                            result?;
+                            self.load_error = None;
+                        },
+                    }
                 }
             } else if self.creator_popup.is_none() && mouse_pos.0 >= window_size.0 - 150 && mouse_pos.1 < 45 && mouse_pos.1 > 20 {
                 self.creator_popup = Some(CreatorUi::new(window_size, self.font_atlas.clone()));
+                self.new_world_mode = GameMode::Survival;
+                self.load_error = None;
+            } else if self.creator_popup.is_some() && mouse_pos.0 >= 150 && mouse_pos.0 < window_size.0 - 125 && mouse_pos.1 >= 175 && mouse_pos.1 < 195 {
+                self.new_world_mode = match self.new_world_mode {
+                    GameMode::Survival => GameMode::Builder,
+                    GameMode::Builder => GameMode::Survival,
+                };
             } else if self.creator_popup.is_some() && mouse_pos.0 >= window_size.0 - 125 - 115 && mouse_pos.1 >= 500 - 25 + 125 && mouse_pos.0 < window_size.0 - 125 && mouse_pos.1 < 500 + 125 {
                 self.creator_popup = None;
                 let name = self.game_world_name.take();
-                if let Some(name) = name.as_ref() {
-                    // creating the directory so it doesn't crash on save
-                    std::fs::create_dir_all(&format!("world_saves/{}/game_version_{}/world_save/entities", name, game_version)).map_err(|e| GameError {
-                        message: format!("[World Creation Error] Failed to create world directory: {:?}", e),
-                        severity: crate::game_manager::game::Severity::Fatal
-                    })?;
-                    std::fs::create_dir_all(&format!("world_saves/{}/game_version_{}/player", name, game_version)).map_err(|e| GameError {
-                        message: format!("[World Creation Error] Failed to create world directory: {:?}", e),
-                        severity: crate::game_manager::game::Severity::Fatal
-                    })?;
-                }
-                self.start_game(None, logs, game_version)?;
-                self.game_world_name = name;
-                // saving the world (for one, this makes sure everything is correctly setup before the user actually gets invested into the world)
-                if let Some(game) = self.game.as_mut() {
-                    if let Some(name) = self.game_world_name.as_ref() {
-                        game.save(&format!("world_saves/{}", name), game_version, logs).map_err(|e| GameError {
-                            message: format!("[World Creation Error] Failed to save the new world: {:?}", e),
-                            severity: crate::game_manager::game::Severity::Fatal
-                        })?;
-                    }
-                }
+                // the world is generated on its own thread so the menu can show the progress (see update_generation)
+                let job = GenerationJob::start(DEFAULT_SEED, Dimension::Overworld, Dimension::Overworld.get_map_size(), logs)?;
+                self.generation = Some((job, name, self.new_world_mode));
             }
         }
         if self.creator_popup.is_some() {
@@ -281,20 +466,28 @@ impl GameStateManager {
     pub fn update_entities(&mut self, entities: &mut Vec<Vec<(u32, u16, i16, i16, u16, u32)>>, window_size: (u32, u32)) {
         if let Some(game) = self.game.as_mut() {
             entities.push(game.player.get_model());
-            entities.push(game.entity_manager.get_render(&game.player.camera, window_size));
+            entities.push(game.get_entity_manager().get_render(&game.player.camera, window_size));
+            entities.push(game.get_precipitation_render(window_size));
         }
     }
 
     pub fn update_text_buffer(&mut self, text_buffer: &mut Vec<Tuple<u128, [u8; 32]>>) {
         if let Some(game) = self.game.as_mut() {
+            let dimension = game.player.dimension;
             text_buffer.push({
+                let weather = match game.get_weather_name() {
+                    Some(name) => format!(" {}", name),
+                    None => String::new(),
+                };
                 let input_text = format!(
-                    "({},{})",
+                    "({},{}){}{}",
                     (game.player.entity.position.0 / 8.0) as usize,
-                    match game.get_tilemap_manager().get_current_map(crate::game_manager::world::tile_map::Dimension::Overworld) {
+                    match game.get_tilemap_manager().get_current_map(dimension) {
                         Some(tile_map) => (tile_map.get_map_height() - 1) as usize,
                         None => 0,
-                    } - (game.player.entity.position.1 / 8.0) as usize
+                    } - (game.player.entity.position.1 / 8.0) as usize,
+                    if game.player.game_mode == GameMode::Builder { " builder" } else { "" },
+                    weather,
                 );
                 Tuple {
                     first:
@@ -311,7 +504,25 @@ impl GameStateManager {
                         text
                     },
                 }
-            })
+            });
+            if let Some((destination, job)) = game.get_travel_generation() {
+                let input_text = format!("Generating {} {}%", destination.get_name(), (job.get_total_progress() * 100.0) as u32);
+                text_buffer.push(Tuple {
+                    first:
+                        100u128 << 112 |  // x offset
+                        110u128 << 96  |  // y offset
+                        ((u16::MAX as u128) << 48) |  // color
+                        16u128  << 8   |  // font size
+                        input_text.len() as u128,  // buffer size
+                    second: {
+                        let mut text = [0u8; 32];
+                        for (i, char) in input_text.chars().take(32).enumerate() {
+                            text[i] = char as u8;
+                        }
+                        text
+                    },
+                });
+            }
         }
     }
 
@@ -346,16 +557,23 @@ impl GameStateManager {
 
             // getting the tilemap slice to render
             let camera = &game.player.camera.clone();  // the struct is only a couple 32 bit floats or whatever, so not too expensive to clone
-            match game.get_tilemap_manager().get_current_map(crate::game_manager::world::tile_map::Dimension::Overworld) {
-                Some(tile_map) => {
-                    let (map, offset_transform, visible_size) = tile_map.get_render_slice(
+            let sky_brightness = game.get_sky_brightness();
+            let dimension = game.player.dimension;
+            let wire_overlay = if game.player.is_showing_wires() { 1.0 } else { 0.0 };
+            match game.get_map_and_generator(dimension) {
+                (Some(tile_map), world_generator) => {
+                    let (map, offset_transform, visible_size, row_backgrounds) = tile_map.get_render_slice(
                         camera,
                         window_size,
+                        world_generator,
+                        sky_brightness,
                     );
                     shader.update_buffer_slice(8, &map)?;
+                    shader.update_buffer_slice(19, &row_backgrounds[0..row_backgrounds.len().min(MAX_VISIBLE_ROWS)])?;
                     shader.update_buffer(6, visible_size.0)?;
                     shader.update_buffer(7, visible_size.1)?;
-                    let transform = shader_handler::Float4::new(offset_transform.x, offset_transform.y, offset_transform.zoom, 0.0);
+                    // the last component turns on the wire overlay
+                    let transform = shader_handler::Float4::new(offset_transform.x, offset_transform.y, offset_transform.zoom, wire_overlay);
                     shader.update_buffer(9, transform)?;
                 },
                 _ => {
@@ -411,11 +629,6 @@ impl GameStateManager {
     }
 
     pub fn close_game_session(&mut self, game_version: &str, logs: &mut Logs) -> Result<(), String> {
-        if let Some(game) = self.game.as_mut() {
-            if let Some(name) = self.game_world_name.as_ref() {
-                game.save(&format!("world_saves/{}", name), game_version, logs).map_err(|e| format!("{:?}", e))?;
-            }
-        }
-        Ok(())
+        self.save_game(game_version, logs).map_err(|e| format!("{:?}", e))
     }
 }
@@ -0,0 +1,166 @@
+use crate::game_manager::entities::manager::ItemDrop;
+use crate::game_manager::entities::mobs::MobKind;
+use crate::game_manager::world::tile_map::entity_lights::{EntityLight, LightFalloff, LightHandle};
+
+/// An id handed out to every entity as it's spawned (never reused, so a stale id just finds nothing)
+#[derive(bincode::Encode, bincode::Decode, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct EntityId(pub(crate) u32);
+
+/// Where the entity is, in pixels (a tile's top left corner being (tile_x * 8, tile_y * 8))
+#[derive(bincode::Encode, bincode::Decode, Clone, Copy, Debug)]
+pub struct Transform {
+    pub position: (f32, f32),
+}
+
+impl Transform {
+    pub fn new(position: (f32, f32)) -> Self {
+        Transform { position }
+    }
+}
+
+/// Moves the entity each update, colliding it with the tiles
+#[derive(bincode::Encode, bincode::Decode, Clone, Copy, Debug)]
+pub struct PhysicsBody {
+    pub velocity: (f32, f32),
+    pub size: (f32, f32),    // the hitbox, starting at the entity's position
+    pub gravity: f32,        // pixels per second squared (0 for anything that flies)
+    pub max_fall_speed: f32,
+    pub drag: f32,           // how quickly the sideways speed dies off while on the ground
+    pub on_ground: bool,
+    pub hit_wall: bool,      // whether it walked into something last update (for the ai to turn around or jump)
+}
+
+impl PhysicsBody {
+    pub fn new(size: (f32, f32), gravity: f32) -> Self {
+        PhysicsBody {
+            velocity: (0.0, 0.0),
+            size,
+            gravity,
+            max_fall_speed: 500.0,
+            drag: 0.0,
+            on_ground: false,
+            hit_wall: false,
+        }
+    }
+
+    pub fn with_drag(mut self, drag: f32) -> Self {
+        self.drag = drag;
+        self
+    }
+}
+
+#[derive(bincode::Encode, bincode::Decode, Clone, Copy, Debug)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+    pub invulnerable_time: f64,  // the seconds left before it can be hurt again
+}
+
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Health { current: max, max, invulnerable_time: 0.0 }
+    }
+}
+
+/// What an entity's ai is trying to do
+#[derive(bincode::Encode, bincode::Decode, Clone, Copy, PartialEq, Debug)]
+pub enum Behaviour {
+    Pulled,  // drifts over to the player once they're close (drops being picked up)
+    Hop,     // rests on the ground between leaps (leaping at the player once they're close)
+    Chase,   // wanders until the player is close, then walks straight at them, jumping anything in the way
+    Fly,     // flutters about ignoring gravity, swooping at the player once they're close
+    Flee,    // wanders until the player is close, then bounds away from them
+}
+
+/// The state an ai's behaviour is currently in
+#[derive(bincode::Encode, bincode::Decode, Clone, Copy, PartialEq, Debug)]
+pub enum AiState {
+    Idle,
+    Wandering,
+    Chasing,
+    Fleeing,
+}
+
+#[derive(bincode::Encode, bincode::Decode, Clone, Copy, Debug)]
+pub struct Ai {
+    pub behaviour: Behaviour,
+    pub state: AiState,
+    pub speed: f32,
+    pub timer: f64,      // counts down to the next decision
+    pub direction: f32,  // -1 left, 1 right, 0 standing still
+}
+
+impl Ai {
+    pub fn new(behaviour: Behaviour, speed: f32) -> Self {
+        Ai { behaviour, state: AiState::Idle, speed, timer: 0.0, direction: 0.0 }
+    }
+}
+
+/// How the entity is drawn; bigger sprites are a grid of 8x8 entity textures, numbered left to right then top to bottom
+#[derive(bincode::Encode, bincode::Decode, Clone, Copy, Debug)]
+pub struct EntitySprite {
+    pub texture: u32,        // the top left cell's texture
+    pub cells: (u32, u32),   // how many 8x8 cells wide and tall it is
+    pub frames: u32,         // animation frames, each following straight on from the last one's cells
+    pub frame_time: f64,     // the seconds each frame is shown for
+    pub animation_time: f64,
+    pub depth: u32,
+}
+
+impl EntitySprite {
+    pub fn new(texture: u32, cells: (u32, u32)) -> Self {
+        EntitySprite { texture, cells, frames: 1, frame_time: 0.0, animation_time: 0.0, depth: 0 }
+    }
+
+    pub fn animated(mut self, frames: u32, frame_time: f64) -> Self {
+        self.frames = frames.max(1);
+        self.frame_time = frame_time;
+        self
+    }
+
+    /// The texture of the top left cell of the current frame
+    pub fn get_frame_texture(&self) -> u32 {
+        if self.frames <= 1 || self.frame_time <= 0.0 { return self.texture; }
+        let frame = (self.animation_time / self.frame_time) as u32 % self.frames;
+        self.texture + frame * self.cells.0 * self.cells.1
+    }
+}
+
+/// A light carried around by the entity (added to the current tile map the first time it's updated)
+#[derive(bincode::Encode, bincode::Decode, Clone, Copy, Debug)]
+pub struct LightEmitter {
+    pub color: (u8, u8, u8, f32),
+    pub radius: f32,
+    pub falloff: LightFalloff,
+    pub(crate) handle: Option<LightHandle>,
+}
+
+impl LightEmitter {
+    pub fn new(color: (u8, u8, u8, f32), radius: f32, falloff: LightFalloff) -> Self {
+        LightEmitter { color, radius, falloff, handle: None }
+    }
+
+    pub fn create_light(&self, position: (f32, f32)) -> EntityLight {
+        EntityLight::new(position, self.color, self.radius, self.falloff)
+    }
+}
+
+/// An item lying on the ground waiting to be picked up
+#[derive(bincode::Encode, bincode::Decode, Clone)]
+pub struct Pickup {
+    pub drop: ItemDrop,
+    pub lifetime: f64,  // the seconds left before it despawns
+}
+
+/// A gravity tile (like sand) that lost its support and is now falling
+#[derive(bincode::Encode, bincode::Decode, Clone, Copy, Debug)]
+pub struct FallingTile {
+    pub tile: u32,  // the tile placed once it lands
+}
+
+/// A creature roaming the world (what kind it is decides how it spawns and despawns)
+#[derive(bincode::Encode, bincode::Decode, Clone, Copy, Debug)]
+pub struct Mob {
+    pub kind: MobKind,
+    pub far_time: f64,  // how long it's been out past its despawn distance
+}
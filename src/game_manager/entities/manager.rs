@@ -1,44 +1,182 @@
-use crate::game_manager::entities::player::{items::Item, player::CameraTransform};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::game_manager::entities::components::{Ai, Behaviour, EntityId, EntitySprite, FallingTile, Health, LightEmitter, Mob, PhysicsBody, Pickup, Transform};
+use crate::game_manager::entities::player::items::{Item, ItemType};
+use crate::game_manager::world::tile_map::TileMap;
+use crate::game_manager::world::tile_map::entity_lights::{LightFalloff, LightHandle};
+use crate::game_manager::world::tile_map::registry::get_tile_properties;
+use crate::shaders::shader_loader::MAX_ENTITIES;
 
 static DEFAULT_ITEM_LIFETIME: f64 = 60.0 * 12.0;  // 12 minutes (should be fine)
+static DROP_GRAVITY: f32 = 600.0;
+static DROP_SIZE: f32 = 4.0;
+/// Drops burn up in a single hit of lava
+static DROP_HEALTH: f32 = 1.0;
+/// How fast drops drift over to the player once they're close enough to be pulled in
+static DROP_PULL_SPEED: f32 = 90.0;
+/// How close (in pixels) the player has to be to pick up a drop
+static PICKUP_RANGE: f32 = 10.0;
+/// Drops of light giving tiles (like torches) still glow a little while lying on the ground
+static DROP_LIGHT: (f32, f32) = (0.45, 20.0);  // (strength, radius)
+static FALLING_TILE_GRAVITY: f32 = 1300.0;
 
+/// Every entity in the world (other than the player); each one is just an id, with whatever components it has stored by that id
+/// The systems (in systems.rs) each run over the entities that have the components they care about
 #[derive(bincode::Encode, bincode::Decode, Clone)]
 pub struct EntityManager {
-    pub drops: Vec<(ItemDrop, u32, u32, f64)>,  // the f64 is the alive for duration
+    next_id: u32,
+    entities: BTreeSet<EntityId>,
+    pub transforms: BTreeMap<EntityId, Transform>,
+    pub bodies: BTreeMap<EntityId, PhysicsBody>,
+    pub healths: BTreeMap<EntityId, Health>,
+    pub ais: BTreeMap<EntityId, Ai>,
+    pub sprites: BTreeMap<EntityId, EntitySprite>,
+    pub lights: BTreeMap<EntityId, LightEmitter>,
+    pub pickups: BTreeMap<EntityId, Pickup>,
+    pub falling_tiles: BTreeMap<EntityId, FallingTile>,
+    pub mobs: BTreeMap<EntityId, Mob>,
+    pub(crate) spawn_timer: f64,  // the seconds until the next attempt at spawning a mob
+    pub(crate) removed_lights: Vec<LightHandle>,  // the lights of despawned entities (taken off the tile map on the next update)
 }
 
 impl EntityManager {
     pub fn new() -> Self {
         EntityManager {
-            drops: Vec::new(),
+            next_id: 0,
+            entities: BTreeSet::new(),
+            transforms: BTreeMap::new(),
+            bodies: BTreeMap::new(),
+            healths: BTreeMap::new(),
+            ais: BTreeMap::new(),
+            sprites: BTreeMap::new(),
+            lights: BTreeMap::new(),
+            pickups: BTreeMap::new(),
+            falling_tiles: BTreeMap::new(),
+            mobs: BTreeMap::new(),
+            spawn_timer: 0.0,
+            removed_lights: Vec::new(),
+        }
+    }
+
+    /// Creates a new entity with just a position (the other components get added onto it)
+    pub fn spawn(&mut self, position: (f32, f32)) -> EntityId {
+        let id = EntityId(self.next_id);
+        self.next_id = self.next_id.wrapping_add(1);
+        self.entities.insert(id);
+        self.transforms.insert(id, Transform::new(position));
+        id
+    }
+
+    /// Removes the entity along with all of its components
+    pub fn despawn(&mut self, id: EntityId) {
+        if !self.entities.remove(&id) { return; }
+        self.transforms.remove(&id);
+        self.bodies.remove(&id);
+        self.healths.remove(&id);
+        self.ais.remove(&id);
+        self.sprites.remove(&id);
+        if let Some(handle) = self.lights.remove(&id).and_then(|light| light.handle) {
+            self.removed_lights.push(handle);
         }
+        self.pickups.remove(&id);
+        self.falling_tiles.remove(&id);
+        self.mobs.remove(&id);
     }
-    
+
+    pub fn is_alive(&self, id: EntityId) -> bool {
+        self.entities.contains(&id)
+    }
+
+    /// Drops the item at the position, pulled in by the player once they're close
+    /// Past the entity limit the drop closest to despawning makes way for it (unless there aren't any drops to clear)
     pub fn new_drop(&mut self, drop: ItemDrop, pos_x: u32, pos_y: u32) {
-        self.drops.push((drop, pos_x, pos_y, DEFAULT_ITEM_LIFETIME));
-    }
-
-    pub fn get_render(&self, camera: &CameraTransform, screen_width: (u32, u32)) -> Vec<(u32, u16, i16, i16, u16, u32)> {
-        let mut render_data = vec![];
-        let edge_x = (screen_width.0 as f32) * 0.5 * camera.zoom;
-        let edge_y = (screen_width.1 as f32) * 0.5 * camera.zoom;
-
-        // the gpu pipeline actually does work a lot better, even if the algerithm is slower on paper
-        for drop in &self.drops {
-            match &drop.0 {
-                ItemDrop::Tile(tile_texture_id, _item) => {
-                    let position = (drop.1 as f32 - camera.x, drop.2 as f32 - camera.y);
-                    if position.0 < -edge_x - 8.0 || position.1 < -edge_y - 8.0 || position.0 > edge_x || position.1 > edge_y {
-                        continue;
-                    }
-                    render_data.push((*tile_texture_id as u32, 0, (position.0 * 100.0) as i16, (position.1 * 100.0) as i16, 0, 0));
-                },
+        if self.get_entity_count() >= MAX_ENTITIES {
+            let oldest = self.pickups.iter()
+                .min_by(|(_, a), (_, b)| a.lifetime.total_cmp(&b.lifetime))
+                .map(|(id, _)| *id);
+            match oldest {
+                Some(id) => self.despawn(id),
+                None => return,
             }
-        } render_data
+        }
+        let ItemDrop::Tile(texture, item) = &drop;
+        let texture = *texture;
+        let light = match item.item_type {
+            Some(ItemType::Block(tile)) => get_tile_properties(tile as u32).light,
+            _ => None,
+        };
+        // drops are given in the same space they're drawn in (a tile ahead of the tile positions)
+        let id = self.spawn((pos_x as f32 - 8.0, pos_y as f32 - 8.0));
+        self.bodies.insert(id, PhysicsBody::new((DROP_SIZE, DROP_SIZE), DROP_GRAVITY).with_drag(8.0));
+        self.healths.insert(id, Health::new(DROP_HEALTH));
+        self.ais.insert(id, Ai::new(Behaviour::Pulled, DROP_PULL_SPEED));
+        self.sprites.insert(id, EntitySprite::new(texture, (1, 1)));
+        if let Some([red, green, blue]) = light {
+            self.lights.insert(id, LightEmitter::new((red, green, blue, DROP_LIGHT.0), DROP_LIGHT.1, LightFalloff::Quadratic));
+        }
+        self.pickups.insert(id, Pickup { drop, lifetime: DEFAULT_ITEM_LIFETIME });
     }
-    
+
+    /// Starts a tile falling from the tile position (it's placed back down as the given tile once it lands)
+    pub fn new_falling_tile(&mut self, tile: u32, texture: u32, tile_x: usize, tile_y: usize) {
+        let id = self.spawn((tile_x as f32 * 8.0, tile_y as f32 * 8.0));
+        self.bodies.insert(id, PhysicsBody::new((8.0, 8.0), FALLING_TILE_GRAVITY));
+        self.sprites.insert(id, EntitySprite::new(texture, (1, 1)));
+        self.falling_tiles.insert(id, FallingTile { tile });
+    }
+
+    /// Hands every drop close enough to the player to the closure, which gives back whatever didn't fit in the inventory
+    pub fn collect_drops(&mut self, player_center: (f32, f32), mut collect: impl FnMut(ItemDrop) -> Option<ItemDrop>) {
+        let in_reach = self.pickups.keys()
+            .filter(|id| self.get_center(**id).is_some_and(|center| {
+                let dif_x = center.0 - player_center.0;
+                let dif_y = center.1 - player_center.1;
+                dif_x * dif_x + dif_y * dif_y < PICKUP_RANGE * PICKUP_RANGE
+            }))
+            .copied()
+            .collect::<Vec<_>>();
+        for id in in_reach {
+            if let Some(pickup) = self.pickups.get_mut(&id) {
+                match collect(pickup.drop.clone()) {
+                    Some(leftover) => pickup.drop = leftover,
+                    None => self.despawn(id),
+                }
+            }
+        }
+    }
+
+    /// The middle of the entity's body (or just its position for anything without one)
+    pub fn get_center(&self, id: EntityId) -> Option<(f32, f32)> {
+        let transform = self.transforms.get(&id)?;
+        let (width, height) = self.bodies.get(&id).map(|body| body.size).unwrap_or((0.0, 0.0));
+        Some((transform.position.0 + width * 0.5, transform.position.1 + height * 0.5))
+    }
+
+    /// Takes every entity light off the tile map (such as when the player leaves the dimension), to be re-added on whichever map is updated next
+    pub fn remove_lights(&mut self, tile_map: &mut TileMap) {
+        for light in self.lights.values_mut() {
+            if let Some(handle) = light.handle.take() {
+                tile_map.remove_entity_light(handle);
+            }
+        }
+        for handle in self.removed_lights.drain(..) {
+            tile_map.remove_entity_light(handle);
+        }
+    }
+
+    /// The tiles the entities are sitting in (so they can weigh down pressure plates), going by the bottom middle of their bodies
+    pub fn get_occupied_tiles(&self) -> Vec<(usize, usize)> {
+        self.bodies.iter()
+            .filter_map(|(id, body)| {
+                let transform = self.transforms.get(id)?;
+                let bottom_middle = (transform.position.0 + body.size.0 * 0.5, transform.position.1 + body.size.1 - 0.5);
+                Some(((bottom_middle.0 / 8.0).max(0.0) as usize, (bottom_middle.1 / 8.0).max(0.0) as usize))
+            })
+            .collect()
+    }
+
     pub fn get_entity_count(&self) -> usize {
-        self.drops.len()
+        self.entities.len()
     }
 }
 
@@ -47,3 +185,80 @@ pub enum ItemDrop {
     Tile (u32, Item),
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_manager::entities::player::items::ItemType;
+
+    fn sand_drop(count: usize) -> ItemDrop {
+        ItemDrop::Tile(13, Item::new(11, Some(ItemType::Block(89)), String::from("Sand"), count, 512))
+    }
+
+    fn torch_drop() -> ItemDrop {
+        ItemDrop::Tile(4, Item::new(4, Some(ItemType::Block(173)), String::from("Torch"), 1, 64))
+    }
+
+    #[test]
+    fn despawning_removes_every_component() {
+        let mut entity_manager = EntityManager::new();
+        entity_manager.new_drop(torch_drop(), 40, 40);
+        entity_manager.new_falling_tile(89, 16, 2, 2);
+        assert_eq!(entity_manager.get_entity_count(), 2);
+        assert_eq!(entity_manager.lights.len(), 1);
+
+        let ids = entity_manager.transforms.keys().copied().collect::<Vec<_>>();
+        for id in ids {
+            entity_manager.despawn(id);
+            assert!(!entity_manager.is_alive(id));
+        }
+        assert_eq!(entity_manager.get_entity_count(), 0);
+        assert!(entity_manager.transforms.is_empty());
+        assert!(entity_manager.bodies.is_empty());
+        assert!(entity_manager.healths.is_empty());
+        assert!(entity_manager.ais.is_empty());
+        assert!(entity_manager.sprites.is_empty());
+        assert!(entity_manager.lights.is_empty());
+        assert!(entity_manager.pickups.is_empty());
+        assert!(entity_manager.falling_tiles.is_empty());
+    }
+
+    #[test]
+    fn only_light_giving_drops_glow() {
+        let mut entity_manager = EntityManager::new();
+        entity_manager.new_drop(sand_drop(1), 40, 40);
+        assert!(entity_manager.lights.is_empty());
+        entity_manager.new_drop(torch_drop(), 40, 40);
+        assert_eq!(entity_manager.lights.len(), 1);
+    }
+
+    #[test]
+    fn collecting_keeps_the_leftovers() {
+        let mut entity_manager = EntityManager::new();
+        entity_manager.new_drop(sand_drop(10), 40, 40);
+        let center = entity_manager.get_center(*entity_manager.pickups.keys().next().unwrap()).unwrap();
+
+        // too far away to be picked up
+        entity_manager.collect_drops((center.0 + 50.0, center.1), |_| panic!("the drop was out of reach"));
+        // only part of the stack fits
+        entity_manager.collect_drops(center, |_| Some(sand_drop(4)));
+        let ItemDrop::Tile(_, item) = &entity_manager.pickups.values().next().unwrap().drop;
+        assert_eq!(item.item_count, 4);
+        // the rest fits
+        entity_manager.collect_drops(center, |_| None);
+        assert_eq!(entity_manager.get_entity_count(), 0);
+    }
+
+    #[test]
+    fn drops_past_the_limit_replace_the_oldest() {
+        let mut entity_manager = EntityManager::new();
+        for _ in 0..MAX_ENTITIES {
+            entity_manager.new_drop(sand_drop(1), 40, 40);
+        }
+        let oldest = *entity_manager.pickups.keys().next().unwrap();
+        entity_manager.pickups.get_mut(&oldest).unwrap().lifetime = 1.0;
+
+        entity_manager.new_drop(sand_drop(1), 40, 40);
+        assert_eq!(entity_manager.get_entity_count(), MAX_ENTITIES);
+        assert!(!entity_manager.is_alive(oldest));
+    }
+}
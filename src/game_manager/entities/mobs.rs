@@ -0,0 +1,455 @@
+use rand::Rng;
+
+use crate::game_manager::entities::components::{Ai, Behaviour, EntityId, EntitySprite, Health, Mob, PhysicsBody};
+use crate::game_manager::entities::manager::EntityManager;
+use crate::game_manager::world::tile_map::TileMap;
+use crate::game_manager::world::world_gen::Biom;
+use crate::shaders::shader_loader::MAX_ENTITIES;
+
+/// The most mobs (of every kind together) that can be around at once
+static MAX_MOBS: usize = 24;
+/// The seconds between attempts at spawning a mob (shortened by the weather)
+static SPAWN_INTERVAL: f64 = 1.5;
+/// How many random tiles are tried on each attempt before giving up until the next one
+static SPAWN_ATTEMPTS: usize = 8;
+/// How far from the player (in tiles) mobs spawn, sideways; far enough to be off screen, but not so far they're never met
+static SPAWN_DISTANCE: (i64, i64) = (24, 48);
+/// How far above or below the player (in tiles) mobs can spawn
+static SPAWN_HEIGHT: i64 = 24;
+/// How long a mob has to stay out past its despawn distance before it's cleared away (so one just out of range can wander back)
+static DESPAWN_TIME: f64 = 10.0;
+static MOB_GRAVITY: f32 = 900.0;
+/// How fast mobs are thrown (sideways, then up) when they're hit
+static KNOCKBACK: (f32, f32) = (120.0, -160.0);
+/// The seconds a mob can't be hurt for after being hit
+static HIT_COOLDOWN: f64 = 0.4;
+/// How fast the player is thrown (sideways, then up) when a hostile mob touches them
+pub static CONTACT_KNOCKBACK: (f32, f32) = (260.0, -220.0);
+
+#[derive(bincode::Encode, bincode::Decode, Clone, Copy, PartialEq, Debug)]
+pub enum MobKind {
+    Slime,
+    Zombie,
+    Bat,
+    Bunny,
+}
+
+/// The part of the day a mob comes out in
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum SpawnTime {
+    Day,
+    Night,
+    Any,
+}
+
+/// Whether a mob spawns out under the open sky, or down in the caves
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum SpawnArea {
+    Surface,
+    Underground,
+}
+
+/// When a mob gets cleared away
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum DespawnPolicy {
+    Far(f32),             // after a while out past the distance (in pixels) from the player
+    FarOrDaylight(f32),   // the same, but also as soon as it's caught out under the day sky
+}
+
+struct MobInfo {
+    kind: MobKind,
+    texture: u32,         // the top left cell of the first frame (there are always two frames)
+    cells: (u32, u32),
+    frame_time: f64,
+    size: (f32, f32),     // the hitbox
+    health: f32,
+    speed: f32,
+    gravity: f32,
+    behaviour: Behaviour,
+    hostile: bool,        // whether it knocks the player back on contact
+    bioms: &'static [Biom],  // the bioms it spawns in
+    time: SpawnTime,
+    area: SpawnArea,
+    light: (u8, u8),      // the range of light levels it spawns in
+    weight: f32,          // how likely it is to be picked over the other mobs that could spawn at the same spot
+    cap: usize,           // the most of this kind that can be around at once
+    despawn: DespawnPolicy,
+}
+
+static SLIME_INFO: MobInfo = MobInfo {
+    kind: MobKind::Slime,
+    texture: 33, cells: (1, 1), frame_time: 0.5,
+    size: (8.0, 8.0), health: 14.0, speed: 70.0, gravity: MOB_GRAVITY,
+    behaviour: Behaviour::Hop,
+    hostile: true,
+    bioms: &[Biom::Forest, Biom::Jungle],
+    time: SpawnTime::Any,
+    area: SpawnArea::Surface,
+    light: (0, 255),
+    weight: 3.0, cap: 6,
+    despawn: DespawnPolicy::Far(60.0 * 8.0),
+};
+
+static ZOMBIE_INFO: MobInfo = MobInfo {
+    kind: MobKind::Zombie,
+    texture: 39, cells: (1, 2), frame_time: 0.35,
+    size: (8.0, 16.0), health: 30.0, speed: 45.0, gravity: MOB_GRAVITY,
+    behaviour: Behaviour::Chase,
+    hostile: true,
+    bioms: &[Biom::Forest, Biom::Desert, Biom::Jungle, Biom::Tundra],
+    time: SpawnTime::Night,
+    area: SpawnArea::Surface,
+    light: (0, 96),
+    weight: 4.0, cap: 6,
+    despawn: DespawnPolicy::FarOrDaylight(60.0 * 8.0),
+};
+
+static BAT_INFO: MobInfo = MobInfo {
+    kind: MobKind::Bat,
+    texture: 37, cells: (1, 1), frame_time: 0.15,
+    size: (8.0, 8.0), health: 8.0, speed: 75.0, gravity: 0.0,
+    behaviour: Behaviour::Fly,
+    hostile: true,
+    bioms: &[Biom::Forest, Biom::Desert, Biom::Jungle, Biom::Tundra],
+    time: SpawnTime::Any,
+    area: SpawnArea::Underground,
+    light: (0, 48),
+    weight: 3.0, cap: 8,
+    despawn: DespawnPolicy::Far(50.0 * 8.0),
+};
+
+static BUNNY_INFO: MobInfo = MobInfo {
+    kind: MobKind::Bunny,
+    texture: 35, cells: (1, 1), frame_time: 0.3,
+    size: (8.0, 8.0), health: 5.0, speed: 40.0, gravity: MOB_GRAVITY,
+    behaviour: Behaviour::Flee,
+    hostile: false,
+    bioms: &[Biom::Forest, Biom::Jungle, Biom::Sky],
+    time: SpawnTime::Day,
+    area: SpawnArea::Surface,
+    light: (96, 255),
+    weight: 2.0, cap: 4,
+    despawn: DespawnPolicy::Far(70.0 * 8.0),
+};
+
+/// Every mob that can spawn
+static MOB_INFO: &[&MobInfo] = &[&SLIME_INFO, &ZOMBIE_INFO, &BAT_INFO, &BUNNY_INFO];
+
+fn get_mob_info(kind: MobKind) -> &'static MobInfo {
+    match kind {
+        MobKind::Slime => &SLIME_INFO,
+        MobKind::Zombie => &ZOMBIE_INFO,
+        MobKind::Bat => &BAT_INFO,
+        MobKind::Bunny => &BUNNY_INFO,
+    }
+}
+
+/// What the world is like around the player, for deciding what spawns (and what despawns)
+pub struct SpawnConditions {
+    pub player_center: (f32, f32),  // in the same space as the entity positions
+    pub sky_brightness: f32,
+    pub is_day: bool,
+    pub spawn_rate: f32,  // a multiplier on how often mobs try to spawn
+}
+
+impl MobInfo {
+    /// Whether the mob could spawn standing (or hovering) in the tile
+    fn can_spawn_at(&self, tile_map: &TileMap, tile_x: usize, tile_y: usize, conditions: &SpawnConditions) -> bool {
+        match self.time {
+            SpawnTime::Day if !conditions.is_day => return false,
+            SpawnTime::Night if conditions.is_day => return false,
+            _ => {},
+        }
+        match tile_map.biome_at(tile_x, tile_y) {
+            Some(biom) if self.bioms.contains(&biom) => {},
+            _ => return false,
+        }
+        // the sky light row is the first solid tile of the column, so anything above it is out under the sky
+        let under_sky = (tile_y as u32) < tile_map.sky_light[tile_x];
+        if under_sky != (self.area == SpawnArea::Surface) { return false; }
+        let light = tile_map.get_light_level(tile_x, tile_y, conditions.sky_brightness);
+        if light < self.light.0 || light > self.light.1 { return false; }
+
+        let (x, y) = get_spawn_position(self, tile_x, tile_y);
+        if tile_map.check_aabb_collision(x, y, self.size.0, self.size.1) { return false; }
+        // none of the mobs can swim, so they'd only end up stuck at the bottom of a lake
+        let (bottom_x, bottom_y) = (((x + self.size.0 - 0.5) / 8.0) as usize, ((y + self.size.1 - 0.5) / 8.0) as usize);
+        for liquid_y in (y.max(0.0) / 8.0) as usize..=bottom_y {
+            for liquid_x in (x.max(0.0) / 8.0) as usize..=bottom_x {
+                if !tile_map.liquids.get(liquid_x, liquid_y).is_empty() { return false; }
+            }
+        }
+        // walking mobs need some ground to stand on
+        self.gravity <= 0.0 || (tile_y + 1 < tile_map.get_map_height() && tile_map.is_solid(tile_x, tile_y + 1))
+    }
+}
+
+/// The mobs that could spawn in the tile, leaving out any kind that's already at its cap (the counts are in the same order as MOB_INFO)
+fn get_spawn_candidates(tile_map: &TileMap, tile_x: usize, tile_y: usize, conditions: &SpawnConditions, counts: &[usize]) -> Vec<&'static MobInfo> {
+    MOB_INFO.iter()
+        .zip(counts)
+        .filter(|(info, count)| **count < info.cap && info.can_spawn_at(tile_map, tile_x, tile_y, conditions))
+        .map(|(info, _)| *info)
+        .collect()
+}
+
+/// Picks one of the candidates based on their weights, the roll being anywhere from 0 up to their total weight
+fn pick_weighted(candidates: &[&'static MobInfo], mut roll: f32) -> Option<&'static MobInfo> {
+    for info in candidates {
+        if roll < info.weight { return Some(info); }
+        roll -= info.weight;
+    }
+    // only reached through rounding errors at the very top of the range
+    candidates.last().copied()
+}
+
+/// Where the mob is placed to spawn in the tile (standing on its floor)
+fn get_spawn_position(info: &MobInfo, tile_x: usize, tile_y: usize) -> (f32, f32) {
+    (tile_x as f32 * 8.0, (tile_y + 1) as f32 * 8.0 - info.size.1)
+}
+
+impl EntityManager {
+    /// Creates a mob standing in the tile
+    pub fn new_mob(&mut self, kind: MobKind, tile_x: usize, tile_y: usize) -> EntityId {
+        let info = get_mob_info(kind);
+        let id = self.spawn(get_spawn_position(info, tile_x, tile_y));
+        self.bodies.insert(id, PhysicsBody::new(info.size, info.gravity));
+        self.healths.insert(id, Health::new(info.health));
+        self.ais.insert(id, Ai::new(info.behaviour, info.speed));
+        self.sprites.insert(id, EntitySprite::new(info.texture, info.cells).animated(2, info.frame_time));
+        self.mobs.insert(id, Mob { kind, far_time: 0.0 });
+        id
+    }
+
+    /// Every so often tries to spawn a mob at a random spot a little way off from the player
+    pub fn update_mob_spawning(&mut self, tile_map: &TileMap, conditions: &SpawnConditions, delta_time: f64, rand_state: &mut dyn rand::RngCore) {
+        self.spawn_timer -= delta_time * conditions.spawn_rate as f64;
+        if self.spawn_timer > 0.0 { return; }
+        self.spawn_timer = SPAWN_INTERVAL;
+        // leaving some room under the entity limit for drops and falling tiles
+        if self.mobs.len() >= MAX_MOBS || self.get_entity_count() + MAX_MOBS >= MAX_ENTITIES { return; }
+
+        // counted once up front, as at most one mob is spawned each attempt
+        let counts = MOB_INFO.iter()
+            .map(|info| self.mobs.values().filter(|mob| mob.kind == info.kind).count())
+            .collect::<Vec<_>>();
+        let player_tile = ((conditions.player_center.0 / 8.0) as i64, (conditions.player_center.1 / 8.0) as i64);
+        for _ in 0..SPAWN_ATTEMPTS {
+            let side = if rand_state.random_bool(0.5) { 1 } else { -1 };
+            let tile_x = player_tile.0 + side * rand_state.random_range(SPAWN_DISTANCE.0..SPAWN_DISTANCE.1);
+            let tile_y = player_tile.1 + rand_state.random_range(-SPAWN_HEIGHT..=SPAWN_HEIGHT);
+            if tile_x < 0 || tile_y < 0 || tile_x as usize >= tile_map.get_map_width() || tile_y as usize >= tile_map.get_map_height() {
+                continue;
+            }
+            let (tile_x, tile_y) = (tile_x as usize, tile_y as usize);
+
+            let candidates = get_spawn_candidates(tile_map, tile_x, tile_y, conditions, &counts);
+            let total_weight = candidates.iter().map(|info| info.weight).sum::<f32>();
+            if candidates.is_empty() || total_weight <= 0.0 { continue; }
+            if let Some(info) = pick_weighted(&candidates, rand_state.random_range(0.0..total_weight)) {
+                self.new_mob(info.kind, tile_x, tile_y);
+                return;
+            }
+        }
+    }
+
+    /// Clears away any mobs their despawn policy says are done with
+    pub fn update_mob_despawning(&mut self, tile_map: &TileMap, conditions: &SpawnConditions, delta_time: f64) {
+        let mut despawned = Vec::new();
+        for (id, mob) in self.mobs.iter_mut() {
+            let (transform, body) = match (self.transforms.get(id), self.bodies.get(id)) {
+                (Some(transform), Some(body)) => (transform, body),
+                _ => {
+                    despawned.push(*id);
+                    continue;
+                },
+            };
+            let center = (transform.position.0 + body.size.0 * 0.5, transform.position.1 + body.size.1 * 0.5);
+            let (dif_x, dif_y) = (center.0 - conditions.player_center.0, center.1 - conditions.player_center.1);
+            let distance = (dif_x * dif_x + dif_y * dif_y).sqrt();
+
+            let (despawn_distance, burns_in_daylight) = match get_mob_info(mob.kind).despawn {
+                DespawnPolicy::Far(distance) => (distance, false),
+                DespawnPolicy::FarOrDaylight(distance) => (distance, true),
+            };
+            if burns_in_daylight && conditions.is_day {
+                let (tile_x, tile_y) = ((center.0 / 8.0).max(0.0) as usize, (center.1 / 8.0).max(0.0) as usize);
+                if tile_map.sky_light.get(tile_x).is_some_and(|sky_row| (tile_y as u32) < *sky_row) {
+                    despawned.push(*id);
+                    continue;
+                }
+            }
+            match distance > despawn_distance {
+                true => mob.far_time += delta_time,
+                false => mob.far_time = 0.0,
+            }
+            if mob.far_time > DESPAWN_TIME {
+                despawned.push(*id);
+            }
+        }
+        for id in despawned {
+            self.despawn(id);
+        }
+    }
+
+    /// Clears away every mob (such as when the player leaves the dimension, as mobs only live around the player)
+    pub fn despawn_mobs(&mut self) {
+        let mobs = self.mobs.keys().copied().collect::<Vec<_>>();
+        for id in mobs {
+            self.despawn(id);
+        }
+    }
+
+    /// Hurts every mob overlapping the tile, knocking them away from its center; returns whether anything was hit
+    pub fn attack_tile(&mut self, tile_x: usize, tile_y: usize, damage: f32) -> bool {
+        let (tile_left, tile_top) = (tile_x as f32 * 8.0, tile_y as f32 * 8.0);
+        let hit = self.mobs.keys()
+            .filter(|id| match (self.transforms.get(id), self.bodies.get(id)) {
+                (Some(transform), Some(body)) => {
+                    let (x, y) = transform.position;
+                    x < tile_left + 8.0 && x + body.size.0 > tile_left && y < tile_top + 8.0 && y + body.size.1 > tile_top
+                },
+                _ => false,
+            })
+            .copied()
+            .collect::<Vec<_>>();
+        let mut any_hit = false;
+        for id in hit {
+            if !self.damage(id, damage, HIT_COOLDOWN) { continue; }
+            any_hit = true;
+            if let (Some(transform), Some(body)) = (self.transforms.get(&id), self.bodies.get_mut(&id)) {
+                let direction = if transform.position.0 + body.size.0 * 0.5 < tile_left + 4.0 { -1.0 } else { 1.0 };
+                body.velocity = (direction * KNOCKBACK.0, KNOCKBACK.1);
+            }
+        } any_hit
+    }
+
+    /// Finds a hostile mob touching the box (in pixels), giving back which way it pushes the box (-1 left, 1 right)
+    pub fn get_hostile_contact(&self, x: f32, y: f32, width: f32, height: f32) -> Option<f32> {
+        self.mobs.iter()
+            .filter(|(_, mob)| get_mob_info(mob.kind).hostile)
+            .find_map(|(id, _)| {
+                let (transform, body) = (self.transforms.get(id)?, self.bodies.get(id)?);
+                let (mob_x, mob_y) = transform.position;
+                let touching = mob_x < x + width && mob_x + body.size.0 > x && mob_y < y + height && mob_y + body.size.1 > y;
+                match touching {
+                    true => Some(if mob_x + body.size.0 * 0.5 < x + width * 0.5 { 1.0 } else { -1.0 }),
+                    false => None,
+                }
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_manager::world::tile_map::STONE_IDS;
+    use crate::game_manager::world::tile_map::liquids::{LiquidCell, LiquidType, MAX_LIQUID_LEVEL};
+
+    /// A forest with a flat stone floor along row 12, open to the sky above it
+    fn forest_tile_map() -> TileMap {
+        let mut tile_map = TileMap::test_tile_map(16, 16);
+        for x in 0..16 {
+            tile_map.change_tile(x, 12, 0, STONE_IDS[0]).unwrap();
+        }
+        tile_map.flood_sky_light(|_| true);
+        tile_map
+    }
+
+    fn conditions(is_day: bool, sky_brightness: f32) -> SpawnConditions {
+        SpawnConditions { player_center: (64.0, 64.0), sky_brightness, is_day, spawn_rate: 1.0 }
+    }
+
+    #[test]
+    fn bunnies_spawn_on_sunny_forest_ground() {
+        let tile_map = forest_tile_map();
+        assert!(BUNNY_INFO.can_spawn_at(&tile_map, 4, 11, &conditions(true, 1.0)));
+    }
+
+    #[test]
+    fn spawning_follows_the_time_and_light() {
+        let tile_map = forest_tile_map();
+        // bunnies only come out in the day
+        assert!(!BUNNY_INFO.can_spawn_at(&tile_map, 4, 11, &conditions(false, 1.0)));
+        // and not in the dark, even during the day
+        assert!(!BUNNY_INFO.can_spawn_at(&tile_map, 4, 11, &conditions(true, 0.1)));
+        // zombies wait for a dark night
+        assert!(!ZOMBIE_INFO.can_spawn_at(&tile_map, 4, 11, &conditions(true, 0.1)));
+        assert!(!ZOMBIE_INFO.can_spawn_at(&tile_map, 4, 11, &conditions(false, 1.0)));
+        assert!(ZOMBIE_INFO.can_spawn_at(&tile_map, 4, 11, &conditions(false, 0.1)));
+    }
+
+    #[test]
+    fn spawning_needs_the_right_biom() {
+        let mut tile_map = forest_tile_map();
+        let mut bioms = vec![Biom::Forest; tile_map.get_map_width()];
+        bioms[4] = Biom::Desert;
+        tile_map.set_bioms(bioms);
+        assert!(!BUNNY_INFO.can_spawn_at(&tile_map, 4, 11, &conditions(true, 1.0)));
+        assert!(BUNNY_INFO.can_spawn_at(&tile_map, 5, 11, &conditions(true, 1.0)));
+    }
+
+    #[test]
+    fn nothing_spawns_in_liquid() {
+        let mut tile_map = forest_tile_map();
+        tile_map.liquids.set(4, 11, LiquidCell::new(LiquidType::Water, MAX_LIQUID_LEVEL));
+        assert!(!BUNNY_INFO.can_spawn_at(&tile_map, 4, 11, &conditions(true, 1.0)));
+    }
+
+    #[test]
+    fn walking_mobs_need_a_floor() {
+        let tile_map = forest_tile_map();
+        assert!(!BUNNY_INFO.can_spawn_at(&tile_map, 4, 8, &conditions(true, 1.0)));
+        // the surface is out of the bats' reach as well, as they only live underground
+        assert!(!BAT_INFO.can_spawn_at(&tile_map, 4, 8, &conditions(false, 0.0)));
+    }
+
+    #[test]
+    fn full_kinds_are_left_out_of_the_candidates() {
+        let tile_map = forest_tile_map();
+        let conditions = conditions(true, 1.0);
+        let bunny_index = MOB_INFO.iter().position(|info| info.kind == MobKind::Bunny).unwrap();
+        let mut counts = vec![0; MOB_INFO.len()];
+        let kinds = |counts: &[usize]| get_spawn_candidates(&tile_map, 4, 11, &conditions, counts).iter().map(|info| info.kind).collect::<Vec<_>>();
+        assert!(kinds(&counts).contains(&MobKind::Bunny));
+        counts[bunny_index] = BUNNY_INFO.cap;
+        assert!(!kinds(&counts).contains(&MobKind::Bunny));
+    }
+
+    #[test]
+    fn picking_goes_by_weight() {
+        let candidates: &[&'static MobInfo] = &[&SLIME_INFO, &BUNNY_INFO];
+        assert_eq!(pick_weighted(candidates, 0.0).map(|info| info.kind), Some(MobKind::Slime));
+        assert_eq!(pick_weighted(candidates, SLIME_INFO.weight - 0.01).map(|info| info.kind), Some(MobKind::Slime));
+        assert_eq!(pick_weighted(candidates, SLIME_INFO.weight + 0.01).map(|info| info.kind), Some(MobKind::Bunny));
+        assert_eq!(pick_weighted(candidates, SLIME_INFO.weight + BUNNY_INFO.weight).map(|info| info.kind), Some(MobKind::Bunny));
+        assert!(pick_weighted(&[], 0.0).is_none());
+    }
+
+    #[test]
+    fn mobs_only_despawn_after_a_while_out_of_range() {
+        let tile_map = forest_tile_map();
+        let mut entity_manager = EntityManager::new();
+        let near = entity_manager.new_mob(MobKind::Slime, 8, 11);
+        let far = entity_manager.new_mob(MobKind::Slime, 4, 11);
+        // the player is way off to the right of the far slime, but close to the near one
+        let conditions = SpawnConditions { player_center: (8.0 * 8.0 + 60.0 * 8.0 - 4.0, 11.0 * 8.0), ..conditions(true, 1.0) };
+
+        entity_manager.update_mob_despawning(&tile_map, &conditions, DESPAWN_TIME * 0.5);
+        assert!(entity_manager.is_alive(far));
+        entity_manager.update_mob_despawning(&tile_map, &conditions, DESPAWN_TIME * 0.6);
+        assert!(!entity_manager.is_alive(far));
+        assert!(entity_manager.is_alive(near));
+    }
+
+    #[test]
+    fn zombies_burn_up_under_the_day_sky() {
+        let tile_map = forest_tile_map();
+        let mut entity_manager = EntityManager::new();
+        let zombie = entity_manager.new_mob(MobKind::Zombie, 4, 11);
+        entity_manager.update_mob_despawning(&tile_map, &conditions(false, 0.1), 0.1);
+        assert!(entity_manager.is_alive(zombie));
+        entity_manager.update_mob_despawning(&tile_map, &conditions(true, 1.0), 0.1);
+        assert!(!entity_manager.is_alive(zombie));
+    }
+}
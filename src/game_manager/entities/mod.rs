@@ -1,5 +1,8 @@
 
 pub mod manager;
+pub mod components;
+pub mod systems;
+pub mod mobs;
 pub mod player;
 pub mod entity;
 
@@ -1,70 +1,21 @@
-use crate::game_manager::{entities::{manager::{EntityManager, ItemDrop}, player::{font_rendering::render_font_unifont, items::{Item, ItemGenerator, ItemType, ToolType}, player::{KeyBindings, PlayerData}, player_ui::PlayerUiManager}}, game::GameError, world::tile_map};
-use crate::core::{event_handling::event_handler::EventHandler, rendering::ui::{UiElement, UiError}, timer::Timer};
+use crate::game_manager::{entities::{manager::{EntityManager, ItemDrop}, player::{font_rendering::render_font_unifont, items::{Item, ItemGenerator, ItemType, MiningStats, ToolType}, player::{GameMode, KeyBindings, PlayerData}, player_ui::PlayerUiManager}}, game::GameError, world::tile_map::{self, registry::{TileType, get_tile_properties}}};
+use crate::core::{event_handling::event_handler::{ButtonState, EventHandler}, rendering::ui::{UiElement, UiError}};
 use std::rc::Rc;
 
 use rand::Rng;
 
-pub static TILE_DROPS: &[TileDrop] = &[
-    TileDrop {
-        parent_tile: &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 47, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 46],
-        drop_chances: &[1.0],
-        drops_items: &[ItemGenerator::new((1, 1), 5, Some(ItemType::Block(1)), "Dirt", 512)],
-        droped_textures: &[7],
-    },
-    TileDrop {
-        parent_tile: &[30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45],
-        drop_chances: &[1.0],
-        drops_items: &[ItemGenerator::new((1, 1), 6, Some(ItemType::Block(45)), "Stone", 512)],
-        droped_textures: &[8],
-    },
-    TileDrop {
-        parent_tile: &[88],
-        drop_chances: &[1.0],
-        drops_items: &[ItemGenerator::new((1, 1), 7, Some(ItemType::Block(88)), "Light", 512)],
-        droped_textures: &[9],
-    },
-    TileDrop {
-        parent_tile: &[173],
-        drop_chances: &[1.0],
-        drops_items: &[ItemGenerator::new((1, 1), 8, Some(ItemType::Block(173)), "Torch", 512)],
-        droped_textures: &[10],
-    },
-    TileDrop {
-        parent_tile: &[137, 138, 139, 140, 141, 142, 143, 144, 145, 146, 147, 148, 149, 150, 151, 135],
-        drop_chances: &[1.0],
-        drops_items: &[ItemGenerator::new((1, 1), 9, Some(ItemType::Block(137)), "Snow", 512)],
-        droped_textures: &[11],
-    },
-    TileDrop {
-        parent_tile: &[118, 119, 120, 121, 122, 123, 124, 125, 126, 127, 128, 129, 130, 131, 132, 136],
-        drop_chances: &[1.0],
-        drops_items: &[ItemGenerator::new((1, 1), 10, Some(ItemType::Block(118)), "Ice", 512)],
-        droped_textures: &[12],
-    },
-    TileDrop {
-        parent_tile: &[89 , 90 , 91 , 92 , 93 , 94 , 95 , 96 , 97 , 98 , 99 , 100, 101, 102, 134],
-        drop_chances: &[1.0],
-        drops_items: &[ItemGenerator::new((1, 1), 11, Some(ItemType::Block(89)), "Sand", 512)],
-        droped_textures: &[13],
-    },
-    TileDrop {
-        parent_tile: &[103, 104, 105, 106, 107, 108, 109, 110, 111, 112, 113, 114, 115, 116, 117, 133],
-        drop_chances: &[1.0],
-        drops_items: &[ItemGenerator::new((1, 1), 12, Some(ItemType::Block(103)), "Sandstone", 512)],
-        droped_textures: &[14],
-    },
-];
+/// How much health a swing of the attack tool takes off a mob
+static ATTACK_DAMAGE: f32 = 6.0;
 
 pub struct TileDrop {
-    parent_tile: &'static [u32],
     drop_chances: &'static [f32],
     drops_items: &'static [ItemGenerator],  // the id of the item (could really be any u32, hopefully this won't require sequential ids so that in theory additions are easy and safe)
     droped_textures: &'static [u32],
 }
 
 impl TileDrop {
-    pub const fn new(parent_tile: &'static [u32], drop_chances: &'static [f32], drops_items: &'static [ItemGenerator], droped_textures: &'static [u32]) -> Self {
-        Self { parent_tile, drop_chances, drops_items, droped_textures }
+    pub const fn new(drop_chances: &'static [f32], drops_items: &'static [ItemGenerator], droped_textures: &'static [u32]) -> Self {
+        Self { drop_chances, drops_items, droped_textures }
     }
 
     // (item, texture id)
@@ -93,18 +44,38 @@ impl Inventory {
             hot_bar: {
                 let mut items: [Option<Item>; 10] = [const { None }; 10];
                 items[0] = Some(Item::new(1, Some(ItemType::Tool(ToolType::Attacker())), String::from("Attack"), 1, 1));
-                items[1] = Some(Item::new(2, Some(ItemType::Tool(ToolType::Breaker(vec![]))), String::from("Break"), 2, 1));
+                items[1] = Some(Item::new(2, Some(ItemType::Tool(ToolType::Breaker(MiningStats::new(2, 1.0)))), String::from("Break"), 2, 1));
                 items[2] = Some(Item::new(3, Some(ItemType::Block(1)), String::from("Build"), 1, 1));
                 items[3] = Some(Item::new(4, Some(ItemType::Block(88)), String::from("Light"), 128, 1));
                 items[4] = Some(Item::new(4, Some(ItemType::Block(173)), String::from("Torch"), 64, 1));
+                items[5] = Some(Item::new(17, Some(ItemType::Block(367)), String::from("Portal"), 4, 64));
+                items[6] = Some(Item::new(18, Some(ItemType::Block(368)), String::from("Bed"), 1, 16));
+                items[7] = Some(Item::new(19, Some(ItemType::Tool(ToolType::Wrench(0))), String::from("Red Wrench"), 1, 1));
+                items[8] = Some(Item::new(20, Some(ItemType::Tool(ToolType::Wrench(1))), String::from("Blue Wrench"), 1, 1));
+                items[9] = Some(Item::new(30, Some(ItemType::Tool(ToolType::Hammer())), String::from("Hammer"), 1, 1));
                 items
             },
             inventory: {
-                 [[const { None }; 10], [const { None }; 10], [const { None }; 10], [const { None }; 10]]
+                let mut inventory = [[const { None }; 10], [const { None }; 10], [const { None }; 10], [const { None }; 10]];
+                // the wiring devices
+                inventory[0][0] = Some(Item::new(21, Some(ItemType::Block(369)), String::from("Lever"), 16, 64));
+                inventory[0][1] = Some(Item::new(22, Some(ItemType::Block(371)), String::from("Button"), 16, 64));
+                inventory[0][2] = Some(Item::new(23, Some(ItemType::Block(373)), String::from("Pressure Plate"), 16, 64));
+                inventory[0][3] = Some(Item::new(24, Some(ItemType::Block(375)), String::from("Door"), 8, 16));
+                inventory[0][4] = Some(Item::new(25, Some(ItemType::Block(377)), String::from("Lamp"), 16, 64));
+                inventory[0][5] = Some(Item::new(26, Some(ItemType::Block(379)), String::from("Spike Trap"), 16, 64));
+                inventory[0][6] = Some(Item::new(27, Some(ItemType::Block(381)), String::from("Platform"), 64, 256));
+                inventory[0][7] = Some(Item::new(28, Some(ItemType::Block(382)), String::from("Ladder"), 64, 256));
+                inventory[0][8] = Some(Item::new(29, Some(ItemType::Block(383)), String::from("Rope"), 64, 256));
+                inventory
             },
         }
     }
 
+    pub fn get_selected_item(&self) -> Option<&Item> {
+        self.hot_bar[self.selected_item].as_ref()
+    }
+
     fn clicked_inventory(&self, mouse_position: (u32, u32), inventory_open: bool) -> bool {
         if inventory_open { mouse_position.0 >= 25 && mouse_position.0 <= 40 * 10 + 50 && mouse_position.1 <= 50 * 4 + 150 }
         else { mouse_position.0 >= 25 && mouse_position.0 <= 40 * 10 + 50 && mouse_position.1 <= 80 }
@@ -118,21 +89,59 @@ impl Inventory {
         event_handler: &EventHandler,
         ui_manager: &mut PlayerUiManager,
         entity_manager: &mut EntityManager,
-        rand_state: &mut dyn rand::RngCore
+        rand_state: &mut dyn rand::RngCore,
+        delta_time: f64,
+        game_mode: GameMode,
     ) -> Result<(), GameError> {
         let inventory_open = ui_manager.ui_elements.iter().any(|e| e.identifier == "Inventory");
         if self.clicked_inventory(event_handler.mouse.position, inventory_open) { return Ok(()); }
+        // building doesn't drop anything, otherwise breaking something and undoing it would duplicate it
+        let drops_items = game_mode != GameMode::Builder;
         match & self.hot_bar[self.selected_item] {
-            Some(Item { item_type: Some(ItemType::Tool(ToolType::Breaker(_can_break))), .. }) => {
+            Some(Item { item_type: Some(ItemType::Tool(ToolType::Breaker(stats))), .. }) => {
                 let tile = tile_map.get_tile(tile_x, tile_y, 0);
-                let tile_texture_id = TILE_DROPS.iter().find(|tile_drop| tile_drop.parent_tile.iter().any(|t| *t == tile));
-                if let Some(tile) = tile_texture_id {
-                    let drops = tile.get_dropped_tile_info(rand_state);
+                if tile == 0 {
+                    // with nothing in the way, any tree behind can be chopped down
+                    let tree = match tile_map.get_tree_trunk(tile_x, tile_y) {
+                        Some(tree) => tree,
+                        None => return Ok(()),
+                    };
+                    if !tile_map.damage_tile(tile_x, tile_y, delta_time as f32 * stats.speed / tree.hardness) { return Ok(()); }
+                    let tree_tiles = tile_map.get_tree_tiles(tile_x, tile_y).into_iter().map(|(x, y)| (x, y, 1)).collect::<Vec<_>>();
+                    tile_map.record_edits(&tree_tiles, |tile_map| Ok(tile_map.fell_tree(tile_x, tile_y)))?;
+                    if !drops_items { return Ok(()); }
+                    for drop in tree.drops.get_dropped_tile_info(rand_state) {
+                        entity_manager.new_drop(ItemDrop::Tile(drop.1, drop.0), ((tile_x + 1) * 8 + 2) as u32, ((tile_y + 1) * 8 + 2) as u32);
+                    }
+                    return Ok(());
+                }
+                let properties = get_tile_properties(tile);
+                let (hardness, tier) = properties.hardness;
+                if tier > stats.power { return Ok(()); }  // the tool isn't strong enough
+                if !tile_map.damage_tile(tile_x, tile_y, delta_time as f32 * stats.speed / hardness) { return Ok(()); }
+                if let Some(tile_drop) = properties.drops.as_ref().filter(|_| drops_items) {
+                    let drops = tile_drop.get_dropped_tile_info(rand_state);
                     for drop in drops {
                         entity_manager.new_drop(ItemDrop::Tile(drop.1, drop.0), ((tile_x + 1) * 8 + 2) as u32, ((tile_y + 1) * 8 + 2) as u32);
                     }
                 }
-                tile_map.change_tile(tile_x, tile_y, 0, 0)?;
+                tile_map.record_edit(tile_x, tile_y, 0, |tile_map| tile_map.change_tile(tile_x, tile_y, 0, 0))?;
+            },
+            Some(Item { item_type: Some(ItemType::Tool(ToolType::Attacker())), .. }) => {
+                if matches!(event_handler.mouse.left, ButtonState::Pressed) {
+                    entity_manager.attack_tile(tile_x, tile_y, ATTACK_DAMAGE);
+                }
+            },
+            Some(Item { item_type: Some(ItemType::Tool(ToolType::Wrench(color))), .. }) => {
+                tile_map.cut_wire(tile_x, tile_y, *color);
+            },
+            Some(Item { item_type: Some(ItemType::Tool(ToolType::Hammer())), .. }) => {
+                if matches!(event_handler.mouse.left, ButtonState::Pressed) {
+                    tile_map.record_edit(tile_x, tile_y, 0, |tile_map| {
+                        tile_map.reset_tile_shape(tile_x, tile_y);
+                        Ok(())
+                    })?;
+                }
             },
             _ => {},
         }
@@ -152,7 +161,23 @@ impl Inventory {
         if self.clicked_inventory(event_handler.mouse.position, inventory_open) { return Ok(()); }
         match self.hot_bar[self.selected_item] {
             Some(Item { item_type: Some(ItemType::Block(id)), .. }) => {
-                tile_map.change_tile(tile_x, tile_y, 0, id as u32)?;
+                // seeds only take on the ground their tree grows on (and nothing is recorded unless one actually gets planted)
+                if get_tile_properties(id as u32).tile_type == TileType::Sapling {
+                    if tile_map.can_plant_sapling(tile_x, tile_y, id as u32) {
+                        tile_map.record_edit(tile_x, tile_y, 0, |tile_map| tile_map.plant_sapling(tile_x, tile_y, id as u32))?;
+                    }
+                    return Ok(());
+                }
+                tile_map.record_edit(tile_x, tile_y, 0, |tile_map| tile_map.change_tile(tile_x, tile_y, 0, id as u32))?;
+            },
+            Some(Item { item_type: Some(ItemType::Tool(ToolType::Wrench(color))), .. }) => {
+                tile_map.place_wire(tile_x, tile_y, color);
+            },
+            Some(Item { item_type: Some(ItemType::Tool(ToolType::Hammer())), .. }) => {
+                // one shape per click, otherwise holding it down would spin through them every frame
+                if matches!(event_handler.mouse.right, ButtonState::Pressed) {
+                    tile_map.record_edit(tile_x, tile_y, 0, |tile_map| Ok(tile_map.cycle_tile_shape(tile_x, tile_y)))?;
+                }
             },
             _ => {},
         }
@@ -206,14 +231,13 @@ impl Inventory {
 
     pub fn update_key_events(
         &mut self,
-        timer: &Timer,
         event_handler: &EventHandler,
         tile_map: &mut tile_map::TileMap,
         screen_size: (u32, u32),
         key_bindings: &super::player::KeyBindings,
         ui_manager: &mut PlayerUiManager,
         entity_manager: &mut EntityManager,
-        player_position: &(f32, f32),
+        player_center: &(f32, f32),
     ) -> Result<(), GameError> {
         for (index, key) in [
             sdl2::keyboard::Keycode::NUM_1, sdl2::keyboard::Keycode::NUM_2,
@@ -227,27 +251,7 @@ impl Inventory {
             }
         }
 
-        let mut i = 0;
-        while i < entity_manager.drops.len() {
-            let (_drop, pos_x, pos_y, alive_duration) = &mut entity_manager.drops[i];
-            let dif_x = *pos_x as f32 - player_position.0;
-            let dif_y = *pos_y as f32 - player_position.1;
-            *alive_duration -= timer.delta_time;
-            if *alive_duration <= 0.0 {
-                entity_manager.drops.remove(i);  // despawned
-                continue;
-            }
-            if dif_x*dif_x + dif_y*dif_y < 100.0 {
-                let (drop, p1, p2, p3) = entity_manager.drops.remove(i);
-                let item = self.add_item(drop);
-                if let Some(item) = item {
-                    entity_manager.drops.insert(i, (item, p1, p2, p3));
-                    i += 1;
-                }
-                continue;
-            }
-            i += 1;
-        }
+        entity_manager.collect_drops(*player_center, |drop| self.add_item(drop));
         
         let raw_keys_down = event_handler.keys_pressed.iter().map(|k| **k).collect::<Vec<_>>();
         if KeyBindings::check_true(&key_bindings.inventory, &raw_keys_down, &event_handler.mods_pressed) {
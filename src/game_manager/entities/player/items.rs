@@ -8,8 +8,22 @@ pub enum ItemType {
 
 #[derive(bincode::Encode, bincode::Decode, Clone, PartialEq)]
 pub enum ToolType {
-    Breaker (Vec<usize>),
+    Breaker (MiningStats),
     Attacker (),
+    Wrench (u32),  // lays wire of the color (and cuts it with the other button)
+    Hammer (),     // shapes blocks into slopes and half blocks (and back into full blocks with the other button)
+}
+
+#[derive(bincode::Encode, bincode::Decode, Clone, PartialEq)]
+pub struct MiningStats {
+    pub power: u32,  // the highest tile tier the tool can break
+    pub speed: f32,  // a multiplier on how fast the damage builds up
+}
+
+impl MiningStats {
+    pub const fn new(power: u32, speed: f32) -> Self {
+        Self { power, speed }
+    }
 }
 
 #[derive(bincode::Encode, bincode::Decode, Clone)]
@@ -2,13 +2,23 @@ use crate::core::event_handling::event_handler::{ButtonState, EventHandler};
 use crate::game_manager::entities::entity::Entity;
 use crate::game_manager::entities::manager::EntityManager;
 use crate::game_manager::entities::player::inventory::Inventory;
+use crate::game_manager::entities::player::items::{Item, ItemType, ToolType};
 use crate::game_manager::entities::player::player_ui::PlayerUiManager;
 use crate::game_manager::game::GameError;
 use crate::game_manager::world::tile_map;
+use crate::game_manager::world::tile_map::Dimension;
+use crate::game_manager::world::tile_map::registry::CollisionShape;
+use crate::game_manager::world::tile_map::entity_lights::{EntityLight, LightFalloff, LightHandle};
 use crate::core::timer::Timer;
 use crate::textures::animation::Animator;
 use crate::textures::sprite::{Hitbox, Sprite};
 
+/// How fast the player moves up and down ladders and ropes (in pixels per second)
+static CLIMB_SPEED: f32 = 150.0;
+/// The tallest ledge the player (or any other entity) walks straight up without jumping (a little over a half block, in pixels)
+/// so slopes and half blocks are walked up while full blocks still need a jump
+pub(crate) static MAX_STEP_HEIGHT: f32 = 4.5;
+
 #[repr(u8)]
 #[derive(bincode::Encode, bincode::Decode, Copy, Clone, Default)]
 pub enum PlayerAnimation {
@@ -30,6 +40,14 @@ impl From<u8> for PlayerAnimation {
     }
 }
 
+/// Survival plays normally, while builder mode keeps an undo/redo history of the tiles built and broken (and doesn't drop anything)
+/// It's picked when the world is created, and can't be changed in game
+#[derive(bincode::Encode, bincode::Decode, serde::Serialize, serde::Deserialize, Copy, Clone, PartialEq, Debug)]
+pub enum GameMode {
+    Survival,
+    Builder,
+}
+
 #[derive(bincode::Encode, bincode::Decode)]
 pub struct KeyBindings {
     pub inventory: Vec<KeyBind>,
@@ -37,6 +55,7 @@ pub struct KeyBindings {
     pub right: Vec<KeyBind>,
     pub jump: Vec<KeyBind>,
     pub down: Vec<KeyBind>,
+    pub respawn: Vec<KeyBind>,
 }
 
 impl KeyBindings {
@@ -66,6 +85,12 @@ pub struct Player {
     pub entity: Entity<PlayerAnimation>,
     player_data: PlayerData,
     key_bindings: KeyBindings,
+    pub light: Option<LightHandle>,  // the light following the player around
+    pub dimension: Dimension,  // the dimension the player is currently in
+    in_portal: bool,  // stops the player being sent straight back after arriving on a portal
+    pub game_mode: GameMode,
+    pub respawn_point: Option<(Dimension, usize, usize)>,  // the bed the player last slept in (None goes back to the world spawn)
+    climbing: bool,  // holding onto a ladder or rope (gravity is ignored until they let go or step off it)
 }
 
 impl Player {
@@ -93,10 +118,112 @@ impl Player {
                 right: vec![KeyBind::Key(*sdl2::keyboard::Keycode::D)],
                 jump: vec![KeyBind::Key(*sdl2::keyboard::Keycode::W), KeyBind::Key(*sdl2::keyboard::Keycode::SPACE)],
                 down: vec![KeyBind::Mod(sdl2::keyboard::Mod::LSHIFTMOD.bits()), KeyBind::Key(*sdl2::keyboard::Keycode::S)],
+                respawn: vec![KeyBind::Key(*sdl2::keyboard::Keycode::R)],
             },
+            light: None,
+            dimension: Dimension::Overworld,
+            in_portal: false,
+            game_mode: GameMode::Survival,
+            respawn_point: None,
+            climbing: false,
         }
     }
 
+    /// The light that follows the player around
+    pub fn create_light(&self) -> EntityLight {
+        EntityLight::new((self.entity.position.0 - 4.0, self.entity.position.1 - 4.0), (225, 225, 128, 0.65), 52.0, LightFalloff::Quadratic)
+    }
+
+    /// The center of the player's hitbox in pixels (the same space the other entities are in)
+    pub fn get_center(&self) -> (f32, f32) {
+        let (x, y, width, height) = self.get_hitbox_bounds();
+        (x + width * 0.5, y + height * 0.5)
+    }
+
+    /// The tile at the center of the player's hitbox
+    pub fn get_tile_position(&self) -> (usize, usize) {
+        let (center_x, center_y) = self.get_center();
+        ((center_x / 8.0).max(0.0) as usize, (center_y / 8.0).max(0.0) as usize)
+    }
+
+    /// The player's hitbox in pixels (left, top, width, height)
+    pub fn get_hitbox_bounds(&self) -> (f32, f32, f32, f32) {
+        let hitbox = self.entity.sprite.get_hitbox();
+        (
+            self.entity.position.0 + hitbox.offset.0 as f32, self.entity.position.1 + hitbox.offset.1 as f32,
+            hitbox.size.0 as f32, hitbox.size.1 as f32,
+        )
+    }
+
+    /// Every tile the player's hitbox overlaps
+    pub fn get_overlapped_tiles(&self) -> Vec<(usize, usize)> {
+        let (x, y, width, height) = self.get_hitbox_bounds();
+        let (left, top) = ((x / 8.0).max(0.0) as usize, (y / 8.0).max(0.0) as usize);
+        let (right, bottom) = (((x + width) / 8.0).max(0.0) as usize, ((y + height) / 8.0).max(0.0) as usize);
+        (left..=right).flat_map(|tile_x| (top..=bottom).map(move |tile_y| (tile_x, tile_y)))
+            .filter(|&(tile_x, tile_y)| self.overlaps_tile(tile_x, tile_y))
+            .collect()
+    }
+
+    /// Whether the player's hitbox overlaps any part of the tile
+    fn overlaps_tile(&self, tile_x: usize, tile_y: usize) -> bool {
+        let (left, top, width, height) = self.get_hitbox_bounds();
+        let (tile_left, tile_top) = (tile_x as f32 * 8.0, tile_y as f32 * 8.0);
+        left < tile_left + 8.0 && left + width > tile_left && top < tile_top + 8.0 && top + height > tile_top
+    }
+
+    /// The lowest tile the player's hitbox reaches into (what they're standing in, rather than on)
+    pub fn get_feet_tile(&self) -> (usize, usize) {
+        let hitbox = self.entity.sprite.get_hitbox();
+        let (center_x, _) = self.get_tile_position();
+        let bottom = self.entity.position.1 + hitbox.offset.1 as f32 + hitbox.size.1 as f32 - 0.5;
+        (center_x, (bottom / 8.0).max(0.0) as usize)
+    }
+
+    /// The wires are only drawn while holding a wrench
+    pub fn is_showing_wires(&self) -> bool {
+        matches!(self.player_data.inventory.get_selected_item(), Some(Item { item_type: Some(ItemType::Tool(ToolType::Wrench(_))), .. }))
+    }
+
+    /// Puts the player standing in the tile (with the camera snapped straight there)
+    pub fn teleport_to_tile(&mut self, tile_x: usize, tile_y: usize) {
+        let hitbox = self.entity.sprite.get_hitbox();
+        // lining the bottom of the hitbox up with the bottom of the tile
+        self.entity.position = (
+            tile_x as f32 * 8.0 + 4.0 - hitbox.offset.0 as f32 - hitbox.size.0 as f32 * 0.5,
+            (tile_y + 1) as f32 * 8.0 - 1.0 - hitbox.offset.1 as f32 - hitbox.size.1 as f32,
+        );
+        self.entity.velocity = (0.0, 0.0);
+        self.camera.x = self.entity.position.0;
+        self.camera.y = self.entity.position.1;
+    }
+
+    /// Returns true when the player first steps into a portal (arriving on one doesn't count until they've stepped off it)
+    pub fn check_portal(&mut self, tile_map: &tile_map::TileMap) -> bool {
+        let (tile_x, tile_y) = self.get_tile_position();
+        let in_portal = tile_map.is_portal(tile_x, tile_y);
+        let entered = in_portal && !self.in_portal;
+        self.in_portal = in_portal;
+        entered
+    }
+
+    /// Marks the player as already standing in the portal they arrived on
+    pub fn arrive_through_portal(&mut self, dimension: Dimension) {
+        self.dimension = dimension;
+        self.in_portal = true;
+    }
+
+    /// Whether the player has dropped off the bottom of the map (such as off the edge of a sky island)
+    pub fn has_fallen_out(&self, tile_map: &tile_map::TileMap) -> bool {
+        self.entity.position.1 > (tile_map.get_map_height() + 16) as f32 * 8.0
+    }
+
+    /// Whether the respawn key was just pressed (for getting back to the bed, or out of somewhere the player is stuck)
+    pub fn wants_respawn(&self, event_handler: &EventHandler) -> bool {
+        let raw_keys_down = event_handler.keys_pressed.iter().map(|k| **k).collect::<Vec<_>>();
+        KeyBindings::check_true(&self.key_bindings.respawn, &raw_keys_down, &event_handler.mods_pressed)
+    }
+
     pub fn render_ui(
         &mut self,
         buffer: &mut [u8],
@@ -107,31 +234,59 @@ impl Player {
         player_ui_manager.render_ui(buffer, buffer_size, &mut self.player_data, pitch)
     }
 
-    fn move_player(&mut self, delta_x: f32, delta_y: f32, tile_map: &tile_map::TileMap) -> bool {
+    /// Handles undo (ctrl + z) and redo (ctrl + y) in builder worlds (the game mode is picked when the world is created)
+    fn update_builder_mode(&mut self, event_handler: &EventHandler, tile_map: &mut tile_map::TileMap, control_held: bool) -> Result<(), GameError> {
+        if self.game_mode != GameMode::Builder { return Ok(()); }
+
+        if control_held && event_handler.keys_pressed.contains(&sdl2::keyboard::Keycode::Z) {
+            tile_map.undo_edit()?;
+        } else if control_held && event_handler.keys_pressed.contains(&sdl2::keyboard::Keycode::Y) {
+            tile_map.redo_edit()?;
+        }
+        // the edits made by the clicks this frame get recorded (see Inventory::left_click_item and right_click_item)
+        if matches!(event_handler.mouse.left, ButtonState::Pressed | ButtonState::Held) ||
+           matches!(event_handler.mouse.right, ButtonState::Pressed | ButtonState::Held) {
+            tile_map.begin_edit_group();
+        }
+        Ok(())
+    }
+
+    /// Moves the player, returning true if they hit something (drop_through lets them fall through platforms)
+    fn move_player(&mut self, delta_x: f32, delta_y: f32, tile_map: &tile_map::TileMap, drop_through: bool) -> bool {
         // trying to do a smoother collision detection by splitting the movement into many steps
         let hitbox = self.entity.sprite.get_hitbox();
+        let (offset_x, offset_y, width, height) = (hitbox.offset.0 as f32, hitbox.offset.1 as f32, hitbox.size.0 as f32, hitbox.size.1 as f32);
+        // walking along the ground keeps the player on it going down slopes (rather than falling down them in little hops)
+        let grounded = delta_x != 0.0 && self.entity.velocity.1 >= 0.0 &&
+            tile_map.get_ground_distance(self.entity.position.0 + offset_x, self.entity.position.1 + offset_y, width, height, 0.01).is_some();
         for _ in 0..100 {
             let new_x = self.entity.position.0 + delta_x * 0.01;
             let new_y = self.entity.position.1 + delta_y * 0.01;
 
-            if tile_map.check_aabb_collision(new_x + hitbox.offset.0 as f32, new_y + hitbox.offset.1 as f32, hitbox.size.0 as f32, hitbox.size.1 as f32) {
+            // platforms only catch the player coming down onto them
+            if delta_y > 0.0 && !drop_through {
+                let old_bottom = self.entity.position.1 + offset_y + height;
+                if tile_map.check_platform_landing(new_x + offset_x, width, old_bottom, old_bottom + delta_y * 0.01) {
+                    return true;
+                }
+            }
+            if tile_map.check_aabb_collision(new_x + offset_x, new_y + offset_y, width, height) {
                 if delta_x != 0.0 && delta_y.abs() <= 0.01 {
-                    let mut jumped = false;
-                    // trying to jump up the block if it's 1 high
-                    for y in 1..=18 {
-                        let test_y = new_y - y as f32 * 0.5;
-                        if !tile_map.check_aabb_collision(new_x + hitbox.offset.0 as f32, test_y + hitbox.offset.1 as f32, hitbox.size.0 as f32, hitbox.size.1 as f32) {
-                            self.entity.position.0 = new_x;
-                            self.entity.position.1 = test_y;
-                            jumped = true;
-                            break;
-                        }
+                    // stepping up onto whatever was walked into (slopes rise a little each step, a full ledge all at once)
+                    let step = tile_map.get_step_height(new_x + offset_x, new_y + offset_y, width, height);
+                    let stepped_y = new_y - step;
+                    if step > MAX_STEP_HEIGHT || tile_map.check_aabb_collision(new_x + offset_x, stepped_y + offset_y, width, height) {
+                        return true;
                     }
-                    if !jumped { return true; }
+                    self.entity.position.0 = new_x;
+                    self.entity.position.1 = stepped_y;
                 } else { return true; }
             } else {
                 self.entity.position.0 = new_x;
                 self.entity.position.1 = new_y;
+                if grounded && let Some(distance) = tile_map.get_ground_distance(new_x + offset_x, new_y + offset_y, width, height, (delta_x * 0.01).abs() + 0.01) {
+                    self.entity.position.1 += distance;
+                }
             }
         } false
     }
@@ -149,14 +304,13 @@ impl Player {
         self.entity.sprite.update_frame(timer.delta_time);  // this is the best place to do this ig
 
         self.player_data.inventory.update_key_events(
-            timer,
             event_handler,
             tile_map,
             screen_size,
             &self.key_bindings,
             ui_manager,
             entity_manager,
-            &self.entity.position,
+            &self.get_center(),
         )?;
         
         self.entity.velocity.0 = lerp(self.entity.velocity.0, 0.0, 50.0 * timer.delta_time as f32);
@@ -168,30 +322,58 @@ impl Player {
         if KeyBindings::check_true(&self.key_bindings.left, &raw_keys_held, &event_handler.mods_held) {
             self.entity.velocity.0 = lerp(self.entity.velocity.0, -300.0, 50.0 * timer.delta_time as f32);
         }
-        if KeyBindings::check_true(&self.key_bindings.down, &raw_keys_held, &event_handler.mods_held) {
-            self.entity.velocity.1 = lerp(self.entity.velocity.1, 300.0, 50.0 * timer.delta_time as f32);
+        let down_held = KeyBindings::check_true(&self.key_bindings.down, &raw_keys_held, &event_handler.mods_held);
+        let up_held = KeyBindings::check_true(&self.key_bindings.jump, &raw_keys_held, &event_handler.mods_held);
+
+        // grabbing onto a ladder or rope by pressing up or down while in front of it
+        let hitbox = self.entity.sprite.get_hitbox();
+        let on_climbable = tile_map.check_climbable(
+            self.entity.position.0 + hitbox.offset.0 as f32, self.entity.position.1 + hitbox.offset.1 as f32, hitbox.size.0 as f32, hitbox.size.1 as f32
+        );
+        if !on_climbable {
+            self.climbing = false;
+        } else if up_held || down_held {
+            self.climbing = true;
         }
-        if KeyBindings::check_true(&self.key_bindings.jump, &raw_keys_held, &event_handler.mods_held) {
-            if self.entity.velocity.1.abs() < 0.1 {  // only allow jumping if the player is on the ground
+
+        if self.climbing {
+            self.entity.velocity.1 = match (up_held, down_held) {
+                (true, false) => -CLIMB_SPEED,
+                (false, true) => CLIMB_SPEED,
+                _ => 0.0,
+            };
+        } else {
+            if down_held {
+                self.entity.velocity.1 = lerp(self.entity.velocity.1, 300.0, 50.0 * timer.delta_time as f32);
+            }
+            if up_held && self.entity.velocity.1.abs() < 0.1 {  // only allow jumping if the player is on the ground
                 self.entity.velocity.1 = -400.0;  // todo! actually check that there is a block beneath the player
+                //self.entity.velocity.1 = lerp(self.entity.velocity.1, -300.0, 50.0 * timer.delta_time as f32);
             }
-            //self.entity.velocity.1 = lerp(self.entity.velocity.1, -300.0, 50.0 * timer.delta_time as f32);
         }
         self.move_player(
             timer.delta_time as f32 * self.entity.velocity.0,
             0.0,
-            tile_map
+            tile_map,
+            down_held,
         );
         let collided = self.move_player(
             0.0,
             timer.delta_time as f32 * self.entity.velocity.1,
-            tile_map
+            tile_map,
+            down_held,
         );
-        if !collided {  // gravity
+        if collided {
+            self.entity.velocity.1 = 0.0;
+        } else if !self.climbing {  // gravity
             self.entity.velocity.1 = (self.entity.velocity.1 + 1300.0 * timer.delta_time as f32).min(500.0);
-        } else { self.entity.velocity.1 = 0.0; }
+        }
 
-        if event_handler.keys_held.contains(&sdl2::keyboard::Keycode::Z) {
+        // ctrl (or cmd on mac) turns z into undo rather than zooming
+        let control_held = event_handler.mods_held.iter().any(|key_mod| key_mod.intersects(
+            sdl2::keyboard::Mod::LCTRLMOD | sdl2::keyboard::Mod::RCTRLMOD | sdl2::keyboard::Mod::LGUIMOD | sdl2::keyboard::Mod::RGUIMOD
+        ));
+        if event_handler.keys_held.contains(&sdl2::keyboard::Keycode::Z) && !control_held {
             if event_handler.mods_held.contains(&sdl2::keyboard::Mod::LALTMOD) {
                 self.camera.zoom += 0.075 * timer.delta_time as f32;
             } else {
@@ -199,6 +381,8 @@ impl Player {
             }
         }
         
+        self.update_builder_mode(event_handler, tile_map, control_held)?;
+
         // tempory tile deletion
         if let ButtonState::Pressed | ButtonState::Held = event_handler.mouse.left {
             let mouse_x = self.camera.x - screen_size.0 as f32 * 0.5 * self.camera.zoom + event_handler.mouse.position.0 as f32 * self.camera.zoom;
@@ -206,7 +390,7 @@ impl Player {
             let tile_x = (mouse_x / 8.0 - 1.0).floor() as usize;
             let tile_y = (mouse_y / 8.0 - 0.5).floor() as usize;
             if tile_x < tile_map.get_map_width() && tile_y < tile_map.get_map_height() {
-                self.player_data.inventory.left_click_item(tile_x, tile_y, tile_map, event_handler, ui_manager, entity_manager, rand_state)?;
+                self.player_data.inventory.left_click_item(tile_x, tile_y, tile_map, event_handler, ui_manager, entity_manager, rand_state, timer.delta_time, self.game_mode)?;
             }
         }
         if let ButtonState::Pressed | ButtonState::Held = event_handler.mouse.right {
@@ -215,9 +399,31 @@ impl Player {
             let tile_x = (mouse_x / 8.0 - 1.0).floor() as usize;
             let tile_y = (mouse_y / 8.0 - 0.5).floor() as usize;
             if tile_x < tile_map.get_map_width() && tile_y < tile_map.get_map_height() {
-                self.player_data.inventory.right_click_item(tile_x, tile_y, tile_map, event_handler, ui_manager, entity_manager)?;
+                // right clicking a bed sleeps in it (and a switch flips it, or a door opens) rather than building over it
+                if tile_map.is_bed(tile_x, tile_y) {
+                    if matches!(event_handler.mouse.right, ButtonState::Pressed) {
+                        self.respawn_point = Some((self.dimension, tile_x, tile_y));
+                    }
+                } else if tile_map.is_switch(tile_x, tile_y) && !self.is_showing_wires() {
+                    if matches!(event_handler.mouse.right, ButtonState::Pressed) {
+                        tile_map.use_switch(tile_x, tile_y)?;
+                    }
+                } else if tile_map.is_door(tile_x, tile_y) && !self.is_showing_wires() {
+                    // a door can't be shut on top of the player
+                    let closing = tile_map.get_collision_shape(tile_x, tile_y) != CollisionShape::Full;
+                    if matches!(event_handler.mouse.right, ButtonState::Pressed) && !(closing && self.overlaps_tile(tile_x, tile_y)) {
+                        tile_map.toggle_door(tile_x, tile_y)?;
+                    }
+                } else {
+                    self.player_data.inventory.right_click_item(tile_x, tile_y, tile_map, event_handler, ui_manager, entity_manager)?;
+                }
             }
         }
+        // the edits from a whole drag are grouped together, so the group only ends once both buttons are let go
+        if !matches!(event_handler.mouse.left, ButtonState::Pressed | ButtonState::Held) &&
+           !matches!(event_handler.mouse.right, ButtonState::Pressed | ButtonState::Held) {
+            tile_map.end_edit_group();
+        }
         
         // smooth camera movement!
         self.camera.x = lerp(
@@ -231,8 +437,8 @@ impl Player {
             10.0 * timer.delta_time as f32,
         );
 
-        if let Some(entity_light) = tile_map.entity_lights.iter_mut().find(|(ident,_)| &**ident == "Player") {
-            entity_light.1.position = (self.entity.position.0 - 4.0, self.entity.position.1 - 4.0);
+        if let Some(light) = self.light {
+            tile_map.move_entity_light(light, (self.entity.position.0 - 4.0, self.entity.position.1 - 4.0));
         }
 
         Ok(())
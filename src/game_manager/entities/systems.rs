@@ -0,0 +1,461 @@
+use rand::Rng;
+
+use crate::game_manager::entities::components::{AiState, Behaviour, EntityId, PhysicsBody, Transform};
+use crate::game_manager::entities::manager::{EntityManager, ItemDrop};
+use crate::game_manager::entities::mobs::SpawnConditions;
+use crate::game_manager::entities::player::player::{CameraTransform, MAX_STEP_HEIGHT};
+use crate::game_manager::world::tile_map::{TileMap, TileMapError, liquids::LiquidType, registry::get_tile_properties};
+use crate::shaders::shader_loader::MAX_ENTITIES;
+
+/// How far an entity moves in each collision step (in pixels), so nothing fast can skip through a tile
+static COLLISION_STEP: f32 = 0.5;
+/// How close (in pixels) the player has to be for drops to start drifting over to them
+static PULL_RANGE: f32 = 3.0 * 8.0;
+/// The damage lava does to anything with health each time it's burnt
+static LAVA_DAMAGE: f32 = 10.0;
+/// The seconds between each burn from lava
+static LAVA_BURN_TIME: f64 = 0.5;
+static HOP_SPEED: f32 = -280.0;
+/// How close (in pixels) the player has to be for hostile mobs to come after them
+static CHASE_RANGE: f32 = 20.0 * 8.0;
+/// How close (in pixels) the player has to be for timid mobs to run off
+static FLEE_RANGE: f32 = 6.0 * 8.0;
+
+/// Moves a body through the tiles, sideways then up or down, setting whether it hit a wall or landed
+fn move_body(transform: &mut Transform, body: &mut PhysicsBody, tile_map: &TileMap, delta_time: f32) {
+    let (width, height) = body.size;
+    let was_on_ground = body.on_ground;
+    body.hit_wall = false;
+    body.on_ground = false;
+
+    let delta_x = body.velocity.0 * delta_time;
+    let steps = (delta_x.abs() / COLLISION_STEP).ceil().max(1.0) as usize;
+    let step_x = delta_x / steps as f32;
+    for _ in 0..steps {
+        if step_x == 0.0 { break; }
+        let (x, y) = (transform.position.0 + step_x, transform.position.1);
+        if tile_map.check_aabb_collision(x, y, width, height) {
+            // walking up slopes and half blocks
+            let step = tile_map.get_step_height(x, y, width, height);
+            if !was_on_ground || step > MAX_STEP_HEIGHT || tile_map.check_aabb_collision(x, y - step, width, height) {
+                body.hit_wall = true;
+                body.velocity.0 = 0.0;
+                break;
+            }
+            transform.position = (x, y - step);
+        } else {
+            transform.position.0 = x;
+            // staying on the ground going down slopes
+            if was_on_ground && body.velocity.1 >= 0.0
+                && let Some(distance) = tile_map.get_ground_distance(x, y, width, height, step_x.abs() + 0.01) {
+                transform.position.1 += distance;
+            }
+        }
+    }
+
+    let delta_y = body.velocity.1 * delta_time;
+    let steps = (delta_y.abs() / COLLISION_STEP).ceil().max(1.0) as usize;
+    let step_y = delta_y / steps as f32;
+    for _ in 0..steps {
+        if step_y == 0.0 { break; }
+        let (x, y) = (transform.position.0, transform.position.1 + step_y);
+        let old_bottom = transform.position.1 + height;
+        let landed_on_platform = step_y > 0.0 && tile_map.check_platform_landing(x, width, old_bottom, old_bottom + step_y);
+        if landed_on_platform || tile_map.check_aabb_collision(x, y, width, height) {
+            body.on_ground = step_y > 0.0;
+            body.velocity.1 = 0.0;
+            break;
+        }
+        transform.position.1 = y;
+    }
+}
+
+impl EntityManager {
+    /// Runs every system over the entities for the frame
+    pub fn update(&mut self, tile_map: &mut TileMap, conditions: &SpawnConditions, delta_time: f64, rand_state: &mut dyn rand::RngCore) -> Result<(), TileMapError> {
+        self.update_mob_spawning(tile_map, conditions, delta_time, rand_state);
+        self.update_ai(conditions.player_center, delta_time, rand_state);
+        self.update_physics(tile_map, delta_time);
+        self.update_falling_tiles(tile_map, delta_time, rand_state)?;
+        self.update_pickups(delta_time);
+        self.update_hazards(tile_map);
+        self.update_health(delta_time);
+        self.update_mob_despawning(tile_map, conditions, delta_time);
+        self.update_sprites(delta_time);
+        self.update_lights(tile_map);
+        Ok(())
+    }
+
+    /// Picks what each entity with an ai does next, steering its body
+    fn update_ai(&mut self, player_center: (f32, f32), delta_time: f64, rand_state: &mut dyn rand::RngCore) {
+        for (id, ai) in self.ais.iter_mut() {
+            let (transform, body) = match (self.transforms.get(id), self.bodies.get_mut(id)) {
+                (Some(transform), Some(body)) => (transform, body),
+                _ => continue,
+            };
+            let to_player = (
+                player_center.0 - (transform.position.0 + body.size.0 * 0.5),
+                player_center.1 - (transform.position.1 + body.size.1 * 0.5),
+            );
+            let player_distance = (to_player.0 * to_player.0 + to_player.1 * to_player.1).sqrt();
+            ai.timer -= delta_time;
+            match ai.behaviour {
+                Behaviour::Pulled => {
+                    // left to fall and slide like anything else until the player comes close
+                    if player_distance < PULL_RANGE && player_distance > 0.0 {
+                        ai.direction = to_player.0.signum();
+                        body.velocity = (to_player.0 / player_distance * ai.speed, to_player.1 / player_distance * ai.speed);
+                    }
+                },
+                Behaviour::Hop => {
+                    if !body.on_ground {
+                        body.velocity.0 = ai.direction * ai.speed;
+                        continue;
+                    }
+                    // just landed, so it sits for a moment before the next leap
+                    if ai.state != AiState::Idle {
+                        ai.state = AiState::Idle;
+                        ai.timer = rand_state.random_range(0.8..2.0);
+                    }
+                    body.velocity.0 = 0.0;
+                    if ai.timer <= 0.0 {
+                        ai.state = match player_distance < CHASE_RANGE {
+                            true => AiState::Chasing,
+                            false => AiState::Wandering,
+                        };
+                        ai.direction = match ai.state {
+                            AiState::Chasing => to_player.0.signum(),
+                            _ => [-1.0, 1.0][rand_state.random_range(0..2)],
+                        };
+                        body.velocity = (ai.direction * ai.speed, HOP_SPEED);
+                    }
+                },
+                Behaviour::Chase => {
+                    if player_distance < CHASE_RANGE {
+                        ai.state = AiState::Chasing;
+                        // standing still right under (or over) the player rather than jittering back and forth
+                        ai.direction = if to_player.0.abs() > 2.0 { to_player.0.signum() } else { 0.0 };
+                    } else if ai.state == AiState::Chasing || ai.timer <= 0.0 {
+                        ai.state = AiState::Wandering;
+                        ai.timer = rand_state.random_range(2.0..5.0);
+                        ai.direction = [-1.0, 0.0, 1.0][rand_state.random_range(0..3)];
+                    }
+                    if body.hit_wall && body.on_ground {
+                        body.velocity.1 = HOP_SPEED;
+                    }
+                    body.velocity.0 = ai.direction * ai.speed;
+                },
+                Behaviour::Fly => {
+                    // bobbing up and down while flying, so it flutters rather than glides
+                    let flutter = (ai.timer * 7.0).sin() as f32 * ai.speed * 0.6;
+                    if player_distance < CHASE_RANGE {
+                        ai.state = AiState::Chasing;
+                        ai.direction = to_player.0.signum();
+                        body.velocity.1 = to_player.1.signum() * ai.speed * 0.5 + flutter;
+                    } else {
+                        if ai.state == AiState::Chasing || ai.timer <= 0.0 {
+                            ai.state = AiState::Wandering;
+                            ai.timer = rand_state.random_range(1.5..4.0);
+                            ai.direction = [-1.0, 1.0][rand_state.random_range(0..2)];
+                        }
+                        body.velocity.1 = flutter;
+                    }
+                    // turning back from walls (the flight is picked again from scratch next time anyway)
+                    if body.hit_wall {
+                        ai.direction = -ai.direction;
+                    }
+                    body.velocity.0 = ai.direction * ai.speed;
+                },
+                Behaviour::Flee => {
+                    if player_distance < FLEE_RANGE {
+                        ai.state = AiState::Fleeing;
+                        ai.direction = if to_player.0 > 0.0 { -1.0 } else { 1.0 };
+                    } else if ai.state == AiState::Fleeing || ai.timer <= 0.0 {
+                        ai.state = AiState::Wandering;
+                        ai.timer = rand_state.random_range(1.0..4.0);
+                        ai.direction = [-1.0, 0.0, 0.0, 1.0][rand_state.random_range(0..4)];
+                    }
+                    // bounding away, and hopping up anything in the way
+                    if body.on_ground && (body.hit_wall || ai.state == AiState::Fleeing) {
+                        body.velocity.1 = HOP_SPEED * 0.7;
+                    }
+                    let speed = if ai.state == AiState::Fleeing { ai.speed * 2.5 } else { ai.speed };
+                    body.velocity.0 = ai.direction * speed;
+                },
+            }
+        }
+    }
+
+    /// Applies gravity and drag, then moves every body through the tiles (falling tiles move themselves), despawning anything that falls out of the map
+    fn update_physics(&mut self, tile_map: &TileMap, delta_time: f64) {
+        // below the map is all empty, so anything that falls out would fall forever
+        let map_bottom = tile_map.get_map_height() as f32 * 8.0;
+        let mut fallen_out = Vec::new();
+        for (id, body) in self.bodies.iter_mut() {
+            if self.falling_tiles.contains_key(id) { continue; }
+            let transform = match self.transforms.get_mut(id) {
+                Some(transform) => transform,
+                None => continue,
+            };
+            if body.gravity > 0.0 {
+                body.velocity.1 = (body.velocity.1 + body.gravity * delta_time as f32).min(body.max_fall_speed);
+            }
+            if body.on_ground && body.drag > 0.0 {
+                body.velocity.0 *= (1.0 - body.drag * delta_time as f32).max(0.0);
+            }
+            move_body(transform, body, tile_map, delta_time as f32);
+            if transform.position.1 > map_bottom {
+                fallen_out.push(*id);
+            }
+        }
+        for id in fallen_out {
+            self.despawn(id);
+        }
+    }
+
+    /// Turns any unsupported gravity tiles into falling entities, then moves them and places them once they land
+    fn update_falling_tiles(&mut self, tile_map: &mut TileMap, delta_time: f64, rand_state: &mut dyn rand::RngCore) -> Result<(), TileMapError> {
+        // removing a tile re-queues the one above, so whole columns of sand come down together
+        let mut checks = tile_map.take_gravity_checks();
+        while let Some((tile_x, tile_y)) = checks.pop() {
+            let tile = tile_map.get_tile(tile_x, tile_y, 0);
+            if let Some((placed_tile, texture)) = get_tile_properties(tile).gravity {
+                // past the entity limit it's left hanging (it's checked again once anything around it changes)
+                if self.get_entity_count() >= MAX_ENTITIES { continue; }
+                if tile_y + 1 < tile_map.get_map_height() && !tile_map.is_solid(tile_x, tile_y + 1) {
+                    tile_map.change_tile(tile_x, tile_y, 0, 0)?;
+                    self.new_falling_tile(placed_tile, texture, tile_x, tile_y);
+                }
+            }
+            checks.extend(tile_map.take_gravity_checks());
+        }
+
+        let mut landed = Vec::new();
+        for (id, falling_tile) in &self.falling_tiles {
+            let (transform, body) = match (self.transforms.get_mut(id), self.bodies.get_mut(id)) {
+                (Some(transform), Some(body)) => (transform, body),
+                _ => continue,
+            };
+            body.velocity.1 = (body.velocity.1 + body.gravity * delta_time as f32).min(body.max_fall_speed);
+            let new_y = transform.position.1 + body.velocity.1 * delta_time as f32;
+            let tile_x = (transform.position.0 / 8.0) as usize;
+            let current_row = (transform.position.1 / 8.0) as usize;
+            let bottom_row = ((new_y + 8.0) / 8.0) as usize;
+
+            // checking every row passed through so fast falls can't skip over a tile
+            let landed_row = (current_row + 1..=bottom_row)
+                .find(|row| *row >= tile_map.get_map_height() || tile_map.is_solid(tile_x, *row))
+                .map(|row| row - 1);
+            match landed_row {
+                Some(row) => landed.push((*id, falling_tile.tile, tile_x, row)),
+                None => transform.position.1 = new_y,
+            }
+        }
+
+        for (id, tile, tile_x, landed_row) in landed {
+            self.despawn(id);
+            if tile_map.get_tile(tile_x, landed_row, 0) == 0 {
+                tile_map.change_tile(tile_x, landed_row, 0, tile)?;
+            } else {
+                // landed in something like a torch, so it gets crushed into its drops instead
+                if let Some(tile_drop) = &get_tile_properties(tile).drops {
+                    for (item, texture) in tile_drop.get_dropped_tile_info(rand_state) {
+                        self.new_drop(ItemDrop::Tile(texture, item), ((tile_x + 1) * 8 + 2) as u32, ((landed_row + 1) * 8 + 2) as u32);
+                    }
+                }
+            }
+        } Ok(())
+    }
+
+    /// Despawns any drops left lying around for too long
+    fn update_pickups(&mut self, delta_time: f64) {
+        let mut expired = Vec::new();
+        for (id, pickup) in self.pickups.iter_mut() {
+            pickup.lifetime -= delta_time;
+            if pickup.lifetime <= 0.0 {
+                expired.push(*id);
+            }
+        }
+        for id in expired {
+            self.despawn(id);
+        }
+    }
+
+    /// Burns anything with health that's in lava
+    fn update_hazards(&mut self, tile_map: &TileMap) {
+        let burning = self.healths.keys()
+            .filter(|id| self.get_center(**id).is_some_and(|center| {
+                let (tile_x, tile_y) = ((center.0 / 8.0).max(0.0) as usize, (center.1 / 8.0).max(0.0) as usize);
+                tile_map.liquids.get(tile_x, tile_y).liquid == LiquidType::Lava
+            }))
+            .copied()
+            .collect::<Vec<_>>();
+        for id in burning {
+            self.damage(id, LAVA_DAMAGE, LAVA_BURN_TIME);
+        }
+    }
+
+    /// Counts down the time after being hurt, and despawns anything that's run out of health
+    fn update_health(&mut self, delta_time: f64) {
+        let mut dead = Vec::new();
+        for (id, health) in self.healths.iter_mut() {
+            health.invulnerable_time = (health.invulnerable_time - delta_time).max(0.0);
+            if health.current <= 0.0 {
+                dead.push(*id);
+            }
+        }
+        for id in dead {
+            self.despawn(id);
+        }
+    }
+
+    /// Hurts the entity (unless it was only just hurt), returning whether it took the damage
+    pub fn damage(&mut self, id: EntityId, amount: f32, invulnerable_time: f64) -> bool {
+        match self.healths.get_mut(&id) {
+            Some(health) if health.invulnerable_time <= 0.0 => {
+                health.current -= amount;
+                health.invulnerable_time = invulnerable_time;
+                true
+            },
+            _ => false,
+        }
+    }
+
+    fn update_sprites(&mut self, delta_time: f64) {
+        for sprite in self.sprites.values_mut() {
+            sprite.animation_time += delta_time;
+        }
+    }
+
+    /// Keeps every entity light on the tile map following its entity around
+    fn update_lights(&mut self, tile_map: &mut TileMap) {
+        for handle in self.removed_lights.drain(..) {
+            tile_map.remove_entity_light(handle);
+        }
+        for (id, light) in self.lights.iter_mut() {
+            let transform = match self.transforms.get(id) {
+                Some(transform) => transform,
+                None => continue,
+            };
+            // lit from the middle of the body (or just the position for anything without one)
+            let (width, height) = self.bodies.get(id).map(|body| body.size).unwrap_or((0.0, 0.0));
+            let position = (transform.position.0 + width * 0.5, transform.position.1 + height * 0.5);
+            match light.handle {
+                Some(handle) => tile_map.move_entity_light(handle, position),
+                None => light.handle = Some(tile_map.add_entity_light(light.create_light(position))),
+            }
+        }
+    }
+
+    pub fn get_render(&self, camera: &CameraTransform, screen_width: (u32, u32)) -> Vec<(u32, u16, i16, i16, u16, u32)> {
+        let mut render_data = vec![];
+        let edge_x = (screen_width.0 as f32) * 0.5 * camera.zoom;
+        let edge_y = (screen_width.1 as f32) * 0.5 * camera.zoom;
+
+        // the gpu pipeline actually does work a lot better, even if the algerithm is slower on paper
+        for (id, sprite) in &self.sprites {
+            let transform = match self.transforms.get(id) {
+                Some(transform) => transform,
+                None => continue,
+            };
+            let texture = sprite.get_frame_texture();
+            for cell_y in 0..sprite.cells.1 {
+                for cell_x in 0..sprite.cells.0 {
+                    // offset by a tile to line up with how the tiles themselves are drawn
+                    let position = (
+                        transform.position.0 + 8.0 + cell_x as f32 * 8.0 - camera.x,
+                        transform.position.1 + 8.0 + cell_y as f32 * 8.0 - camera.y,
+                    );
+                    if position.0 < -edge_x - 8.0 || position.1 < -edge_y - 8.0 || position.0 > edge_x || position.1 > edge_y {
+                        continue;
+                    }
+                    render_data.push((
+                        texture + cell_x + cell_y * sprite.cells.0, 0,
+                        (position.0 * 100.0) as i16, (position.1 * 100.0) as i16,
+                        0, sprite.depth,
+                    ));
+                }
+            }
+        } render_data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_manager::entities::player::items::{Item, ItemType};
+    use crate::game_manager::world::tile_map::{SAND_IDS, STONE_IDS};
+
+    /// The player far enough away that nothing gets pulled over to them (or spawns around them)
+    static FAR_AWAY: SpawnConditions = SpawnConditions {
+        player_center: (-1000.0, -1000.0),
+        sky_brightness: 1.0,
+        is_day: true,
+        spawn_rate: 1.0,
+    };
+
+    /// Runs the systems until nothing is left falling (or it gives up after a few seconds)
+    fn settle(entity_manager: &mut EntityManager, tile_map: &mut TileMap) {
+        for _ in 0..200 {
+            entity_manager.update(tile_map, &FAR_AWAY, 0.02, &mut rand::rng()).unwrap();
+            if entity_manager.falling_tiles.is_empty() { return; }
+        }
+    }
+
+    #[test]
+    fn drops_despawn_once_they_expire() {
+        let mut tile_map = TileMap::test_tile_map(8, 8);
+        let mut entity_manager = EntityManager::new();
+        // a floor for it to lie on, so it doesn't just fall out of the map
+        tile_map.change_tile(4, 5, 0, STONE_IDS[0]).unwrap();
+        let drop = ItemDrop::Tile(13, Item::new(11, Some(ItemType::Block(89)), String::from("Sand"), 1, 512));
+        entity_manager.new_drop(drop, 40, 40);
+        let id = *entity_manager.pickups.keys().next().unwrap();
+        entity_manager.pickups.get_mut(&id).unwrap().lifetime = 0.5;
+
+        entity_manager.update(&mut tile_map, &FAR_AWAY, 0.25, &mut rand::rng()).unwrap();
+        assert!(entity_manager.is_alive(id));
+        entity_manager.update(&mut tile_map, &FAR_AWAY, 0.3, &mut rand::rng()).unwrap();
+        assert!(!entity_manager.is_alive(id));
+    }
+
+    #[test]
+    fn falling_tiles_land_on_the_ground() {
+        let mut tile_map = TileMap::test_tile_map(8, 8);
+        let mut entity_manager = EntityManager::new();
+        tile_map.change_tile(4, 7, 0, STONE_IDS[0]).unwrap();
+        tile_map.change_tile(4, 2, 0, SAND_IDS[0]).unwrap();
+        settle(&mut entity_manager, &mut tile_map);
+
+        assert!(entity_manager.falling_tiles.is_empty());
+        assert_eq!(tile_map.get_tile(4, 2, 0), 0);
+        assert!(SAND_IDS.contains(&tile_map.get_tile(4, 6, 0)));
+        assert_eq!(entity_manager.get_entity_count(), 0);
+    }
+
+    #[test]
+    fn falling_tiles_are_crushed_landing_in_a_torch() {
+        let mut tile_map = TileMap::test_tile_map(8, 8);
+        let mut entity_manager = EntityManager::new();
+        tile_map.change_tile(4, 7, 0, STONE_IDS[0]).unwrap();
+        tile_map.change_tile(4, 6, 0, 173).unwrap();
+        tile_map.change_tile(4, 2, 0, SAND_IDS[0]).unwrap();
+        settle(&mut entity_manager, &mut tile_map);
+
+        assert!(entity_manager.falling_tiles.is_empty());
+        assert_eq!(tile_map.get_tile(4, 6, 0), 173);
+        assert!(!entity_manager.pickups.is_empty());
+    }
+
+    #[test]
+    fn anything_falling_out_of_the_map_despawns() {
+        let mut tile_map = TileMap::test_tile_map(8, 8);
+        let mut entity_manager = EntityManager::new();
+        let drop = ItemDrop::Tile(13, Item::new(11, Some(ItemType::Block(89)), String::from("Sand"), 1, 512));
+        entity_manager.new_drop(drop, 40, 40);
+        for _ in 0..200 {
+            entity_manager.update(&mut tile_map, &FAR_AWAY, 0.02, &mut rand::rng()).unwrap();
+        }
+        assert_eq!(entity_manager.get_entity_count(), 0);
+    }
+}
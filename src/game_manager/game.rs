@@ -1,8 +1,10 @@
 use std::rc::Rc;
 
 use crate::game_manager::entities::manager::EntityManager;
+use crate::game_manager::entities::mobs::{CONTACT_KNOCKBACK, SpawnConditions};
 use crate::game_manager::entities::player::{player::*, player_ui::PlayerUiManager};
-use crate::game_manager::world::{world_gen::*, tile_map::*};
+use crate::game_manager::world::{world_gen::{*, background::GenerationJob}, tile_map::*, day_cycle::DayCycle, weather::{Precipitation, Weather}};
+use crate::game_manager::world::tile_map::wiring::TRAP_LAUNCH_SPEED;
 use crate::shaders::shader_loader::MAX_ENTITIES;
 use crate::textures::textures::get_texture_atlas;
 use crate::logging::logging::{Log, LogType, Logs};
@@ -12,16 +14,23 @@ static MAX_ITEM_TEXTURES: usize = u16::MAX as usize;
 
 static MAX_MAP_TEXTURES: usize = u16::MAX as usize;
 
+/// The seed every new world uses (until the world creation menu lets it be picked)
+pub static DEFAULT_SEED: f32 = 123456.234;
+
 /// The main game structure
 pub struct Game {
     pub player: Player,
     tile_map: TileMapManager,
-    world_generator: WorldGenerator,
+    world_generators: Vec<WorldGenerator>,  // one for each dimension (indexed by the dimension)
+    day_cycle: DayCycle,
+    weather: Weather,
+    precipitation: Precipitation,  // the rain and snow falling around the player (not saved)
 
     // if a lot of unique ui elements are added, this could be abstracted into its own ui manager struct
     pub player_ui_manager: PlayerUiManager,  // storing this external to player since it can't be saved (and really doesn't need to be)
 
-    pub(crate) entity_manager: EntityManager,
+    entity_managers: Vec<EntityManager>,  // one for each dimension (indexed by the dimension), so drops and falling tiles stay where they were left
+    travel_generation: Option<(Dimension, GenerationJob)>,  // a dimension being generated in the background for its first visit (the player stays put until it's done)
 
     pub(crate) random_state: rand::rngs::ThreadRng,
 
@@ -35,14 +44,25 @@ impl Game {
         let encoded: Vec<u8> = bincode::encode_to_vec(&self.player, config).unwrap();
         std::fs::write(format!("{}/game_version_{}/player/player.bin", path_prefix, version), &encoded)?;  // just dump to file
 
-        let encoded: Vec<u8> = bincode::encode_to_vec(&self.tile_map, config).unwrap();
-        std::fs::write(format!("{}/game_version_{}/world_save/tile_map.bin", path_prefix, version), &encoded)?;  // just dump to file
+        // each dimension gets its own folder with its generator and entities (dimensions that haven't been visited yet have no map to save)
+        for dimension in Dimension::ALL {
+            let dimension_path = format!("{}/game_version_{}/world_save/dimensions/{}", path_prefix, version, dimension.get_name());
+            std::fs::create_dir_all(&dimension_path)?;
+            let encoded: Vec<u8> = bincode::encode_to_vec(&self.world_generators[dimension as usize], config).unwrap();
+            std::fs::write(format!("{}/world_generator.bin", dimension_path), &encoded)?;  // just dump to file
+            if let Some(tile_map) = self.tile_map.get_current_map_ref(dimension) {
+                let encoded: Vec<u8> = bincode::encode_to_vec(tile_map, config).unwrap();
+                std::fs::write(format!("{}/tile_map.bin", dimension_path), &encoded)?;  // just dump to file
+            }
+            let encoded: Vec<u8> = bincode::encode_to_vec(&self.entity_managers[dimension as usize], config).unwrap();
+            std::fs::write(format!("{}/entities.bin", dimension_path), &encoded)?;  // just dump to file
+        }
 
-        let encoded: Vec<u8> = bincode::encode_to_vec(&self.world_generator, config).unwrap();
-        std::fs::write(format!("{}/game_version_{}/world_save/world_generator.bin", path_prefix, version), &encoded)?;  // just dump to file
+        let encoded: Vec<u8> = bincode::encode_to_vec(&self.day_cycle, config).unwrap();
+        std::fs::write(format!("{}/game_version_{}/world_save/day_cycle.bin", path_prefix, version), &encoded)?;  // just dump to file
 
-        let encoded: Vec<u8> = bincode::encode_to_vec(&self.entity_manager, config).unwrap();
-        std::fs::write(format!("{}/game_version_{}/world_save/entities/entity.bin", path_prefix, version), &encoded)?;  // just dump to file
+        let encoded: Vec<u8> = bincode::encode_to_vec(&self.weather, config).unwrap();
+        std::fs::write(format!("{}/game_version_{}/world_save/weather.bin", path_prefix, version), &encoded)?;  // just dump to file
 
         logs.push(Log {
             message: format!("Saved game in {} seconds", start.elapsed().as_secs_f64()),
@@ -52,6 +72,12 @@ impl Game {
         Ok(())
     }
 
+    /// Creates the folders a new world gets saved into (the dimension folders are made while saving)
+    pub fn create_save_directories(path_prefix: &str, version: &str) -> Result<(), std::io::Error> {
+        std::fs::create_dir_all(format!("{}/game_version_{}/world_save", path_prefix, version))?;
+        std::fs::create_dir_all(format!("{}/game_version_{}/player", path_prefix, version))
+    }
+
     // the version parameter should hopefully make it easier to update old saves into newer versions by targeting them specifically
     fn file_loader<T: bincode::Decode<()>>(path: &str) -> Result<T, GameError> {
         let config = bincode::config::standard();
@@ -66,16 +92,50 @@ impl Game {
         Ok(decoded)
     }
 
+    /// Makes sure the world was saved by this version of the game (older saves are laid out and encoded differently, and there's no migrating them yet)
+    fn check_save_version(path_prefix: &str, version: &str) -> Result<(), GameError> {
+        if std::path::Path::new(&format!("{}/game_version_{}", path_prefix, version)).exists() { return Ok(()); }
+        // each version saves into a folder named after it
+        let saved_versions = std::fs::read_dir(path_prefix)
+            .map(|entries| entries
+                .filter_map(|entry| entry.ok()?.file_name().into_string().ok()?.strip_prefix("game_version_").map(String::from))
+                .collect::<Vec<_>>())
+            .unwrap_or_default();
+        Err(GameError {
+            message: match saved_versions.is_empty() {
+                true => format!("[World Load Error] {} has no save for version {}", path_prefix, version),
+                false => format!("[World Load Error] {} was saved with version {}, which can't be opened by version {}", path_prefix, saved_versions.join(", "), version),
+            },
+            severity: Severity::High,
+        })
+    }
+
     // the version parameter should hopefully make it easier to update old saves into newer versions by targeting them specifically
     pub fn from_save(logs: &mut Logs, path_prefix: &str, version: &str, font_atlas: Rc<Vec<[u32; 256]>>) -> Result<Self, GameError> {
+        Self::check_save_version(path_prefix, version)?;
         let player: Player = Self::file_loader(&format!("{}/game_version_{}/player/player.bin", path_prefix, version))?;
-        let tile_map: TileMapManager = Self::file_loader(&format!("{}/game_version_{}/world_save/tile_map.bin", path_prefix, version))?;
-        let world_generator: WorldGenerator = Self::file_loader(&format!("{}/game_version_{}/world_save/world_generator.bin", path_prefix, version))?;
-        let entity: EntityManager = Self::file_loader(&format!("{}/game_version_{}/world_save/entities/entity.bin", path_prefix, version))?;
+        let mut tile_map = TileMapManager::new();
+        let mut world_generators = Vec::with_capacity(Dimension::TOTAL as usize);
+        let mut entity_managers = Vec::with_capacity(Dimension::TOTAL as usize);
+        for dimension in Dimension::ALL {
+            let dimension_path = format!("{}/game_version_{}/world_save/dimensions/{}", path_prefix, version, dimension.get_name());
+            world_generators.push(Self::file_loader::<WorldGenerator>(&format!("{}/world_generator.bin", dimension_path))?);
+            // a missing map just means the dimension was never visited
+            let tile_map_path = format!("{}/tile_map.bin", dimension_path);
+            if std::path::Path::new(&tile_map_path).exists() {
+                tile_map.replace_tile_map(dimension, Self::file_loader(&tile_map_path)?);
+            }
+            entity_managers.push(Self::file_loader(&format!("{}/entities.bin", dimension_path))?);
+        }
+        let day_cycle: DayCycle = Self::file_loader(&format!("{}/game_version_{}/world_save/day_cycle.bin", path_prefix, version))?;
+        let weather: Weather = Self::file_loader(&format!("{}/game_version_{}/world_save/weather.bin", path_prefix, version))?;
         Ok(Game {
             player,
             tile_map,
-            world_generator,
+            world_generators,
+            day_cycle,
+            weather,
+            precipitation: Precipitation::new(),
             player_ui_manager: PlayerUiManager::new({
                 let mut total_textures_loaded = 0;
                 let textures = get_texture_atlas::<MAX_ITEM_TEXTURES, 256>("textures/items/", (16, 16), vec![[0u32; 256]; MAX_ITEM_TEXTURES], &mut total_textures_loaded)
@@ -92,7 +152,8 @@ impl Game {
                 message: e.details,
                 severity: Severity::Fatal
             })?,
-            entity_manager: entity,
+            entity_managers,
+            travel_generation: None,
             random_state: rand::rng(),
             mini_map_textures: {
                 let mut total_textures_loaded = 0;
@@ -113,20 +174,39 @@ impl Game {
     }
 
     pub fn new(logs: &mut Logs, font_atlas: Rc<Vec<[u32; 256]>>) -> Result<Self, GameError> {
-        let world_generator = WorldGenerator::new(123456.23456);
-        let mut tile_map_manager = TileMapManager::new();
         // todo! temporary for now; eventually a world creation menue will be added
-        tile_map_manager.replace_tile_map(
-            Dimension::Overworld,
-            TileMap::new(4095, 1024, Some(&world_generator), logs)?
-        );
-        tile_map_manager.get_current_map(Dimension::Overworld)
+        Self::generate(logs, font_atlas, DEFAULT_SEED, Dimension::Overworld.get_map_size())
+    }
+
+    /// Creates a brand new world from the seed, with an overworld of the given size (in tiles)
+    pub fn generate(logs: &mut Logs, font_atlas: Rc<Vec<[u32; 256]>>, seed: f32, (width, height): (usize, usize)) -> Result<Self, GameError> {
+        let overworld = TileMap::new(width, height, Some(&WorldGenerator::new(seed, Dimension::Overworld)), logs)?;
+        Self::from_generated(logs, font_atlas, seed, overworld)
+    }
+
+    /// Creates a new world around an overworld that's already been generated from the seed (such as by a background generation job)
+    pub fn from_generated(logs: &mut Logs, font_atlas: Rc<Vec<[u32; 256]>>, seed: f32, overworld: TileMap) -> Result<Self, GameError> {
+        let world_generators: Vec<WorldGenerator> = Dimension::ALL.iter()
+            .map(|dimension| WorldGenerator::new(seed, *dimension))
+            .collect();
+        let mut tile_map_manager = TileMapManager::new();
+        // the other dimensions are only generated once they're first travelled to
+        tile_map_manager.replace_tile_map(Dimension::Overworld, overworld);
+        let mut player = Player::new();
+        let (spawn_x, spawn_y) = tile_map_manager.get_current_map_ref(Dimension::Overworld)
+            .ok_or_else(|| GameError { message: String::from("Failed to get current map"), severity: Severity::Fatal })?
+            .get_spawn_point();
+        player.teleport_to_tile(spawn_x, spawn_y);
+        player.light = Some(tile_map_manager.get_current_map(Dimension::Overworld)
             .ok_or_else(|| GameError { message: String::from("Failed to get current map"), severity: Severity::Fatal })?
-            .add_entity_light(String::from("Player"), (0.0, 0.0), (225, 225, 128, 0.65));
+            .add_entity_light(player.create_light()));
         Ok(Game {
-            player: Player::new(),
+            player,
             tile_map: tile_map_manager,
-            world_generator: world_generator,
+            world_generators,
+            day_cycle: DayCycle::new(),
+            weather: Weather::new(seed),
+            precipitation: Precipitation::new(),
             player_ui_manager: PlayerUiManager::new({
                 let mut total_textures_loaded = 0;
                 let textures = get_texture_atlas::<MAX_ITEM_TEXTURES, 256>("textures/items/", (16, 16), vec![[0u32; 256]; MAX_ITEM_TEXTURES], &mut total_textures_loaded)
@@ -143,7 +223,8 @@ impl Game {
                 message: e.details,
                 severity: Severity::Fatal
             })?,
-            entity_manager: EntityManager::new(),
+            entity_managers: Dimension::ALL.iter().map(|_| EntityManager::new()).collect(),
+            travel_generation: None,
             random_state: rand::rng(),
             mini_map_textures: {
                 let mut total_textures_loaded = 0;
@@ -169,28 +250,86 @@ impl Game {
         screen_size: (u32, u32),
         logs: &mut Logs,
     ) -> Result<(), GameError> {
-        if let Some(tile_map) = self.tile_map.get_current_map(Dimension::Overworld) {
+        let mut entered_portal = false;
+        let mut fell_out = false;
+        let respawn_point = self.player.respawn_point;
+        let spawn_conditions = self.get_spawn_conditions();
+        if let Some(tile_map) = self.tile_map.get_current_map(self.player.dimension) {
             if tile_map.entity_lights.len() > 256 {
                 logs.push(Log {
                     message: format!("[Memory Warning] Total dynamic lights has exceeded a reasonable count. Current count: {}", tile_map.entity_lights.len()),
                     level: crate::logging::logging::LoggingError::Warning,
                 }, 9, LogType::Memory);
             }
+            self.day_cycle.update(timer.delta_time);
+            if let Some(state) = self.weather.update(timer.delta_time) {
+                logs.push(Log {
+                    message: format!("The weather changed to {:?}", state),
+                    level: crate::logging::logging::LoggingError::Info,
+                }, 30, LogType::Information);
+            }
+            tile_map.update_liquids(timer.delta_time)?;
+            tile_map.update_tile_damage(timer.delta_time);
+            tile_map.update_random_ticks(timer.delta_time, &mut self.random_state)?;
+            let entity_manager = &mut self.entity_managers[self.player.dimension as usize];
+            entity_manager.update(tile_map, &spawn_conditions, timer.delta_time, &mut self.random_state)?;
             self.player.update_key_events(
                 timer,
                 event_handler,
                 tile_map,
                 screen_size,
                 &mut self.player_ui_manager,
-                &mut self.entity_manager,
+                entity_manager,
                 &mut self.random_state,
             )?;
+            // the weather only reaches the overworld (the sky islands are above the clouds)
+            if self.player.dimension == Dimension::Overworld {
+                self.precipitation.update(
+                    timer.delta_time,
+                    &self.weather,
+                    tile_map,
+                    &self.player.camera,
+                    screen_size,
+                    &mut self.random_state,
+                );
+            } else { self.precipitation.clear(); }
+            // anything standing on a pressure plate holds it down, and a spike trap firing underneath throws the player up
+            // (wired doors also wait for the player to get out of the way before closing, the same as when they're clicked shut)
+            let (feet_x, feet_y) = self.player.get_feet_tile();
+            let mut occupied_tiles = entity_manager.get_occupied_tiles();
+            occupied_tiles.push((feet_x, feet_y));
+            tile_map.update_wiring(timer.delta_time, &occupied_tiles, &self.player.get_overlapped_tiles())?;
+            if tile_map.is_active_trap(feet_x, feet_y + 1) && self.player.entity.velocity.1 >= 0.0 {
+                self.player.entity.velocity.1 = TRAP_LAUNCH_SPEED;
+            }
+            // hostile mobs knock the player back away
+            let (hitbox_x, hitbox_y, hitbox_width, hitbox_height) = self.player.get_hitbox_bounds();
+            if let Some(direction) = entity_manager.get_hostile_contact(hitbox_x, hitbox_y, hitbox_width, hitbox_height)
+                && self.player.entity.velocity.1 >= 0.0 {
+                self.player.entity.velocity = (direction * CONTACT_KNOCKBACK.0, CONTACT_KNOCKBACK.1);
+            }
+            entered_portal = self.player.check_portal(tile_map);
+            fell_out = self.player.has_fallen_out(tile_map);
         }
+        if entered_portal {
+            self.travel(self.player.dimension.get_portal_destination(), logs)?;
+        } else if fell_out || self.player.wants_respawn(event_handler) {
+            self.respawn(logs)?;
+        }
+        if let Some((dimension, x, y)) = self.player.respawn_point && respawn_point != self.player.respawn_point {
+            logs.push(Log {
+                message: format!("Set the respawn point to the bed at ({}, {}) in the {} dimension", x, y, dimension.get_name()),
+                level: crate::logging::logging::LoggingError::Info,
+            }, 28, LogType::Information);
+        }
+        self.update_travel_generation(logs)?;
         
         // doing some checks and possibly logging anything abnormal or that could be logged
-        if self.entity_manager.get_entity_count() > MAX_ENTITIES {
+        // every dimension's entities are kept around (and saved), not just the ones being drawn
+        let entity_count = self.entity_managers.iter().map(|entity_manager| entity_manager.get_entity_count()).sum::<usize>();
+        if entity_count > MAX_ENTITIES {
             logs.push(Log {
-                message: format!("[Memory Warning] Total entity count is higher than expected: {}", self.entity_manager.get_entity_count()),
+                message: format!("[Memory Warning] Total entity count is higher than expected: {}", entity_count),
                 level: crate::logging::logging::LoggingError::Warning,
             }, 1, LogType::Memory);
         }
@@ -241,6 +380,108 @@ impl Game {
         Ok(())
     }
 
+    /// Takes the player's light out of the dimension they're leaving
+    fn leave_dimension(&mut self) {
+        if let Some(tile_map) = self.tile_map.get_current_map(self.player.dimension) {
+            if let Some(light) = self.player.light.take() {
+                tile_map.remove_entity_light(light);
+            }
+            self.entity_managers[self.player.dimension as usize].remove_lights(tile_map);
+            // mobs only live around the player, so they're left behind
+            self.entity_managers[self.player.dimension as usize].despawn_mobs();
+        }
+    }
+
+    /// Moves the player through a portal into the dimension
+    /// A dimension that's never been visited is generated in the background first, and the trip finishes once it's done (see update_travel_generation)
+    fn travel(&mut self, destination: Dimension, logs: &mut Logs) -> Result<(), GameError> {
+        if self.tile_map.get_current_map_ref(destination).is_none() {
+            if self.travel_generation.is_none() {
+                let seed = self.world_generators[destination as usize].get_seed();
+                let job = GenerationJob::start(seed, destination, destination.get_map_size(), logs)?;
+                self.travel_generation = Some((destination, job));
+            }
+            return Ok(());
+        }
+
+        let origin_width = match self.tile_map.get_current_map_ref(self.player.dimension) {
+            Some(tile_map) => tile_map.get_map_width(),
+            None => destination.get_map_size().0,
+        };
+        let (tile_x, _) = self.player.get_tile_position();
+        self.leave_dimension();
+
+        let tile_map = self.tile_map.get_current_map(destination)
+            .ok_or_else(|| GameError { message: String::from("Failed to get destination map"), severity: Severity::Fatal })?;
+        // arriving at the same relative spot across the world, so portals built far apart lead to places far apart
+        let target_x = (tile_x as f32 / origin_width as f32 * tile_map.get_map_width() as f32) as usize;
+        let (portal_x, portal_y) = tile_map.place_arrival_portal(target_x.min(tile_map.get_map_width() - 1))?;
+        self.player.teleport_to_tile(portal_x, portal_y);
+        self.player.light = Some(tile_map.add_entity_light(self.player.create_light()));
+        self.player.arrive_through_portal(destination);
+        Ok(())
+    }
+
+    /// Checks on the dimension being generated for the player's first trip into it, finishing the trip once it's ready
+    fn update_travel_generation(&mut self, logs: &mut Logs) -> Result<(), GameError> {
+        let result = match self.travel_generation.as_mut() {
+            Some((_, job)) => match job.poll() {
+                Some(result) => result,
+                None => return Ok(()),
+            },
+            None => return Ok(()),
+        };
+        let Some((destination, job)) = self.travel_generation.take() else { return Ok(()); };
+        self.tile_map.replace_tile_map(destination, result?);
+        logs.push(Log {
+            message: format!("Generated the {} dimension in {} seconds", destination.get_name(), job.get_elapsed().as_secs_f64()),
+            level: crate::logging::logging::LoggingError::Info,
+        }, 25, LogType::Information);
+        self.travel(destination, logs)
+    }
+
+    /// The dimension being generated for the player's first trip into it, if there is one
+    pub(crate) fn get_travel_generation(&self) -> Option<&(Dimension, GenerationJob)> {
+        self.travel_generation.as_ref()
+    }
+
+    /// Sends the player back to their bed, or to the world spawn if they haven't slept in one (or it's been broken since)
+    fn respawn(&mut self, logs: &mut Logs) -> Result<(), GameError> {
+        let bed = self.player.respawn_point.filter(|(dimension, x, y)| {
+            self.tile_map.get_current_map_ref(*dimension).is_some_and(|tile_map| tile_map.is_bed(*x, *y))
+        });
+        if bed.is_none() {
+            self.player.respawn_point = None;
+        }
+        let (dimension, x, y) = match bed {
+            Some(bed) => bed,
+            None => {
+                let (x, y) = self.tile_map.get_current_map_ref(Dimension::Overworld)
+                    .ok_or_else(|| GameError { message: String::from("Failed to get the overworld map"), severity: Severity::Fatal })?
+                    .get_spawn_point();
+                (Dimension::Overworld, x, y)
+            },
+        };
+        // the respawn point is always somewhere that's already been generated (a bed that's still there, or the overworld)
+        self.leave_dimension();
+
+        let tile_map = self.tile_map.get_current_map(dimension)
+            .ok_or_else(|| GameError { message: String::from("Failed to get respawn map"), severity: Severity::Fatal })?;
+        self.player.teleport_to_tile(x, y);
+        self.player.light = Some(tile_map.add_entity_light(self.player.create_light()));
+        self.player.dimension = dimension;
+        logs.push(Log {
+            message: format!("Respawned at ({}, {}) in the {} dimension ({})", x, y, dimension.get_name(), if bed.is_some() { "bed" } else { "world spawn" }),
+            level: crate::logging::logging::LoggingError::Info,
+        }, 29, LogType::Information);
+        Ok(())
+    }
+
+    /// The entities in the dimension the player is currently in
+    pub(crate) fn get_entity_manager(&self) -> &EntityManager {
+        &self.entity_managers[self.player.dimension as usize]
+    }
+
     pub fn get_tilemap_manager(&mut self) -> &mut TileMapManager {
         &mut self.tile_map
     }
@@ -249,6 +490,76 @@ impl Game {
         &self.tile_map
     }
 
+    /// Gets the tile map alongside the generator that created it (the generator holds the layer settings needed for rendering)
+    pub fn get_map_and_generator(&mut self, dimension: Dimension) -> (Option<&mut TileMap>, &WorldGenerator) {
+        (self.tile_map.get_current_map(dimension), &self.world_generators[dimension as usize])
+    }
+
+    /// The seed every dimension was generated from
+    pub fn get_seed(&self) -> f32 {
+        self.world_generators[Dimension::Overworld as usize].get_seed()
+    }
+
+    /// The size of the overworld in tiles
+    pub fn get_world_size(&self) -> (usize, usize) {
+        match self.tile_map.get_current_map_ref(Dimension::Overworld) {
+            Some(tile_map) => (tile_map.get_map_width(), tile_map.get_map_height()),
+            None => Dimension::Overworld.get_map_size(),
+        }
+    }
+
+    /// The multiplier on the sky light for the current time of day (and the clouds, when in the overworld)
+    pub fn get_sky_brightness(&self) -> f32 {
+        match self.player.dimension {
+            Dimension::Overworld => self.day_cycle.get_sky_brightness() * self.weather.get_sky_brightness(),
+            Dimension::SkyIslands => self.day_cycle.get_sky_brightness(),
+            Dimension::TOTAL => unreachable!("Dimension::TOTAL only counts the dimensions"),
+        }
+    }
+
+    /// What decides which mobs spawn around the player (the weather only brings more out in the overworld)
+    fn get_spawn_conditions(&self) -> SpawnConditions {
+        SpawnConditions {
+            player_center: self.player.get_center(),
+            sky_brightness: self.get_sky_brightness(),
+            is_day: self.day_cycle.get_day_progress() < 0.5,
+            spawn_rate: match self.player.dimension {
+                Dimension::Overworld => self.weather.get_mob_spawn_rate(),
+                Dimension::SkyIslands => 1.0,
+                Dimension::TOTAL => unreachable!("Dimension::TOTAL only counts the dimensions"),
+            },
+        }
+    }
+
+    pub fn get_weather(&self) -> &Weather {
+        &self.weather
+    }
+
+    /// What the weather is called where the player is standing (None when it's clear, or away from the overworld)
+    pub fn get_weather_name(&self) -> Option<&'static str> {
+        match self.player.dimension {
+            Dimension::Overworld => {
+                let (tile_x, tile_y) = self.player.get_tile_position();
+                self.tile_map.get_current_map_ref(Dimension::Overworld)
+                    .and_then(|tile_map| tile_map.biome_at(tile_x, tile_y))
+                    .and_then(|biom| self.weather.get_name_at(biom))
+            },
+            Dimension::SkyIslands => None,
+            Dimension::TOTAL => unreachable!("Dimension::TOTAL only counts the dimensions"),
+        }
+    }
+
+    /// The rain and snow to draw alongside the other entities
+    pub fn get_precipitation_render(&self, window_size: (u32, u32)) -> Vec<(u32, u16, i16, i16, u16, u32)> {
+        self.precipitation.get_render(&self.player.camera, window_size)
+    }
+
+    /// Gets the layer of the dimension at the given tile row
+    pub fn get_layer(&self, dimension: Dimension, tile_y: usize) -> Option<&LayerInfo> {
+        self.tile_map.get_current_map_ref(dimension)
+            .map(|tile_map| self.world_generators[dimension as usize].get_layer(tile_y, tile_map.get_map_height()))
+    }
+
     pub fn render_ui(&mut self, buffer: &mut [u8], window_size: (u32, u32), pitch: usize) -> Result<(), crate::core::rendering::ui::UiError> {
         // rendering any ui related to the player
         self.player.render_ui(buffer, window_size, &mut self.player_ui_manager, pitch)?;
@@ -256,11 +567,11 @@ impl Game {
         // rendering the mini map
         let camera_x = self.player.camera.x;
         let camera_y = self.player.camera.y;
-        if let Some(map) = self.tile_map.get_current_map(Dimension::Overworld) {
+        if let Some(map) = self.tile_map.get_current_map(self.player.dimension) {
             map.mini_map.camera_transform.x = camera_x;
             map.mini_map.camera_transform.y = camera_y;
             map.mini_map.render(
-                &map.tiles,buffer, (350, 200), (window_size.0 as usize - 375, 25), pitch, &self.mini_map_textures
+                &map.tiles, &map.liquids, buffer, (350, 200), (window_size.0 as usize - 375, 25), pitch, &self.mini_map_textures
             );
         }
 
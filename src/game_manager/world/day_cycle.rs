@@ -0,0 +1,50 @@
+/// How long a full day lasts in seconds
+pub static DAY_LENGTH: f64 = 1200.0;
+/// How bright the sky is in the middle of the night
+static NIGHT_BRIGHTNESS: f32 = 0.12;
+/// The fraction of the day spent fading between day and night (for each of dawn and dusk)
+static TWILIGHT_LENGTH: f64 = 0.08;
+
+/// Tracks the time of day, which scales the sky light
+#[derive(bincode::Encode, bincode::Decode)]
+pub struct DayCycle {
+    time: f64,  // seconds into the current day (0 is dawn)
+}
+
+impl Default for DayCycle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DayCycle {
+    pub fn new() -> Self {
+        DayCycle {
+            time: DAY_LENGTH * 0.1,  // starting a bit after dawn
+        }
+    }
+
+    pub fn update(&mut self, delta_time: f64) {
+        self.time = (self.time + delta_time) % DAY_LENGTH;
+    }
+
+    /// How far through the day it is (0 to 1, with the first half being daytime)
+    pub fn get_day_progress(&self) -> f64 {
+        self.time / DAY_LENGTH
+    }
+
+    /// The multiplier on the sky light (1 during the day, fading down to the night brightness)
+    pub fn get_sky_brightness(&self) -> f32 {
+        let progress = self.get_day_progress();
+        let daylight = if progress < TWILIGHT_LENGTH {
+            progress / TWILIGHT_LENGTH  // dawn
+        } else if progress < 0.5 - TWILIGHT_LENGTH {
+            1.0
+        } else if progress < 0.5 {
+            (0.5 - progress) / TWILIGHT_LENGTH  // dusk
+        } else { 0.0 };
+        // smoothing out the fade so it doesn't start or stop abruptly
+        let daylight = (daylight * daylight * (3.0 - 2.0 * daylight)) as f32;
+        NIGHT_BRIGHTNESS + (1.0 - NIGHT_BRIGHTNESS) * daylight
+    }
+}
@@ -1,4 +1,5 @@
-
 pub mod tile_map;
 pub mod world_gen;
-
+pub mod day_cycle;
+pub mod weather;
+pub mod world_info;
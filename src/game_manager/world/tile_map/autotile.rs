@@ -0,0 +1,179 @@
+use crate::game_manager::world::tile_map::TileMap;
+use crate::game_manager::world::tile_map::registry::{TileProperties, get_tile_properties};
+use crate::game_manager::world::world_gen::WorldGenerator;
+
+/// The number of tiles in a blob set (every distinct way the 8 neighbours can connect)
+pub static BLOB_TILE_COUNT: u32 = 47;
+
+// the bit for each neighbour in the blob mask (a set bit means it connects)
+static NORTH: u8 = 1;
+static NORTH_EAST: u8 = 2;
+static EAST: u8 = 4;
+static SOUTH_EAST: u8 = 8;
+static SOUTH: u8 = 16;
+static SOUTH_WEST: u8 = 32;
+static WEST: u8 = 64;
+static NORTH_WEST: u8 = 128;
+
+/// (x offset, y offset, mask bit) for each neighbour
+static NEIGHBOURS: [(isize, isize, u8); 8] = [
+    ( 0, -1, NORTH),
+    ( 1, -1, NORTH_EAST),
+    ( 1,  0, EAST),
+    ( 1,  1, SOUTH_EAST),
+    ( 0,  1, SOUTH),
+    (-1,  1, SOUTH_WEST),
+    (-1,  0, WEST),
+    (-1, -1, NORTH_WEST),
+];
+
+/// Drops the corners whose sides aren't both connected, as those corners can't be seen anyway
+fn reduce_blob_mask(mask: u8) -> u8 {
+    let mut mask = mask;
+    for (corner, side_a, side_b) in [(NORTH_EAST, NORTH, EAST), (SOUTH_EAST, SOUTH, EAST), (SOUTH_WEST, SOUTH, WEST), (NORTH_WEST, NORTH, WEST)] {
+        if mask & side_a == 0 || mask & side_b == 0 {
+            mask &= !corner;
+        }
+    } mask
+}
+
+lazy_static::lazy_static! {
+    /// Maps every 8 bit neighbour mask to its tile within a blob set (the sets are laid out by ascending reduced mask)
+    static ref BLOB_INDICES: [u32; 256] = {
+        let mut reduced: Vec<u8> = (0..=255u8).map(reduce_blob_mask).collect();
+        reduced.sort();
+        reduced.dedup();
+        let mut indices = [0u32; 256];
+        for (mask, index) in indices.iter_mut().enumerate() {
+            *index = reduced.iter().position(|reduced_mask| *reduced_mask == reduce_blob_mask(mask as u8)).unwrap_or(0) as u32;
+        } indices
+    };
+}
+
+/// Whether a tile of the given material visually joins up with the neighbouring one
+fn connects(properties: &TileProperties, neighbour: &TileProperties) -> bool {
+    if !neighbour.is_solid() { return false; }
+    if neighbour.tile_type == properties.tile_type { return true; }
+    // blended materials merge together (the tile owning the blend draws the transition)
+    if properties.blends.iter().any(|(tile_type, _)| *tile_type == neighbour.tile_type) ||
+       neighbour.blends.iter().any(|(tile_type, _)| *tile_type == properties.tile_type) {
+        return true;
+    }
+    // materials without a blob set don't have the art to show a seam, so they act the same as the old solid check
+    neighbour.blob_autotile.is_none() || properties.blob_autotile.is_none()
+}
+
+impl TileMap {
+    /// Picks the right edge variant for the tile, using the 47 tile blob set if the material has one and the 16 tile set otherwise
+    pub fn autotile(&mut self, x: usize, y: usize, layer: usize) {
+        let properties = get_tile_properties(self.get_tile(x, y, layer));
+        let blob_start = match properties.blob_autotile {
+            Some(blob_start) => blob_start,
+            None => {
+                if let Some(tile_set) = properties.autotile {
+                    WorldGenerator::update_edge_tiles(tile_set, self, x, y, layer);
+                } return;
+            },
+        };
+
+        let mut connected_mask = 0u8;
+        let mut same_mask = 0u8;
+        let mut blend_partner = None;
+        for (offset_x, offset_y, bit) in NEIGHBOURS {
+            let neighbour_x = x as isize + offset_x;
+            let neighbour_y = y as isize + offset_y;
+            let neighbour = match neighbour_x < 0 || neighbour_y < 0 || neighbour_x as usize >= self.get_map_width() || neighbour_y as usize >= self.get_map_height() {
+                true => properties,  // the edge of the world counts as more of the same
+                false => get_tile_properties(self.get_tile(neighbour_x as usize, neighbour_y as usize, layer)),
+            };
+            if connects(properties, neighbour) { connected_mask |= bit; }
+            if neighbour.tile_type == properties.tile_type { same_mask |= bit; }
+            else if let Some((_, blend_start)) = properties.blends.iter().find(|(tile_type, _)| *tile_type == neighbour.tile_type) {
+                blend_partner = Some(*blend_start);
+            }
+        }
+
+        // anything open takes priority, otherwise the edges against the blended material get the transition tiles
+        let cardinals = NORTH | EAST | SOUTH | WEST;
+        let new_tile = match (connected_mask & cardinals == cardinals, blend_partner) {
+            (true, Some(blend_start)) => blend_start + BLOB_INDICES[same_mask as usize],
+            _ => blob_start + BLOB_INDICES[connected_mask as usize],
+        };
+        *self.get_tile_mut(x, y, layer) = new_tile;
+    }
+
+    /// Re-picks the edge variants for the tile and everything around it
+    pub fn autotile_area(&mut self, x: usize, y: usize, layer: usize) {
+        for neighbour_y in y.saturating_sub(1)..=(y + 1).min(self.get_map_height() - 1) {
+            for neighbour_x in x.saturating_sub(1)..=(x + 1).min(self.get_map_width() - 1) {
+                self.autotile(neighbour_x, neighbour_y, layer);
+            }
+        }
+    }
+}
+
+/// Lists every tile id in a blob set (for registering the ids with their material)
+pub fn blob_tile_ids(blob_start: u32) -> impl Iterator<Item = u32> {
+    blob_start..blob_start + BLOB_TILE_COUNT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_manager::world::tile_map::{DIRT_IDS, STONE_IDS};
+
+    /// Where the dirt's own blob set and its blend into stone start (see the registry)
+    static DIRT_BLOB_START: u32 = 224;
+    static DIRT_STONE_BLEND_START: u32 = 271;
+
+    /// Fills the square of tiles centered on (4, 4), leaving the edges of the map clear
+    fn place_square(tile_map: &mut TileMap, radius: usize, tile: u32) {
+        for y in 4 - radius..=4 + radius {
+            for x in 4 - radius..=4 + radius {
+                tile_map.change_tile(x, y, 0, tile).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn blob_masks_map_onto_the_47_tiles() {
+        assert_eq!(BLOB_INDICES[0], 0);
+        assert_eq!(BLOB_INDICES[255], 46);
+        assert_eq!(BLOB_INDICES[(NORTH | EAST) as usize], 3);
+        assert_eq!(BLOB_INDICES[(NORTH | NORTH_EAST | EAST) as usize], 4);
+        assert_eq!(BLOB_INDICES[(EAST | SOUTH_EAST | SOUTH | SOUTH_WEST | WEST) as usize], 31);
+        // corners only count when both of their sides connect
+        assert_eq!(BLOB_INDICES[NORTH_EAST as usize], BLOB_INDICES[0]);
+        assert_eq!(BLOB_INDICES[(NORTH | NORTH_EAST) as usize], BLOB_INDICES[NORTH as usize]);
+
+        let mut indices = BLOB_INDICES.to_vec();
+        indices.sort();
+        indices.dedup();
+        assert_eq!(indices, (0..BLOB_TILE_COUNT).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn isolated_tiles_use_the_first_blob_tile() {
+        let mut tile_map = TileMap::test_tile_map(8, 8);
+        tile_map.change_tile(4, 4, 0, DIRT_IDS[0]).unwrap();
+        assert_eq!(tile_map.get_tile(4, 4, 0), DIRT_BLOB_START);
+    }
+
+    #[test]
+    fn surrounded_tiles_use_the_last_blob_tile() {
+        let mut tile_map = TileMap::test_tile_map(8, 8);
+        place_square(&mut tile_map, 1, DIRT_IDS[0]);
+        assert_eq!(tile_map.get_tile(4, 4, 0), DIRT_BLOB_START + 46);
+        // while the corners only join along their two inner sides
+        assert_eq!(tile_map.get_tile(3, 3, 0), DIRT_BLOB_START + BLOB_INDICES[(EAST | SOUTH_EAST | SOUTH) as usize]);
+    }
+
+    #[test]
+    fn dirt_blends_into_neighbouring_stone() {
+        let mut tile_map = TileMap::test_tile_map(8, 8);
+        place_square(&mut tile_map, 1, DIRT_IDS[0]);
+        tile_map.change_tile(4, 3, 0, STONE_IDS[0]).unwrap();
+        // fully enclosed, so it takes a transition tile (picked by the sides that are still dirt)
+        assert_eq!(tile_map.get_tile(4, 4, 0), DIRT_STONE_BLEND_START + 31);
+    }
+}
@@ -0,0 +1,242 @@
+use crate::game_manager::world::tile_map::{TileMap, TileMapError};
+use crate::game_manager::world::tile_map::registry::{CollisionShape, TileType, get_tile_properties, get_tile_type_properties};
+use crate::game_manager::world::tile_map::tile_data::SHAPE;
+
+/// The shapes a solid tile can be hammered into (the shader masks the texture to match, so keep the order in sync with shader.metal)
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TileShape {
+    Full,
+    HalfBlock,     // the bottom half
+    SlopeUpRight,  // the floor rises going right (the bottom right half is solid)
+    SlopeUpLeft,   // the floor rises going left (the bottom left half is solid)
+    CeilingRight,  // the top right half is solid
+    CeilingLeft,   // the top left half is solid
+}
+
+static TILE_SHAPES: &[TileShape] = &[
+    TileShape::Full,
+    TileShape::HalfBlock,
+    TileShape::SlopeUpRight,
+    TileShape::SlopeUpLeft,
+    TileShape::CeilingRight,
+    TileShape::CeilingLeft,
+];
+
+impl TileShape {
+    pub(crate) fn from_bits(bits: u32) -> Self {
+        TILE_SHAPES.get(bits as usize).copied().unwrap_or(TileShape::Full)
+    }
+
+    fn to_bits(self) -> u32 {
+        TILE_SHAPES.iter().position(|shape| *shape == self).unwrap_or(0) as u32
+    }
+
+    /// The highest solid point of the tile across the columns a to b (in pixels from the top of the tile)
+    fn get_floor(self, a: f32, b: f32) -> f32 {
+        match self {
+            TileShape::HalfBlock => 4.0,
+            TileShape::SlopeUpRight => 8.0 - b,
+            TileShape::SlopeUpLeft => a,
+            _ => 0.0,
+        }
+    }
+
+    /// The lowest solid point of the tile across the columns a to b (in pixels from the top of the tile)
+    fn get_ceiling(self, a: f32, b: f32) -> f32 {
+        match self {
+            TileShape::CeilingRight => b,
+            TileShape::CeilingLeft => 8.0 - a,
+            _ => 8.0,
+        }
+    }
+
+    /// Whether the solid part of the tile overlaps the box (given relative to the tile's top left corner)
+    fn overlaps(self, left: f32, top: f32, right: f32, bottom: f32) -> bool {
+        let (a, b) = (left.max(0.0), right.min(8.0));
+        a < b && self.get_floor(a, b) < bottom && self.get_ceiling(a, b) > top
+    }
+}
+
+impl TileMap {
+    pub fn get_collision_shape(&self, x: usize, y: usize) -> CollisionShape {
+        get_tile_properties(self.get_tile(x, y, 0)).collision
+    }
+
+    pub fn get_tile_shape(&self, x: usize, y: usize) -> TileShape {
+        TileShape::from_bits(self.get_tile_data_field(x, y, 0, SHAPE))
+    }
+
+    /// Hammers the next shape into a solid tile (cycling back round to a full block), returning whether it changed
+    /// Wire devices like doors and traps keep their full shape, as they swap between tiles when switched
+    pub fn cycle_tile_shape(&mut self, x: usize, y: usize) -> bool {
+        if self.get_collision_shape(x, y) != CollisionShape::Full || self.is_wire_device(x, y) { return false; }
+        let next = (self.get_tile_shape(x, y).to_bits() + 1) % TILE_SHAPES.len() as u32;
+        self.set_tile_shape(x, y, TileShape::from_bits(next));
+        true
+    }
+
+    /// Hammers a tile back into a full block
+    pub fn reset_tile_shape(&mut self, x: usize, y: usize) {
+        self.set_tile_shape(x, y, TileShape::Full);
+    }
+
+    /// Only full blocks are solid, so changing between a full block and anything else lets light and liquids through (or stops them)
+    pub(crate) fn set_tile_shape(&mut self, x: usize, y: usize, shape: TileShape) {
+        let was_solid = self.is_solid(x, y);
+        self.set_tile_data_field(x, y, 0, SHAPE, shape.to_bits());
+        let is_solid = self.is_solid(x, y);
+        if was_solid != is_solid {
+            self.refresh_lights(&[(x, y)]);
+            self.update_sky_light(x, y, is_solid);
+            self.liquids.wake_area(x, y);
+        }
+    }
+
+    /// Calls the closure with every on-map tile the box (in pixels) overlaps, stopping once it returns true
+    fn any_tile_in_box(&self, x: f32, y: f32, width: f32, height: f32, mut check: impl FnMut(usize, usize) -> bool) -> bool {
+        let start_x = (x / 8.0).floor() as isize;
+        let start_y = (y / 8.0).floor() as isize;
+        let end_x = ((x + width) / 8.0).ceil() as isize;
+        let end_y = ((y + height) / 8.0).ceil() as isize;
+
+        for tile_y in start_y..end_y {
+            for tile_x in start_x..end_x {
+                if tile_x < 0 || tile_y < 0 || tile_y as usize >= self.get_map_height() || tile_x as usize >= self.get_map_width() {
+                    continue;
+                }
+                if check(tile_x as usize, tile_y as usize) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// Whether any tile the box (in pixels) overlaps has the collision shape
+    fn box_touches_shape(&self, x: f32, y: f32, width: f32, height: f32, shape: CollisionShape) -> bool {
+        self.any_tile_in_box(x, y, width, height, |tile_x, tile_y| self.get_collision_shape(tile_x, tile_y) == shape)
+    }
+
+    /// Whether the box hits the solid part of any tile (half blocks and slopes only collide where they're filled in)
+    pub fn check_aabb_collision(&self, x: f32, y: f32, width: f32, height: f32) -> bool {
+        self.any_tile_in_box(x, y, width, height, |tile_x, tile_y| {
+            if self.get_collision_shape(tile_x, tile_y) != CollisionShape::Full { return false; }
+            let (tile_left, tile_top) = (tile_x as f32 * 8.0, tile_y as f32 * 8.0);
+            self.get_tile_shape(tile_x, tile_y).overlaps(x - tile_left, y - tile_top, x + width - tile_left, y + height - tile_top)
+        })
+    }
+
+    /// How far the box would have to rise to stand on top of whatever solid tiles it's pushed into
+    /// (used to walk up slopes and onto half blocks and small ledges)
+    pub fn get_step_height(&self, x: f32, y: f32, width: f32, height: f32) -> f32 {
+        let bottom = y + height;
+        let mut step = 0.0f32;
+        self.any_tile_in_box(x, y, width, height, |tile_x, tile_y| {
+            if self.get_collision_shape(tile_x, tile_y) == CollisionShape::Full {
+                let (tile_left, tile_top) = (tile_x as f32 * 8.0, tile_y as f32 * 8.0);
+                let (a, b) = ((x - tile_left).max(0.0), (x + width - tile_left).min(8.0));
+                if a < b {
+                    step = step.max(bottom - (tile_top + self.get_tile_shape(tile_x, tile_y).get_floor(a, b)));
+                }
+            } false
+        });
+        step
+    }
+
+    /// The distance from the bottom of the box down to the solid ground below it, if it's within max_distance
+    /// (used to keep things on the ground walking down slopes, rather than falling down them in little steps)
+    pub fn get_ground_distance(&self, x: f32, y: f32, width: f32, height: f32, max_distance: f32) -> Option<f32> {
+        let bottom = y + height;
+        let mut distance = None::<f32>;
+        self.any_tile_in_box(x, bottom, width, max_distance, |tile_x, tile_y| {
+            if self.get_collision_shape(tile_x, tile_y) == CollisionShape::Full {
+                let (tile_left, tile_top) = (tile_x as f32 * 8.0, tile_y as f32 * 8.0);
+                let (a, b) = ((x - tile_left).max(0.0), (x + width - tile_left).min(8.0));
+                let floor = tile_top + self.get_tile_shape(tile_x, tile_y).get_floor(a, b);
+                // anything the box is already inside of isn't ground below it
+                if a < b && floor >= bottom - 0.01 {
+                    let gap = (floor - bottom).max(0.0);
+                    distance = Some(distance.map_or(gap, |distance| distance.min(gap)));
+                }
+            } false
+        });
+        distance.filter(|distance| *distance <= max_distance)
+    }
+
+    /// Whether the bottom of a box moving down from old_bottom to new_bottom lands on the top of a platform
+    /// Anything already below the top of the platform (such as jumping up through it) passes straight through
+    pub fn check_platform_landing(&self, x: f32, width: f32, old_bottom: f32, new_bottom: f32) -> bool {
+        let tile_y = (new_bottom / 8.0).floor();
+        let top = tile_y * 8.0;
+        if tile_y < 0.0 || old_bottom > top || new_bottom <= top { return false; }
+        self.box_touches_shape(x, top, width, 1.0, CollisionShape::Platform)
+    }
+
+    /// Whether the box overlaps any ladders or ropes
+    pub fn check_climbable(&self, x: f32, y: f32, width: f32, height: f32) -> bool {
+        self.box_touches_shape(x, y, width, height, CollisionShape::Climbable)
+    }
+
+    pub fn is_door(&self, x: usize, y: usize) -> bool {
+        matches!(get_tile_properties(self.get_tile(x, y, 0)).tile_type, TileType::Door | TileType::OpenDoor)
+    }
+
+    /// Opens a closed door or shuts an open one, returning whether it moved
+    /// Doors hooked up to wires are left to whatever is powering them
+    pub fn toggle_door(&mut self, x: usize, y: usize) -> Result<bool, TileMapError> {
+        if self.is_wire_controlled(x, y) { return Ok(false); }
+        let toggled = match get_tile_properties(self.get_tile(x, y, 0)).tile_type {
+            TileType::Door => TileType::OpenDoor,
+            TileType::OpenDoor => TileType::Door,
+            _ => return Ok(false),
+        };
+        self.change_tile(x, y, 0, get_tile_type_properties(toggled).tile_ids[0])?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static PLATFORM: u32 = 381;
+    static DOOR: u32 = 375;
+    static LEVER: u32 = 369;
+
+    #[test]
+    fn falling_onto_a_platform_lands_on_it() {
+        let mut tile_map = TileMap::test_tile_map(8, 8);
+        tile_map.change_tile(2, 4, 0, PLATFORM).unwrap();
+        // the top of the platform is at y = 32
+        assert!(tile_map.check_platform_landing(17.0, 6.0, 30.0, 33.0));
+        // missing it to the side
+        assert!(!tile_map.check_platform_landing(41.0, 6.0, 30.0, 33.0));
+    }
+
+    #[test]
+    fn jumping_up_through_a_platform_passes_through() {
+        let mut tile_map = TileMap::test_tile_map(8, 8);
+        tile_map.change_tile(2, 4, 0, PLATFORM).unwrap();
+        // rising up through it from below
+        assert!(!tile_map.check_platform_landing(17.0, 6.0, 40.0, 33.0));
+        // and starting to fall again while still partway through it
+        assert!(!tile_map.check_platform_landing(17.0, 6.0, 34.0, 35.0));
+    }
+
+    #[test]
+    fn wired_doors_cant_be_toggled_by_hand() {
+        let mut tile_map = TileMap::test_tile_map(8, 8);
+        tile_map.change_tile(2, 4, 0, DOOR).unwrap();
+        assert!(tile_map.toggle_door(2, 4).unwrap());
+        assert!(tile_map.toggle_door(2, 4).unwrap());
+
+        // a lever at (5, 4) wired up to it in red
+        tile_map.change_tile(5, 4, 0, LEVER).unwrap();
+        for x in 2..=5 {
+            tile_map.place_wire(x, 4, 0);
+        }
+        tile_map.update_wiring(0.1, &[], &[]).unwrap();
+        assert!(!tile_map.toggle_door(2, 4).unwrap());
+        assert_eq!(tile_map.get_tile(2, 4, 0), DOOR);
+    }
+}
@@ -0,0 +1,216 @@
+use std::collections::VecDeque;
+
+use crate::game_manager::world::tile_map::{TileMap, TileMapError};
+use crate::game_manager::world::tile_map::collision::TileShape;
+use crate::game_manager::world::tile_map::tile_data::{BREAK_DAMAGE, SHAPE};
+
+/// The most groups of edits that can be undone (the oldest are dropped past this)
+static MAX_EDIT_GROUPS: usize = 64;
+/// The most edits a single group can hold (a really long drag stops recording rather than eating memory)
+static MAX_GROUP_EDITS: usize = 4096;
+
+/// A single tile edited by the player
+#[derive(bincode::Encode, bincode::Decode, Clone, Copy)]
+struct TileEdit {
+    x: usize,
+    y: usize,
+    layer: usize,
+    old_tile: u32,
+    old_data: u32,  // change_tile clears the tile data, so it's put back by hand when undoing (see restore_tile)
+    new_tile: u32,
+    new_data: u32,  // put back the same way when redoing
+}
+
+/// The tile edits made while building, grouped up so a whole mouse drag is undone at once
+/// Only the player's own edits are recorded (see record_edit), and only the tiles themselves are restored (liquids that were pushed out and such stay as they are)
+#[derive(bincode::Encode, bincode::Decode)]
+pub struct EditHistory {
+    undo_groups: VecDeque<Vec<TileEdit>>,
+    redo_groups: Vec<Vec<TileEdit>>,
+    current_group: Option<Vec<TileEdit>>,  // edits are only recorded while a group is open (undo and redo close it first, so they never record themselves)
+}
+
+impl Default for EditHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EditHistory {
+    pub fn new() -> Self {
+        EditHistory {
+            undo_groups: VecDeque::new(),
+            redo_groups: Vec::new(),
+            current_group: None,
+        }
+    }
+}
+
+impl TileMap {
+    /// Starts grouping the following edits together (does nothing if a group is already open)
+    pub fn begin_edit_group(&mut self) {
+        if self.edit_history.current_group.is_none() {
+            self.edit_history.current_group = Some(Vec::new());
+        }
+    }
+
+    /// Closes the open group, adding it to the history if anything was changed
+    pub fn end_edit_group(&mut self) {
+        let group = match self.edit_history.current_group.take() {
+            Some(group) if !group.is_empty() => group,
+            _ => return,
+        };
+        // a new edit branches off the history, so anything undone can't be redone anymore
+        self.edit_history.redo_groups.clear();
+        self.edit_history.undo_groups.push_back(group);
+        if self.edit_history.undo_groups.len() > MAX_EDIT_GROUPS {
+            self.edit_history.undo_groups.pop_front();
+        }
+    }
+
+    /// Edits the tile through the closure, adding the change to the open group (if there is one) so it can be undone
+    /// Only the player's building goes through here, so the world changing by itself (liquids, falling sand, random ticks...) is never undone
+    pub fn record_edit<T>(&mut self, x: usize, y: usize, layer: usize, edit: impl FnOnce(&mut TileMap) -> Result<T, TileMapError>) -> Result<T, TileMapError> {
+        self.record_edits(&[(x, y, layer)], edit)
+    }
+
+    /// The same as record_edit, for edits that change several tiles at once (like felling a whole tree)
+    pub fn record_edits<T>(&mut self, tiles: &[(usize, usize, usize)], edit: impl FnOnce(&mut TileMap) -> Result<T, TileMapError>) -> Result<T, TileMapError> {
+        // any cracks from mining it aren't worth bringing back
+        let old_states = tiles.iter()
+            .map(|&(x, y, layer)| (self.get_tile(x, y, layer), BREAK_DAMAGE.set(self.get_tile_data(x, y, layer), 0)))
+            .collect::<Vec<_>>();
+        let result = edit(self)?;
+        if self.edit_history.current_group.is_none() { return Ok(result); }
+        let edits = tiles.iter().zip(old_states)
+            .map(|(&(x, y, layer), (old_tile, old_data))| TileEdit {
+                x, y, layer,
+                old_tile, old_data,
+                new_tile: self.get_tile(x, y, layer),
+                new_data: self.get_tile_data(x, y, layer),
+            })
+            // holding the mouse over a tile places it again every frame, which changes nothing after the first time
+            .filter(|edit| (edit.old_tile, edit.old_data) != (edit.new_tile, edit.new_data))
+            .collect::<Vec<_>>();
+        if let Some(group) = &mut self.edit_history.current_group {
+            let space = MAX_GROUP_EDITS.saturating_sub(group.len());
+            group.extend(edits.into_iter().take(space));
+        }
+        Ok(result)
+    }
+
+    /// Reverts the most recent group of edits, returning false if there was nothing to undo
+    pub fn undo_edit(&mut self) -> Result<bool, TileMapError> {
+        self.end_edit_group();
+        let group = match self.edit_history.undo_groups.pop_back() {
+            Some(group) => group,
+            None => return Ok(false),
+        };
+        // going backwards so tiles changed more than once end up at their very first state
+        for edit in group.iter().rev() {
+            self.restore_tile(edit.x, edit.y, edit.layer, edit.old_tile, edit.old_data)?;
+        }
+        self.edit_history.redo_groups.push(group);
+        Ok(true)
+    }
+
+    /// Re-applies the most recently undone group of edits, returning false if there was nothing to redo
+    pub fn redo_edit(&mut self) -> Result<bool, TileMapError> {
+        self.end_edit_group();
+        let group = match self.edit_history.redo_groups.pop() {
+            Some(group) => group,
+            None => return Ok(false),
+        };
+        for edit in &group {
+            self.restore_tile(edit.x, edit.y, edit.layer, edit.new_tile, edit.new_data)?;
+        }
+        self.edit_history.undo_groups.push_back(group);
+        Ok(true)
+    }
+
+    /// Puts the tile and its data back, with the shape hammered back in through set_tile_shape so the light and liquids around it catch up
+    fn restore_tile(&mut self, x: usize, y: usize, layer: usize, tile: u32, data: u32) -> Result<(), TileMapError> {
+        self.change_tile(x, y, layer, tile)?;
+        if layer != 0 {
+            self.set_tile_data(x, y, layer, data);
+            return Ok(());
+        }
+        let current_shape = self.get_tile_data_field(x, y, 0, SHAPE);
+        self.set_tile_data(x, y, 0, SHAPE.set(data, current_shape));
+        self.set_tile_shape(x, y, TileShape::from_bits(SHAPE.get(data)));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_manager::world::tile_map::STONE_IDS;
+    use crate::game_manager::world::tile_map::registry::{TileType, get_tile_properties};
+
+    /// Places stone as its own group of edits (the same as a single click while building)
+    fn place_stone(tile_map: &mut TileMap, x: usize, y: usize) {
+        tile_map.begin_edit_group();
+        tile_map.record_edit(x, y, 0, |tile_map| tile_map.change_tile(x, y, 0, STONE_IDS[0])).unwrap();
+        tile_map.end_edit_group();
+    }
+
+    fn is_stone(tile_map: &TileMap, x: usize, y: usize) -> bool {
+        get_tile_properties(tile_map.get_tile(x, y, 0)).tile_type == TileType::Stone
+    }
+
+    #[test]
+    fn undo_then_redo_restores_the_tile() {
+        let mut tile_map = TileMap::test_tile_map(8, 8);
+        place_stone(&mut tile_map, 4, 4);
+        let placed = tile_map.get_tile(4, 4, 0);
+
+        assert!(tile_map.undo_edit().unwrap());
+        assert_eq!(tile_map.get_tile(4, 4, 0), 0);
+        assert!(tile_map.redo_edit().unwrap());
+        assert_eq!(tile_map.get_tile(4, 4, 0), placed);
+        assert!(!tile_map.redo_edit().unwrap());
+    }
+
+    #[test]
+    fn new_edits_clear_the_redo_history() {
+        let mut tile_map = TileMap::test_tile_map(8, 8);
+        place_stone(&mut tile_map, 4, 4);
+        assert!(tile_map.undo_edit().unwrap());
+
+        place_stone(&mut tile_map, 2, 2);
+        assert!(!tile_map.redo_edit().unwrap());
+        assert!(!is_stone(&tile_map, 4, 4));
+        assert!(is_stone(&tile_map, 2, 2));
+    }
+
+    #[test]
+    fn the_oldest_groups_are_dropped() {
+        let mut tile_map = TileMap::test_tile_map(MAX_EDIT_GROUPS + 1, 4);
+        for x in 0..=MAX_EDIT_GROUPS {
+            place_stone(&mut tile_map, x, 2);
+        }
+        while tile_map.undo_edit().unwrap() {}
+
+        // only the very first edit is left in place
+        assert!(is_stone(&tile_map, 0, 2));
+        assert!((1..=MAX_EDIT_GROUPS).all(|x| !is_stone(&tile_map, x, 2)));
+    }
+
+    #[test]
+    fn hammering_is_undone() {
+        let mut tile_map = TileMap::test_tile_map(8, 8);
+        tile_map.change_tile(4, 4, 0, STONE_IDS[0]).unwrap();
+        tile_map.begin_edit_group();
+        tile_map.record_edit(4, 4, 0, |tile_map| Ok(tile_map.cycle_tile_shape(4, 4))).unwrap();
+        tile_map.end_edit_group();
+        assert_eq!(tile_map.get_tile_shape(4, 4), TileShape::HalfBlock);
+
+        assert!(tile_map.undo_edit().unwrap());
+        assert_eq!(tile_map.get_tile_shape(4, 4), TileShape::Full);
+        assert!(tile_map.is_solid(4, 4));
+        assert!(tile_map.redo_edit().unwrap());
+        assert_eq!(tile_map.get_tile_shape(4, 4), TileShape::HalfBlock);
+        assert!(!tile_map.is_solid(4, 4));
+    }
+}
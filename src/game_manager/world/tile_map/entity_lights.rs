@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+
+use crate::game_manager::world::tile_map::TileMap;
+
+/// The furthest any entity light can reach in pixels (this keeps the grid lookups and raycasts cheap)
+pub static MAX_LIGHT_RADIUS: f32 = 128.0;
+/// The size of each cell of the light grid in pixels (matching the max radius means only the neighbouring cells need checking)
+static LIGHT_GRID_SIZE: f32 = 128.0;
+
+/// An id for a dynamic light, handed out when it's added and used to move or remove it later
+#[derive(bincode::Encode, bincode::Decode, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct LightHandle(u32);
+
+/// How the light fades out towards the edge of its radius
+#[derive(bincode::Encode, bincode::Decode, Clone, Copy, Debug)]
+pub enum LightFalloff {
+    Linear,     // fades evenly the whole way out
+    Quadratic,  // stays bright near the middle then drops off quickly at the edge
+    Focused,    // a bright core with a long dim tail
+}
+
+impl LightFalloff {
+    /// The strength of the light (0 to 1) at a fraction of the way out to the radius
+    pub fn get_strength(&self, distance: f32) -> f32 {
+        let distance = distance.clamp(0.0, 1.0);
+        match self {
+            LightFalloff::Linear => 1.0 - distance,
+            LightFalloff::Quadratic => 1.0 - distance * distance,
+            LightFalloff::Focused => (1.0 - distance) * (1.0 - distance),
+        }
+    }
+}
+
+#[derive(bincode::Encode, bincode::Decode, Clone, Copy)]
+pub struct EntityLight {
+    pub(crate) position: (f32, f32),
+    pub(crate) color: (u8, u8, u8, f32),  // the last value is the intensity
+    pub(crate) radius: f32,
+    pub(crate) falloff: LightFalloff,
+}
+
+impl EntityLight {
+    pub fn new(position: (f32, f32), color: (u8, u8, u8, f32), radius: f32, falloff: LightFalloff) -> Self {
+        Self { position, color, radius: radius.min(MAX_LIGHT_RADIUS), falloff }
+    }
+}
+
+fn get_grid_cell(position: (f32, f32)) -> (i32, i32) {
+    ((position.0 / LIGHT_GRID_SIZE).floor() as i32, (position.1 / LIGHT_GRID_SIZE).floor() as i32)
+}
+
+/// All the dynamic lights (the player, held torches, projectiles, etc...) bucketed into a coarse grid by position
+#[derive(bincode::Encode, bincode::Decode)]
+pub struct EntityLights {
+    lights: HashMap<LightHandle, EntityLight>,
+    grid: HashMap<(i32, i32), Vec<LightHandle>>,
+    next_handle: u32,
+}
+
+impl Default for EntityLights {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EntityLights {
+    pub fn new() -> Self {
+        EntityLights {
+            lights: HashMap::new(),
+            grid: HashMap::new(),
+            next_handle: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.lights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    pub fn add(&mut self, light: EntityLight) -> LightHandle {
+        let handle = LightHandle(self.next_handle);
+        self.next_handle += 1;
+        self.grid.entry(get_grid_cell(light.position)).or_default().push(handle);
+        self.lights.insert(handle, light);
+        handle
+    }
+
+    /// Replaces the light, moving it between grid cells if needed (does nothing if the handle was already removed)
+    pub fn update(&mut self, handle: LightHandle, light: EntityLight) {
+        let old_position = match self.lights.get(&handle) {
+            Some(old_light) => old_light.position,
+            None => return,
+        };
+        let old_cell = get_grid_cell(old_position);
+        let new_cell = get_grid_cell(light.position);
+        if old_cell != new_cell {
+            self.remove_from_grid(handle, old_cell);
+            self.grid.entry(new_cell).or_default().push(handle);
+        }
+        self.lights.insert(handle, light);
+    }
+
+    pub fn remove(&mut self, handle: LightHandle) {
+        if let Some(light) = self.lights.remove(&handle) {
+            self.remove_from_grid(handle, get_grid_cell(light.position));
+        }
+    }
+
+    pub fn get(&self, handle: LightHandle) -> Option<&EntityLight> {
+        self.lights.get(&handle)
+    }
+
+    fn remove_from_grid(&mut self, handle: LightHandle, cell: (i32, i32)) {
+        if let Some(cell_lights) = self.grid.get_mut(&cell) {
+            cell_lights.retain(|cell_handle| *cell_handle != handle);
+            if cell_lights.is_empty() {
+                self.grid.remove(&cell);
+            }
+        }
+    }
+
+    /// Every light that could reach into the area (in pixels)
+    fn query_area(&self, start: (f32, f32), end: (f32, f32)) -> impl Iterator<Item = &EntityLight> {
+        let start_cell = get_grid_cell((start.0 - MAX_LIGHT_RADIUS, start.1 - MAX_LIGHT_RADIUS));
+        let end_cell = get_grid_cell((end.0 + MAX_LIGHT_RADIUS, end.1 + MAX_LIGHT_RADIUS));
+        (start_cell.1..=end_cell.1)
+            .flat_map(move |cell_y| (start_cell.0..=end_cell.0).map(move |cell_x| (cell_x, cell_y)))
+            .filter_map(|cell| self.grid.get(&cell))
+            .flatten()
+            .filter_map(|handle| self.lights.get(handle))
+    }
+}
+
+impl TileMap {
+    pub fn add_entity_light(&mut self, light: EntityLight) -> LightHandle {
+        self.entity_lights.add(light)
+    }
+
+    pub fn update_entity_light(&mut self, handle: LightHandle, light: EntityLight) {
+        self.entity_lights.update(handle, light);
+    }
+
+    /// Moves the light without changing anything else about it
+    pub fn move_entity_light(&mut self, handle: LightHandle, position: (f32, f32)) {
+        if let Some(light) = self.entity_lights.get(handle) {
+            let light = EntityLight { position, ..*light };
+            self.entity_lights.update(handle, light);
+        }
+    }
+
+    pub fn remove_entity_light(&mut self, handle: LightHandle) {
+        self.entity_lights.remove(handle);
+    }
+
+    /// Whether a straight line between the two tiles gets through without hitting anything solid
+    /// The end tile itself can be solid, so the faces of walls still get lit
+    fn has_line_of_sight(&self, start: (isize, isize), end: (isize, isize)) -> bool {
+        // stepping along the line one tile at a time (bresenham)
+        let (delta_x, delta_y) = ((end.0 - start.0).abs(), -(end.1 - start.1).abs());
+        let (step_x, step_y) = ((end.0 - start.0).signum(), (end.1 - start.1).signum());
+        let mut error = delta_x + delta_y;
+        let (mut x, mut y) = start;
+        while (x, y) != end {
+            let doubled_error = error * 2;
+            if doubled_error >= delta_y { error += delta_y; x += step_x; }
+            if doubled_error <= delta_x { error += delta_x; y += step_y; }
+            if (x, y) == end { break; }
+            if self.is_solid(x as usize, y as usize) { return false; }
+        } true
+    }
+
+    /// The light from every entity light across the area of tiles (row by row), blocked by any solid tiles in the way
+    pub(crate) fn get_entity_lighting(&self, start: (usize, usize), end: (usize, usize)) -> Vec<[u8; 3]> {
+        let width = end.0 - start.0;
+        let mut lighting = vec![[0u8; 3]; width * (end.1 - start.1)];
+        let area_start = (start.0 as f32 * 8.0, start.1 as f32 * 8.0);
+        let area_end = (end.0 as f32 * 8.0, end.1 as f32 * 8.0);
+        for light in self.entity_lights.query_area(area_start, area_end) {
+            let light_tile = ((light.position.0 / 8.0).floor() as isize, (light.position.1 / 8.0).floor() as isize);
+            let reach = (light.radius / 8.0).ceil() as isize;
+            // only the tiles within the radius and on screen
+            let min_x = (light_tile.0 - reach).max(start.0 as isize);
+            let max_x = (light_tile.0 + reach).min(end.0 as isize - 1);
+            let min_y = (light_tile.1 - reach).max(start.1 as isize);
+            let max_y = (light_tile.1 + reach).min(end.1 as isize - 1);
+            for y in min_y..=max_y {
+                for x in min_x..=max_x {
+                    let dif_x = light.position.0 - (x as f32 * 8.0 + 4.0);
+                    let dif_y = light.position.1 - (y as f32 * 8.0 + 4.0);
+                    let distance = (dif_x * dif_x + dif_y * dif_y).sqrt() / light.radius;
+                    if distance >= 1.0 { continue; }
+                    let strength = light.falloff.get_strength(distance) * light.color.3;
+                    let index = (y as usize - start.1) * width + (x as usize - start.0);
+                    let current = lighting[index];
+                    let new_light = [
+                        current[0].max((light.color.0 as f32 * strength) as u8),
+                        current[1].max((light.color.1 as f32 * strength) as u8),
+                        current[2].max((light.color.2 as f32 * strength) as u8),
+                    ];
+                    // the raycast is the expensive part, so it's skipped if this light wouldn't brighten the tile anyway
+                    if new_light == current { continue; }
+                    if !self.has_line_of_sight(light_tile, (x, y)) { continue; }
+                    lighting[index] = new_light;
+                }
+            }
+        } lighting
+    }
+}
@@ -0,0 +1,250 @@
+// a queue based flood fill for the tile lighting (only working through the LightMap trait, so the benchmarks can light their own maps)
+
+use std::collections::VecDeque;
+
+/// How much light is lost moving into an open tile
+pub static LIGHT_FALLOFF_AIR: u8 = 25;
+/// How much light is lost moving into a solid tile (light only bleeds a couple tiles into walls)
+pub static LIGHT_FALLOFF_SOLID: u8 = 80;
+
+/// Anything the light engine can spread light across
+pub trait LightMap {
+    fn light_size(&self) -> (usize, usize);
+    fn get_light(&self, x: usize, y: usize) -> [u8; 3];
+    fn set_light(&mut self, x: usize, y: usize, light: [u8; 3]);
+    /// The light lost entering the tile
+    fn light_falloff(&self, x: usize, y: usize) -> u8;
+    /// The light given off by the tile (black if it isn't a light source)
+    fn light_emission(&self, x: usize, y: usize) -> [u8; 3];
+}
+
+fn for_each_neighbour(size: (usize, usize), x: usize, y: usize, mut action: impl FnMut(usize, usize)) {
+    if x > 0 { action(x - 1, y); }
+    if x + 1 < size.0 { action(x + 1, y); }
+    if y > 0 { action(x, y - 1); }
+    if y + 1 < size.1 { action(x, y + 1); }
+}
+
+/// How many tiles are spread from between each progress callback of the full floods
+const PROGRESS_INTERVAL: usize = 4096;
+
+/// Spreads the light outwards from every tile in the queue (each channel is mixed separately by taking the brightest)
+pub fn propagate_light<M: LightMap>(map: &mut M, queue: &mut VecDeque<(usize, usize)>) {
+    propagate_light_with_progress(map, queue, |_| true);
+}
+
+/// Same as propagate_light, but calls `progress` every so often with a rough guess of how far along it is (0 to 1)
+/// Returns false if `progress` asked for it to stop, leaving the rest of the queue unspread
+pub fn propagate_light_with_progress<M: LightMap>(map: &mut M, queue: &mut VecDeque<(usize, usize)>, mut progress: impl FnMut(f32) -> bool) -> bool {
+    let size = map.light_size();
+    let mut spread = 0;
+    while let Some((x, y)) = queue.pop_front() {
+        spread += 1;
+        // the queue keeps growing as it goes, so this can only ever be an estimate
+        if spread % PROGRESS_INTERVAL == 0 && !progress(spread as f32 / (spread + queue.len()) as f32) {
+            return false;
+        }
+        let light = map.get_light(x, y);
+        if light == [0, 0, 0] { continue; }
+        for_each_neighbour(size, x, y, |neighbour_x, neighbour_y| {
+            let falloff = map.light_falloff(neighbour_x, neighbour_y);
+            let current = map.get_light(neighbour_x, neighbour_y);
+            let mut new_light = current;
+            for channel in 0..3 {
+                new_light[channel] = current[channel].max(light[channel].saturating_sub(falloff));
+            }
+            if new_light != current {
+                map.set_light(neighbour_x, neighbour_y, new_light);
+                queue.push_back((neighbour_x, neighbour_y));
+            }
+        });
+    }
+    true
+}
+
+/// Clears out all the light that came from (or through) the tiles, then relights the gap from whatever is left around them
+pub fn remove_lights<M: LightMap>(map: &mut M, tiles: &[(usize, usize)]) {
+    let size = map.light_size();
+    let mut relight = VecDeque::new();
+    let mut cleared = tiles.to_vec();
+    let original = tiles.iter().map(|(x, y)| map.get_light(*x, *y)).collect::<Vec<[u8; 3]>>();
+
+    // each channel has to be removed on its own, as a tile can get its red from one light and its blue from another
+    for channel in 0..3 {
+        let mut queue = VecDeque::new();
+        for (index, (x, y)) in tiles.iter().enumerate() {
+            let mut light = map.get_light(*x, *y);
+            light[channel] = 0;
+            map.set_light(*x, *y, light);
+            queue.push_back((*x, *y, original[index][channel]));
+        }
+
+        while let Some((tile_x, tile_y, level)) = queue.pop_front() {
+            for_each_neighbour(size, tile_x, tile_y, |neighbour_x, neighbour_y| {
+                let mut light = map.get_light(neighbour_x, neighbour_y);
+                let neighbour_level = light[channel];
+                if neighbour_level != 0 && neighbour_level < level {
+                    // this was (probably) lit by the removed light, so it goes too
+                    light[channel] = 0;
+                    map.set_light(neighbour_x, neighbour_y, light);
+                    queue.push_back((neighbour_x, neighbour_y, neighbour_level));
+                    cleared.push((neighbour_x, neighbour_y));
+                } else if neighbour_level != 0 {
+                    // lit by something else, so it can fill the gap back in
+                    relight.push_back((neighbour_x, neighbour_y));
+                }
+            });
+        }
+    }
+
+    // any light sources caught up in the removal get their own light back
+    for (cleared_x, cleared_y) in cleared {
+        let emission = map.light_emission(cleared_x, cleared_y);
+        if emission == [0, 0, 0] { continue; }
+        let light = map.get_light(cleared_x, cleared_y);
+        map.set_light(cleared_x, cleared_y, [light[0].max(emission[0]), light[1].max(emission[1]), light[2].max(emission[2])]);
+        relight.push_back((cleared_x, cleared_y));
+    }
+    propagate_light(map, &mut relight);
+}
+
+/// Recalculates the light around the tiles after they change (a light being placed or broken, or a wall opening up or closing off)
+pub fn refresh_lights<M: LightMap>(map: &mut M, tiles: &[(usize, usize)]) {
+    remove_lights(map, tiles);
+    // the neighbours have to spread back in even if nothing was removed, as the falloff through the tiles may have changed
+    let size = map.light_size();
+    let mut queue = VecDeque::new();
+    for (x, y) in tiles {
+        for_each_neighbour(size, *x, *y, |neighbour_x, neighbour_y| queue.push_back((neighbour_x, neighbour_y)));
+    }
+    propagate_light(map, &mut queue);
+}
+
+/// Lights the whole map from scratch
+pub fn flood_all_lights<M: LightMap>(map: &mut M) {
+    flood_all_lights_with_progress(map, |_| true);
+}
+
+/// Same as flood_all_lights, but calls `progress` every so often with how far along it is (0 to 1)
+/// Returns false if `progress` asked for it to stop part way through
+pub fn flood_all_lights_with_progress<M: LightMap>(map: &mut M, mut progress: impl FnMut(f32) -> bool) -> bool {
+    let size = map.light_size();
+    let mut queue = VecDeque::new();
+    // seeding the light sources is counted as the first half, and spreading them as the second
+    let rows_per_report = (PROGRESS_INTERVAL / size.0.max(1)).max(1);
+    for y in 0..size.1 {
+        if y % rows_per_report == 0 && !progress(y as f32 / size.1 as f32 * 0.5) {
+            return false;
+        }
+        for x in 0..size.0 {
+            let emission = map.light_emission(x, y);
+            map.set_light(x, y, emission);
+            if emission != [0, 0, 0] {
+                queue.push_back((x, y));
+            }
+        }
+    }
+    propagate_light_with_progress(map, &mut queue, |spread| progress(0.5 + spread * 0.5))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static TORCH: [u8; 3] = [255, 255, 128];
+
+    /// A small open map where any tile can be made solid or turned into a light
+    struct TestMap {
+        solid: Vec<Vec<bool>>,
+        lights: Vec<Vec<[u8; 3]>>,
+        lighting: Vec<Vec<[u8; 3]>>,
+    }
+
+    impl TestMap {
+        fn new() -> Self {
+            TestMap {
+                solid: vec![vec![false; 9]; 9],
+                lights: vec![vec![[0; 3]; 9]; 9],
+                lighting: vec![vec![[0; 3]; 9]; 9],
+            }
+        }
+
+        fn set_emission(&mut self, x: usize, y: usize, light: [u8; 3]) {
+            self.lights[y][x] = light;
+            refresh_lights(self, &[(x, y)]);
+        }
+    }
+
+    impl LightMap for TestMap {
+        fn light_size(&self) -> (usize, usize) {
+            (9, 9)
+        }
+
+        fn get_light(&self, x: usize, y: usize) -> [u8; 3] {
+            self.lighting[y][x]
+        }
+
+        fn set_light(&mut self, x: usize, y: usize, light: [u8; 3]) {
+            self.lighting[y][x] = light;
+        }
+
+        fn light_falloff(&self, x: usize, y: usize) -> u8 {
+            match self.solid[y][x] {
+                true => LIGHT_FALLOFF_SOLID,
+                false => LIGHT_FALLOFF_AIR,
+            }
+        }
+
+        fn light_emission(&self, x: usize, y: usize) -> [u8; 3] {
+            self.lights[y][x]
+        }
+    }
+
+    #[test]
+    fn placed_lights_fall_off_with_distance() {
+        let mut map = TestMap::new();
+        map.set_emission(4, 4, TORCH);
+        assert_eq!(map.get_light(4, 4), TORCH);
+        assert_eq!(map.get_light(5, 4), [230, 230, 103]);
+        assert_eq!(map.get_light(4, 2), [205, 205, 78]);
+        // it spreads around corners rather than diagonally
+        assert_eq!(map.get_light(5, 5), [205, 205, 78]);
+        assert_eq!(map.get_light(0, 0), [55, 55, 0]);
+    }
+
+    #[test]
+    fn removed_lights_leave_nothing_behind() {
+        let mut map = TestMap::new();
+        map.set_emission(4, 4, TORCH);
+        map.set_emission(4, 4, [0, 0, 0]);
+        assert!(map.lighting.iter().flatten().all(|light| *light == [0, 0, 0]));
+    }
+
+    #[test]
+    fn solid_tiles_block_the_light() {
+        let mut map = TestMap::new();
+        for y in 0..9 {
+            for x in 5..8 {
+                map.solid[y][x] = true;
+            }
+        }
+        map.set_emission(4, 4, TORCH);
+        assert_eq!(map.get_light(3, 4), [230, 230, 103]);
+        assert_eq!(map.get_light(5, 4), [175, 175, 48]);
+        // three tiles of wall is enough to stop it completely
+        assert!((0..9).all(|y| map.get_light(8, y) == [0, 0, 0]));
+    }
+
+    #[test]
+    fn overlapping_lights_keep_the_brightest_of_each_channel() {
+        let mut map = TestMap::new();
+        map.set_emission(2, 4, TORCH);
+        map.set_emission(6, 4, [100, 100, 255]);
+        assert_eq!(map.get_light(4, 4), [205, 205, 205]);
+
+        // taking one away falls back to what the other gives
+        map.set_emission(6, 4, [0, 0, 0]);
+        assert_eq!(map.get_light(4, 4), [205, 205, 78]);
+        assert_eq!(map.get_light(6, 4), [155, 155, 28]);
+    }
+}
@@ -0,0 +1,301 @@
+use crate::game_manager::world::tile_map::{TileMap, TileMapError};
+
+/// The number of tiles along each side of a liquid chunk (only chunks with moving liquid get simulated)
+pub static LIQUID_CHUNK_SIZE: usize = 32;
+/// How many times the liquids flow each second
+static LIQUID_TICKS_PER_SECOND: f64 = 20.0;
+pub static MAX_LIQUID_LEVEL: u8 = 255;
+/// Anything shallower than this won't spread sideways (otherwise thin puddles jitter back and forth forever)
+static MIN_SIDEWAYS_FLOW: u8 = 2;
+/// The tile formed when water and lava touch
+static LIQUID_REACTION_TILE: u32 = 44;
+
+#[repr(u8)]
+#[derive(bincode::Encode, bincode::Decode, Clone, Copy, PartialEq, Debug, Default)]
+pub enum LiquidType {
+    #[default] Empty = 0,
+    Water = 1,
+    Lava = 2,
+}
+
+pub struct LiquidInfo {
+    pub liquid: LiquidType,
+    pub texture: u32,  // the tile texture drawn over the cell
+    pub light: Option<[u8; 3]>,  // works the same as the TILE_LIGHTS
+    pub flow_interval: u32,  // the number of ticks between each flow (lava is a lot thicker than water)
+    pub map_color: [u8; 3],  // the flat color used when exporting maps
+}
+
+pub static LIQUIDS: &[LiquidInfo] = &[
+    LiquidInfo {
+        liquid: LiquidType::Water,
+        texture: 176,
+        light: None,
+        flow_interval: 1,
+        map_color: [40, 90, 200],
+    },
+    LiquidInfo {
+        liquid: LiquidType::Lava,
+        texture: 175,
+        light: Some([255, 120, 40]),
+        flow_interval: 4,
+        map_color: [230, 90, 20],
+    },
+];
+
+pub fn get_liquid_info(liquid: LiquidType) -> Option<&'static LiquidInfo> {
+    LIQUIDS.iter().find(|info| info.liquid == liquid)
+}
+
+#[derive(bincode::Encode, bincode::Decode, Clone, Copy, Default)]
+pub struct LiquidCell {
+    pub liquid: LiquidType,
+    pub level: u8,
+}
+
+impl LiquidCell {
+    pub fn new(liquid: LiquidType, level: u8) -> Self {
+        LiquidCell { liquid, level }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.level == 0 || self.liquid == LiquidType::Empty
+    }
+}
+
+/// Stores the liquids in a grid parallel to the tiles along with which chunks still need simulating
+#[derive(bincode::Encode, bincode::Decode)]
+pub struct LiquidMap {
+    cells: Vec<Vec<LiquidCell>>,
+    active_chunks: Vec<Vec<bool>>,
+    tick_timer: f64,
+    tick: u32,
+}
+
+impl LiquidMap {
+    pub fn new(width: usize, height: usize) -> Self {
+        LiquidMap {
+            cells: vec![vec![LiquidCell::default(); width]; height],
+            active_chunks: vec![vec![false; width.div_ceil(LIQUID_CHUNK_SIZE)]; height.div_ceil(LIQUID_CHUNK_SIZE)],
+            tick_timer: 0.0,
+            tick: 0,
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> LiquidCell {
+        if y >= self.cells.len() || x >= self.cells[0].len() { return LiquidCell::default(); }
+        self.cells[y][x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, cell: LiquidCell) {
+        if y >= self.cells.len() || x >= self.cells[0].len() { return; }
+        self.cells[y][x] = match cell.level {
+            0 => LiquidCell::default(),
+            _ => cell,
+        };
+    }
+
+    /// Marks every chunk touching the tile (and its direct neighbours) as needing to be simulated
+    pub fn wake_area(&mut self, x: usize, y: usize) {
+        for chunk_y in y.saturating_sub(1) / LIQUID_CHUNK_SIZE..=((y + 1) / LIQUID_CHUNK_SIZE).min(self.active_chunks.len() - 1) {
+            for chunk_x in x.saturating_sub(1) / LIQUID_CHUNK_SIZE..=((x + 1) / LIQUID_CHUNK_SIZE).min(self.active_chunks[0].len() - 1) {
+                self.active_chunks[chunk_y][chunk_x] = true;
+            }
+        }
+    }
+}
+
+impl TileMap {
+    /// Steps the liquid simulation forward at a fixed rate
+    pub fn update_liquids(&mut self, delta_time: f64) -> Result<(), TileMapError> {
+        self.liquids.tick_timer += delta_time;
+        // capping it so a long frame doesn't stack up a huge number of ticks
+        self.liquids.tick_timer = self.liquids.tick_timer.min(4.0 / LIQUID_TICKS_PER_SECOND);
+        while self.liquids.tick_timer >= 1.0 / LIQUID_TICKS_PER_SECOND {
+            self.liquids.tick_timer -= 1.0 / LIQUID_TICKS_PER_SECOND;
+            self.tick_liquids()?;
+        } Ok(())
+    }
+
+    fn tick_liquids(&mut self) -> Result<(), TileMapError> {
+        self.liquids.tick = self.liquids.tick.wrapping_add(1);
+
+        // collecting the chunks first; they go back to sleep unless something inside them moves this tick
+        let mut chunks = vec![];
+        for (chunk_y, row) in self.liquids.active_chunks.iter_mut().enumerate() {
+            for (chunk_x, active) in row.iter_mut().enumerate() {
+                if *active {
+                    chunks.push((chunk_x, chunk_y));
+                    *active = false;
+                }
+            }
+        }
+        // bottom up so falling liquid doesn't get moved multiple times in a single tick
+        chunks.sort_by_key(|(_chunk_x, chunk_y)| std::cmp::Reverse(*chunk_y));
+
+        let mut light_changes = vec![];
+        let flow_right = self.liquids.tick.is_multiple_of(2);  // alternating the direction removes the bias towards one side
+        for (chunk_x, chunk_y) in chunks {
+            let end_y = ((chunk_y + 1) * LIQUID_CHUNK_SIZE).min(self.get_map_height());
+            let end_x = ((chunk_x + 1) * LIQUID_CHUNK_SIZE).min(self.get_map_width());
+            for y in (chunk_y * LIQUID_CHUNK_SIZE..end_y).rev() {
+                for offset in 0..end_x - chunk_x * LIQUID_CHUNK_SIZE {
+                    let x = match flow_right {
+                        true => chunk_x * LIQUID_CHUNK_SIZE + offset,
+                        false => end_x - 1 - offset,
+                    };
+                    self.flow_liquid_cell(x, y, flow_right, &mut light_changes)?;
+                }
+            }
+        }
+
+        // only refreshing the lighting once per spot, as it's fairly expensive
+        light_changes.sort();
+        light_changes.dedup();
+        self.refresh_lights(&light_changes);
+        Ok(())
+    }
+
+    fn flow_liquid_cell(&mut self, x: usize, y: usize, flow_right: bool, light_changes: &mut Vec<(usize, usize)>) -> Result<(), TileMapError> {
+        let cell = self.liquids.get(x, y);
+        if cell.is_empty() { return Ok(()); }
+        let info = match get_liquid_info(cell.liquid) {
+            Some(info) => info,
+            None => return Ok(()),
+        };
+        if !self.liquids.tick.is_multiple_of(info.flow_interval) {
+            // not ready to move yet, but it still needs to be checked next tick
+            self.liquids.wake_area(x, y);
+            return Ok(());
+        }
+
+        // falling takes priority over spreading
+        if y + 1 < self.get_map_height() && !self.is_solid(x, y + 1) {
+            self.transfer_liquid(x, y, x, y + 1, cell.level, light_changes)?;
+        }
+
+        // spreading out to either side (evening out the levels)
+        let sides = match flow_right {
+            true => [x.checked_add(1), x.checked_sub(1)],
+            false => [x.checked_sub(1), x.checked_add(1)],
+        };
+        for side_x in sides.into_iter().flatten() {
+            let cell = self.liquids.get(x, y);
+            if cell.level < MIN_SIDEWAYS_FLOW { break; }
+            if side_x >= self.get_map_width() || self.is_solid(side_x, y) { continue; }
+            let side_cell = self.liquids.get(side_x, y);
+            let difference = match side_cell.liquid == cell.liquid || side_cell.is_empty() {
+                true => cell.level.saturating_sub(side_cell.level),
+                false => cell.level,  // a different liquid, so they'll react
+            };
+            if difference < MIN_SIDEWAYS_FLOW { continue; }
+            self.transfer_liquid(x, y, side_x, y, (difference / 3).max(1), light_changes)?;
+        } Ok(())
+    }
+
+    /// Moves up to the given amount of liquid between two cells, reacting if the liquids differ
+    fn transfer_liquid(&mut self, from_x: usize, from_y: usize, to_x: usize, to_y: usize, amount: u8, light_changes: &mut Vec<(usize, usize)>) -> Result<(), TileMapError> {
+        let from = self.liquids.get(from_x, from_y);
+        let to = self.liquids.get(to_x, to_y);
+        if !to.is_empty() && to.liquid != from.liquid {
+            // water and lava touching turn into stone
+            let lost_light = [from, to].iter().any(|cell| get_liquid_info(cell.liquid).is_some_and(|info| info.light.is_some()));
+            self.liquids.set(to_x, to_y, LiquidCell::default());
+            self.liquids.set(from_x, from_y, LiquidCell::new(from.liquid, from.level.saturating_sub(amount)));
+            self.change_tile(to_x, to_y, 0, LIQUID_REACTION_TILE)?;
+            if lost_light {
+                light_changes.push((to_x, to_y));
+            }
+            self.liquids.wake_area(from_x, from_y);
+            return Ok(());
+        }
+
+        let moved = amount.min(MAX_LIQUID_LEVEL - to.level).min(from.level);
+        if moved == 0 { return Ok(()); }
+        self.liquids.set(to_x, to_y, LiquidCell::new(from.liquid, to.level + moved));
+        self.liquids.set(from_x, from_y, LiquidCell::new(from.liquid, from.level - moved));
+        self.liquids.wake_area(from_x, from_y);
+        self.liquids.wake_area(to_x, to_y);
+
+        if get_liquid_info(from.liquid).is_some_and(|info| info.light.is_some()) {
+            if to.is_empty() {
+                light_changes.push((to_x, to_y));
+            }
+            if from.level == moved {
+                light_changes.push((from_x, from_y));
+            }
+        } Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_manager::world::tile_map::STONE_IDS;
+    use crate::game_manager::world::tile_map::registry::{TileType, get_tile_properties};
+
+    /// An 8x8 map with a stone floor along the bottom row
+    fn floored_tile_map() -> TileMap {
+        let mut tile_map = TileMap::test_tile_map(8, 8);
+        for x in 0..8 {
+            tile_map.change_tile(x, 7, 0, STONE_IDS[0]).unwrap();
+        }
+        tile_map
+    }
+
+    fn pour(tile_map: &mut TileMap, x: usize, y: usize, liquid: LiquidType) {
+        tile_map.liquids.set(x, y, LiquidCell::new(liquid, MAX_LIQUID_LEVEL));
+        tile_map.liquids.wake_area(x, y);
+    }
+
+    fn run_ticks(tile_map: &mut TileMap, ticks: usize) {
+        for _ in 0..ticks {
+            tile_map.tick_liquids().unwrap();
+        }
+    }
+
+    #[test]
+    fn water_spreads_out_and_levels_across_a_flat_floor() {
+        let mut tile_map = floored_tile_map();
+        pour(&mut tile_map, 4, 2, LiquidType::Water);
+        run_ticks(&mut tile_map, 400);
+
+        // it all fell down to the floor without losing any along the way
+        for y in 0..6 {
+            assert!((0..8).all(|x| tile_map.liquids.get(x, y).is_empty()));
+        }
+        let levels = (0..8).map(|x| tile_map.liquids.get(x, 6).level as u32).collect::<Vec<u32>>();
+        assert_eq!(levels.iter().sum::<u32>(), MAX_LIQUID_LEVEL as u32);
+        // neighbours only stop flowing once they're within a level of each other
+        assert!(levels.windows(2).all(|pair| pair[0].abs_diff(pair[1]) < MIN_SIDEWAYS_FLOW as u32));
+        assert!(levels.iter().all(|level| *level > 0));
+    }
+
+    #[test]
+    fn solid_tiles_push_the_liquid_out() {
+        let mut tile_map = floored_tile_map();
+        pour(&mut tile_map, 4, 6, LiquidType::Water);
+        tile_map.change_tile(4, 6, 0, STONE_IDS[0]).unwrap();
+        assert!(tile_map.liquids.get(4, 6).is_empty());
+
+        // and nothing flows back into it
+        pour(&mut tile_map, 3, 6, LiquidType::Water);
+        run_ticks(&mut tile_map, 100);
+        assert!(tile_map.liquids.get(4, 6).is_empty());
+    }
+
+    #[test]
+    fn lava_and_water_react_into_stone() {
+        let mut tile_map = floored_tile_map();
+        pour(&mut tile_map, 3, 6, LiquidType::Water);
+        pour(&mut tile_map, 4, 6, LiquidType::Lava);
+        run_ticks(&mut tile_map, 8);
+
+        // (the stone gets autotiled, so it's checked by type rather than the exact tile)
+        let reacted = (0..8).filter(|x| get_tile_properties(tile_map.get_tile(*x, 6, 0)).tile_type == TileType::Stone).collect::<Vec<usize>>();
+        assert_eq!(reacted.len(), 1);
+        assert!(reacted[0] == 3 || reacted[0] == 4);
+        assert!(tile_map.liquids.get(reacted[0], 6).is_empty());
+        assert!(tile_map.is_solid(reacted[0], 6));
+    }
+}
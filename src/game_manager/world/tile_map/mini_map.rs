@@ -1,3 +1,5 @@
+use crate::game_manager::world::tile_map::liquids::{LiquidMap, get_liquid_info};
+use crate::game_manager::world::tile_map::registry::get_tile_properties;
 use crate::{game_manager::entities::player::player::CameraTransform, logging::logging::{Log, Logs}, textures::textures::{TextureError, get_texture_atlas}};
 
 #[derive(bincode::Encode, bincode::Decode)]
@@ -30,6 +32,7 @@ impl MiniMap {
     pub fn render(
         &self,
         tiles: &Vec<Vec<[u32; 3]>>,
+        liquids: &LiquidMap,
         pixels: &mut [u8],
         window_slice_size: (usize, usize),
         window_slice_position: (usize, usize),
@@ -58,7 +61,25 @@ impl MiniMap {
                 let texture_x = ((tile_x - tile_x.floor()) * 4.0) as usize;
                 let texture_y = ((tile_y - tile_y.floor()) * 4.0) as usize;
                 let light = self.lighting[tile_y as usize][tile_x as usize];
-                let texture = &mini_map_textures[tiles[tile_y as usize][tile_x as usize][0] as usize];
+                let liquid = liquids.get(tile_x as usize, tile_y as usize);
+                let texture_id = match get_liquid_info(liquid.liquid) {
+                    Some(info) if !liquid.is_empty() => info.texture,
+                    _ => tiles[tile_y as usize][tile_x as usize][0],
+                };
+                // the atlas is padded out with blank textures, so a fully transparent one means there's no icon (air is meant to be blank though)
+                let icon = mini_map_textures.get(texture_id as usize)
+                    .filter(|texture| texture_id == 0 || texture.iter().any(|pixel| (pixel >> 24) & 0xFF != 0));
+                let texture = match icon {
+                    Some(texture) => texture,
+                    None => {
+                        // no icon for this tile, so it's just drawn as a flat color
+                        let color = get_tile_properties(texture_id).map_color;
+                        for (channel, value) in color.iter().enumerate() {
+                            pixels[pixel_x * 3 + pixel_y * pitch + channel] = (*value as f32 * light) as u8;
+                        }
+                        continue;
+                    },
+                };
                 let texture_index = texture_x + texture_y * 4;
                 let alpha = ((texture[texture_index] >> 24) & 0xFF) as f32 / 255.0;
                 pixels[pixel_x * 3 + pixel_y * pitch    ] = lerp(((texture[texture_index]      ) & 0xFF) as f32 * light, light * 255.0, alpha);
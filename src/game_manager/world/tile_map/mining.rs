@@ -0,0 +1,60 @@
+use crate::game_manager::world::tile_map::TileMap;
+use crate::game_manager::world::tile_map::tile_data::BREAK_DAMAGE;
+
+/// How long a tile has to be left alone before its damage starts wearing off
+static DAMAGE_DECAY_DELAY: f32 = 0.4;
+/// The fraction of a tile's health recovered each second once it's decaying
+static DAMAGE_DECAY_RATE: f32 = 0.75;
+
+/// A tile that's part way through being mined
+#[derive(bincode::Encode, bincode::Decode)]
+pub struct DamagedTile {
+    x: usize,
+    y: usize,
+    damage: f32,  // 0 to 1, with 1 being broken
+    idle_time: f32,  // the time since it was last hit
+}
+
+impl TileMap {
+    /// Adds damage (as a fraction of the tile's health) to the tile on the main layer, returning true once it should break
+    pub fn damage_tile(&mut self, tile_x: usize, tile_y: usize, damage: f32) -> bool {
+        let index = match self.damaged_tiles.iter().position(|tile| tile.x == tile_x && tile.y == tile_y) {
+            Some(index) => index,
+            None => {
+                self.damaged_tiles.push(DamagedTile { x: tile_x, y: tile_y, damage: 0.0, idle_time: 0.0 });
+                self.damaged_tiles.len() - 1
+            },
+        };
+        let tile = &mut self.damaged_tiles[index];
+        tile.damage += damage;
+        tile.idle_time = 0.0;
+        if tile.damage >= 1.0 {
+            self.damaged_tiles.swap_remove(index);
+            return true;
+        }
+        let stage = (tile.damage * 255.0) as u32;
+        self.set_tile_data_field(tile_x, tile_y, 0, BREAK_DAMAGE, stage);
+        false
+    }
+
+    /// Heals any tiles that haven't been mined for a little while
+    pub fn update_tile_damage(&mut self, delta_time: f64) {
+        let mut healed = vec![];
+        for tile in self.damaged_tiles.iter_mut() {
+            tile.idle_time += delta_time as f32;
+            if tile.idle_time < DAMAGE_DECAY_DELAY { continue; }
+            tile.damage -= DAMAGE_DECAY_RATE * delta_time as f32;
+            healed.push((tile.x, tile.y, (tile.damage.max(0.0) * 255.0) as u32));
+        }
+        self.damaged_tiles.retain(|tile| tile.damage > 0.0);
+        for (x, y, stage) in healed {
+            self.set_tile_data_field(x, y, 0, BREAK_DAMAGE, stage);
+        }
+    }
+
+    /// Forgets any damage on the tile, wiping its cracks (called whenever the tile gets replaced)
+    pub(crate) fn clear_tile_damage(&mut self, tile_x: usize, tile_y: usize) {
+        self.damaged_tiles.retain(|tile| tile.x != tile_x || tile.y != tile_y);
+        self.set_tile_data_field(tile_x, tile_y, 0, BREAK_DAMAGE, 0);
+    }
+}
@@ -1,10 +1,36 @@
 use crate::game_manager::entities::player::player::CameraTransform;
 use crate::game_manager::game::GameError;
 use crate::game_manager::world::tile_map::mini_map::MiniMap;
-use crate::game_manager::world::world_gen::WorldGenerator;
+use crate::game_manager::world::tile_map::liquids::{LiquidCell, LiquidMap, get_liquid_info};
+use crate::game_manager::world::tile_map::tile_data::{SHAPE, TILE_FIELDS, TileDataField, WIRING};
+use crate::game_manager::world::tile_map::mining::DamagedTile;
+use crate::game_manager::world::tile_map::registry::{CollisionShape, get_tile_properties};
+use crate::game_manager::world::tile_map::collision::TileShape;
+use crate::game_manager::world::tile_map::entity_lights::EntityLights;
+use crate::game_manager::world::tile_map::random_ticks::RandomTicks;
+use crate::game_manager::world::tile_map::edit_history::EditHistory;
+use crate::game_manager::world::tile_map::wiring::Wiring;
+use crate::game_manager::world::tile_map::light_engine::{LightMap, LIGHT_FALLOFF_AIR, LIGHT_FALLOFF_SOLID};
+use crate::game_manager::world::world_gen::{Biom, Layer, WorldGenerator};
+use crate::game_manager::world::world_gen::progress::GenerationReporter;
 use crate::logging::logging::{LoggingError, Logs};
 
 pub mod mini_map;
+pub mod liquids;
+pub mod tile_data;
+pub mod mining;
+pub mod registry;
+pub mod autotile;
+pub mod light_engine;
+pub mod sky_light;
+pub mod entity_lights;
+pub mod random_ticks;
+pub mod trees;
+pub mod portals;
+pub mod edit_history;
+pub mod spawn;
+pub mod wiring;
+pub mod collision;
 
 pub static GRASS_IDS: &[u32]      = &[1  , 2  , 3  , 4  , 5  , 6  , 7  , 8  , 9  , 10 , 11 , 12 , 13 , 14 , 47 ];
 pub static DIRT_IDS: &[u32]       = &[15 , 16 , 17 , 18 , 19 , 20 , 21 , 22 , 23 , 24 , 25 , 26 , 27 , 28 , 29 , 46 ];
@@ -15,34 +41,9 @@ pub static ICE_IDS: &[u32]        = &[118, 119, 120, 121, 122, 123, 124, 125, 12
 pub static SNOW_IDS: &[u32]       = &[137, 138, 139, 140, 141, 142, 143, 144, 145, 146, 147, 148, 149, 150, 151, 135];
 pub static CACTUS_IDS: &[u32]     = &[153, 154, 155, 156, 157, 158, 159, 160, 161, 162];
 pub static WOOD_IDS: &[u32]       = &[163, 164, 165, 166, 167, 168, 169, 170, 171, 172];
-pub static TILE_LIGHTS: &[(u32, [u8; 3])] = &[
-    (88, [255, 255, 128]),
-    (173, [200, 200, 128])
-];
-
-pub static SOLID_TILES: &[&[u32]] = &[
-    GRASS_IDS,
-    DIRT_IDS,
-    STONE_IDS,
-    SAND_IDS,
-    SAND_STONE_IDS,
-    ICE_IDS,
-    SNOW_IDS,
-    WOOD_IDS,
-];
-
-#[derive(bincode::Encode, bincode::Decode)]
-pub struct EntityLight {
-    pub(crate) position: (f32, f32),
-    pub(crate) color: (u8, u8, u8, f32),
-}
-
-impl EntityLight {
-    pub fn new(position: (f32, f32), color: (u8, u8, u8, f32)) -> Self {
-        Self { position, color }
-    }
-}
+pub static ASH_IDS: &[u32]        = &[174];
 
+#[derive(Debug)]
 pub struct TileMapError {
     pub(crate) message: String,
     pub(crate) level: LoggingError,
@@ -70,69 +71,93 @@ impl From<TileMapError> for String {
 #[derive(bincode::Encode, bincode::Decode)]
 pub struct TileMap {
     pub tiles: Vec<Vec<[u32; 3]>>,
+    tile_data: Vec<Vec<[u32; 3]>>,  // the per tile state (see tile_data.rs for the layout)
     lighting: Vec<Vec<[u8; 3]>>,
-    pub sky_light: Vec<u32>,
-    pub(crate) entity_lights: Vec<(String, EntityLight)>,
+    pub sky_light: Vec<u32>,  // the first solid row of each column (everything above it is lit directly by the sky)
+    sky_lighting: Vec<Vec<u8>>,  // the sky light spread out from the open tiles (scaled by the time of day when rendering)
+    pub(crate) entity_lights: EntityLights,  // the dynamic lights carried around by entities
     pub(crate) mini_map: mini_map::MiniMap,
+    pub(crate) liquids: LiquidMap,
+    gravity_checks: Vec<(usize, usize)>,  // tiles that may have lost their support (handled by the entity manager as it spawns the falling tiles)
+    damaged_tiles: Vec<DamagedTile>,  // tiles part way through being mined (the damage is mirrored into the tile data for the crack overlay)
+    random_ticks: RandomTicks,
+    edit_history: EditHistory,  // the undo/redo history for builder mode
+    spawn_point: (usize, usize),  // the tile new players start in (see spawn.rs)
+    bioms: Vec<Biom>,  // the main biom of each column (set as the terrain is generated)
+    wiring: Wiring,  // the networks of wire joining up the switches and the things they power
 }
 
 impl TileMap {
     pub fn new(width: usize, height: usize, world_generator: Option<&WorldGenerator>, logs: &mut Logs) -> Result<Self, TileMapError> {
         let mut tile_map = TileMap {
             tiles: vec![vec![[0; 3]; width]; height],
+            tile_data: vec![vec![[0; 3]; width]; height],
             lighting: vec![vec![[0; 3]; width]; height],
             sky_light: vec![height as u32; width],
-            entity_lights: Vec::new(),
+            sky_lighting: vec![vec![0; width]; height],
+            entity_lights: EntityLights::new(),
             mini_map: MiniMap::new(width, height, logs).map_err(|e| TileMapError {
                 message: format!("Failed to create MiniMap: {:?}", e),
                 level: LoggingError::Error,
             })?,
+            liquids: LiquidMap::new(width, height),
+            gravity_checks: Vec::new(),
+            damaged_tiles: Vec::new(),
+            random_ticks: RandomTicks::new(),
+            edit_history: EditHistory::new(),
+            spawn_point: (width / 2, height / 2),
+            bioms: vec![Biom::Forest; width],
+            wiring: Wiring::new(),
         };
         if let Some(generator) = world_generator {
-            generator.generate_tile_map(&mut tile_map)?;
+            generator.generate_tile_map(&mut tile_map, &GenerationReporter::none())?;
         }
         Ok(tile_map)
     }
 
-    pub fn add_entity_light(&mut self, name: String, position: (f32, f32), color: (u8, u8, u8, f32)) {
-        self.entity_lights.push((name, EntityLight::new(position, color)));
-    }
-
-    pub fn update_entity_light(&mut self, name: &str, position: (f32, f32), color: (u8, u8, u8, f32)) {
-        if let Some(light) = self.entity_lights.iter_mut().find(|(n, _)| n == name) {
-            light.1 = EntityLight::new(position, color);
-        }
+    /// An empty tile map for the tests (with nothing generated and the logs thrown away)
+    #[cfg(test)]
+    pub(crate) fn test_tile_map(width: usize, height: usize) -> Self {
+        let (_concluded_sender, concluded_receiver) = crossbeam::channel::bounded(1);
+        let mut logs = Logs::new(concluded_receiver, crate::logging::logging::Logging::Nothing);
+        TileMap::new(width, height, None, &mut logs).unwrap()
     }
 
     pub fn get_tile_mut(&mut self, x: usize, y: usize, layer: usize) -> &mut u32 {
         &mut self.tiles[y][x][layer]
     }
 
-    pub fn check_aabb_collision(&self, x: f32, y: f32, width: f32, height: f32) -> bool {
-        let start_x = (x / 8.0).floor() as isize;
-        let start_y = (y / 8.0).floor() as isize;
-        let end_x = ((x + width) / 8.0).ceil() as isize;
-        let end_y = ((y + height) / 8.0).ceil() as isize;
+    pub fn get_tile(&self, x: usize, y: usize, layer: usize) -> u32 {
+        if y >= self.get_map_height() || x >= self.get_map_width() { return 0; }
+        self.tiles[y][x][layer]
+    }
+
+    /// The liquid sitting in the tile (the cell is empty outside the map)
+    pub fn get_liquid(&self, x: usize, y: usize) -> LiquidCell {
+        self.liquids.get(x, y)
+    }
 
-        for tile_y in start_y..end_y {
-            for tile_x in start_x..end_x {
-                if tile_x < 0 || tile_y < 0 || tile_y as usize >= self.get_map_height() || tile_x as usize >= self.get_map_width() {
-                    continue;
-                }
-                let tile_id = self.get_tile(tile_x as usize, tile_y as usize, 0);
-                for solid_ids in SOLID_TILES {
-                    if solid_ids.contains(&tile_id) {
-                        return true;
-                    }
-                }
-            }
+    pub fn get_tile_data(&self, x: usize, y: usize, layer: usize) -> u32 {
+        if y >= self.get_map_height() || x >= self.get_map_width() { return 0; }
+        self.tile_data[y][x][layer]
+    }
+
+    pub fn set_tile_data(&mut self, x: usize, y: usize, layer: usize, data: u32) {
+        if y >= self.get_map_height() || x >= self.get_map_width() { return; }
+        // the wires live in the main layer's data
+        if layer == 0 && WIRING.get(data) != WIRING.get(self.tile_data[y][x][0]) {
+            self.wires_changed(x, y, WIRING.get(data));
         }
-        false
+        self.tile_data[y][x][layer] = data;
     }
 
-    pub fn get_tile(&self, x: usize, y: usize, layer: usize) -> u32 {
-        if y >= self.get_map_height() || x >= self.get_map_width() { return 0; }
-        self.tiles[y][x][layer]
+    pub fn get_tile_data_field(&self, x: usize, y: usize, layer: usize, field: TileDataField) -> u32 {
+        field.get(self.get_tile_data(x, y, layer))
+    }
+
+    pub fn set_tile_data_field(&mut self, x: usize, y: usize, layer: usize, field: TileDataField, value: u32) {
+        let data = field.set(self.get_tile_data(x, y, layer), value);
+        self.set_tile_data(x, y, layer, data);
     }
 
     pub fn get_map_width(&self) -> usize {
@@ -143,117 +168,101 @@ impl TileMap {
         self.tiles.len()
     }
 
-    pub fn get_light_mut(&mut self, x: usize, y: usize) -> &mut [u8; 3] {
-        &mut self.lighting[y][x]
+    /// Takes the positions that need checking for unsupported gravity tiles
+    pub fn take_gravity_checks(&mut self) -> Vec<(usize, usize)> {
+        std::mem::take(&mut self.gravity_checks)
     }
 
-    pub fn change_tile(&mut self, tile_x: usize, tile_y: usize, layer: usize, new_tile: u32) -> Result<(), TileMapError> {
-        let mut min_edit_height = tile_y;
-        let mut max_edit_height = tile_y;
-        let mut edit_width = 0;
+    /// The biom at the position (bioms only change going across the world, so the row is just checked to be on the map)
+    pub fn biome_at(&self, x: usize, y: usize) -> Option<Biom> {
+        if y >= self.get_map_height() { return None; }
+        self.bioms.get(x).copied()
+    }
 
-        let was_removed_light = TILE_LIGHTS.iter().any(|(tile_id, _)| *tile_id == self.get_tile(tile_x, tile_y as usize, 0));
+    pub(crate) fn set_bioms(&mut self, bioms: Vec<Biom>) {
+        self.bioms = bioms;
+    }
+
+    /// Whether the tile blocks light and liquids (hammered half blocks and slopes let them through)
+    pub fn is_solid(&self, x: usize, y: usize) -> bool {
+        get_tile_properties(self.get_tile(x, y, 0)).is_solid() && self.get_tile_shape(x, y) == TileShape::Full
+    }
+
+    /// Gets the light given off by the tile or liquid at the position (if there is any)
+    pub fn get_light_emission(&self, x: usize, y: usize) -> Option<[u8; 3]> {
+        if let Some(light) = get_tile_properties(self.get_tile(x, y, 0)).light {
+            return Some(light);
+        }
+        let liquid = self.liquids.get(x, y);
+        if liquid.is_empty() { return None; }
+        get_liquid_info(liquid.liquid).and_then(|info| info.light)
+    }
+
+    /// How bright the tile is from the tile lights and the sky (scaled by the sky brightness), ignoring any entity lights
+    pub fn get_light_level(&self, x: usize, y: usize, sky_brightness: f32) -> u8 {
+        if x >= self.get_map_width() || y >= self.get_map_height() { return 0; }
+        let light = self.lighting[y][x];
+        let sky_light = (self.sky_lighting[y][x] as f32 * sky_brightness) as u8;
+        light[0].max(light[1]).max(light[2]).max(sky_light)
+    }
+
+    /// Recalculates the lighting around the tiles (after a light or wall was placed or removed)
+    pub fn refresh_lights(&mut self, tiles: &[(usize, usize)]) {
+        light_engine::refresh_lights(self, tiles);
+    }
+
+    /// Lights the whole map from scratch from every light emitting tile (only needed once the world is generated, the sky light is done separately)
+    /// The progress is passed along to the light engine, which stops early (returning false) if it returns false
+    pub fn flood_block_lights(&mut self, progress: impl FnMut(f32) -> bool) -> bool {
+        light_engine::flood_all_lights_with_progress(self, progress)
+    }
+
+    pub fn change_tile(&mut self, tile_x: usize, tile_y: usize, layer: usize, new_tile: u32) -> Result<(), TileMapError> {
+        let old_emission = self.get_light_emission(tile_x, tile_y);
+        let was_solid = self.is_solid(tile_x, tile_y);
+        let old_tile = self.get_tile(tile_x, tile_y, layer);
 
-        let mut light_was_edited = false;
         *self.get_tile_mut(tile_x, tile_y, layer) = new_tile;
-        if TILE_LIGHTS.iter().any(|(tile_id, _)| *tile_id == self.get_tile(tile_x, tile_y as usize, 0)) {
-            *self.get_light_mut(tile_x, tile_y as usize) = TILE_LIGHTS
-                .iter()
-                .find(|(tile_id, _)| *tile_id == self
-                    .get_tile(tile_x, tile_y as usize, 0))
-                .ok_or_else(|| TileMapError {
-                    message: format!("[Tilemap Error] Unable to locate light for tile at ({}, {}) while updating tiles", tile_x, tile_y),
-                    level: LoggingError::Error
-                })?.1;
-            light_was_edited = true;
-        } else if was_removed_light {
-            min_edit_height -= 8;
-            max_edit_height += 8;
-            edit_width = 8;
-            for x_index in tile_x.saturating_sub(16)..(tile_x + 16).min(self.get_map_width() - 1) {
-                for y_index in (tile_y - 16)..(tile_y + 16).min(self.get_map_height()) {
-                    if !TILE_LIGHTS.iter().any(|(tile_id, _)| *tile_id == self.get_tile(x_index, y_index as usize, 0)) {
-                        *self.get_light_mut(x_index, y_index as usize) = [0, 0, 0];
-                        continue;
-                    }
-                    *self.get_light_mut(x_index, y_index as usize) = TILE_LIGHTS
-                        .iter()
-                        .find(|(tile_id, _)| *tile_id == self
-                            .get_tile(x_index, y_index as usize, 0))
-                        .ok_or_else(|| TileMapError {
-                            message: format!("[Tilemap Error] Unable to locate light for tile at ({}, {}) while updating tiles and removing light source", x_index, y_index),
-                            level: LoggingError::Error
-                        })?.1;
-                }
-            }
-            light_was_edited = true;
+        // a new tile starts fresh, but anything belonging to the spot itself (like wires running through it) stays
+        let mut kept_data = TILE_FIELDS.iter().fold(self.get_tile_data(tile_x, tile_y, layer), |data, field| field.set(data, 0));
+        // the hammered shape only carries over onto another full block (like grass dying into dirt)
+        if get_tile_properties(new_tile).collision != CollisionShape::Full {
+            kept_data = SHAPE.set(kept_data, 0);
+        }
+        self.set_tile_data(tile_x, tile_y, layer, kept_data);
+        if layer == 0 {
+            self.clear_tile_damage(tile_x, tile_y);
+            self.check_wire_devices(old_tile, new_tile);
         }
+        let is_solid = self.is_solid(tile_x, tile_y);
+        if is_solid && layer == 0 {
+            // solid tiles push out any liquid that was there
+            self.liquids.set(tile_x, tile_y, LiquidCell::default());
+        }
+        // the surrounding liquids may now be able to flow (or be blocked)
+        self.liquids.wake_area(tile_x, tile_y);
 
-        let is_solid = SOLID_TILES.iter().any(|solid_ids| solid_ids.contains(&new_tile));
-        if tile_y <= self.sky_light[tile_x] as usize && is_solid {
-            self.sky_light[tile_x] = tile_y as u32;
-        } else if !is_solid {
-            self.sky_light[tile_x] = self.tiles.iter().enumerate().find_map::<u32, _>(|(index, tiles)| {
-                if tiles[tile_x].iter()
-                                .any(|&tile| SOLID_TILES
-                                .iter()
-                                .any(|solid_ids| solid_ids.contains(&tile)))
-                {
-                    Some(index as u32)
-                } else { None }
-            }).unwrap_or(self.get_map_height() as u32);
+        // only the main layer blocks light
+        if layer == 0 && (old_emission != self.get_light_emission(tile_x, tile_y) || was_solid != is_solid) {
+            self.refresh_lights(&[(tile_x, tile_y)]);
+        }
+        if layer == 0 && was_solid != is_solid {
+            self.update_sky_light(tile_x, tile_y, is_solid);
         }
 
-        if light_was_edited {
-            for _ in 0..16 {
-                for x in tile_x.saturating_sub(16 + edit_width)..(tile_x + 16 + edit_width).min(self.get_map_width() - 1) {
-                    for y in min_edit_height.saturating_sub(16)..(max_edit_height + 16).min(self.get_map_height()) {
-                        let left = self.get_light_mut(x.saturating_sub(1), y).iter().map(|f| f.saturating_sub(25)).collect::<Vec<u8>>();
-                        let right = self.get_light_mut((x + 1).min(self.get_map_width() - 1), y).iter().map(|f| f.saturating_sub(25)).collect::<Vec<u8>>();
-                        let up = self.get_light_mut(x, y.saturating_sub(1)).iter().map(|f| f.saturating_sub(25)).collect::<Vec<u8>>();
-                        let down = self.get_light_mut(x, (y + 1).min(self.get_map_height() - 1)).iter().map(|f| f.saturating_sub(25)).collect::<Vec<u8>>();
-                        let self_light = self.get_light_mut(x, y);
-                        let light_level = left.iter()
-                            .enumerate()
-                            .map(|(i, f)| (*f).max(right[i].max(up[i].max(down[i].max(self_light[i])))))
-                            .collect::<Vec<u8>>();
-                        *self.get_light_mut(x, y) = [light_level[0], light_level[1], light_level[2]];
-                    }
-                }
-            }
+        // anything resting on top of this may now be unsupported
+        if layer == 0 {
+            self.gravity_checks.push((tile_x, tile_y));
+            if tile_y > 0 { self.gravity_checks.push((tile_x, tile_y - 1)); }
         }
-        
-        // updating the surrounding tiles (really ugly.... but works)
-        for (x, y) in [(tile_x.saturating_sub(1), tile_y), (tile_x + 1, tile_y), (tile_x, tile_y.saturating_sub(1)), (tile_x, tile_y + 1)] {
-            if x >= self.get_map_width() || tile_y >= self.get_map_height() { continue; }
-            let tile = self.get_tile(x, y, layer);
-            WorldGenerator::update_edge_tiles(tile, &[GRASS_IDS], self, [
-                7, 8, 10, 1, 47, 5, 2, 4, 13, 9, 3, 6, 11, 12, 14, 29
-            ], x, y, 0);
-            WorldGenerator::update_edge_tiles(tile, &[DIRT_IDS], self, [
-                7 + 14, 8 + 14, 10 + 14, 1 + 14, 46, 5 + 14, 2 + 14, 4 + 14, 13 + 14, 9 + 14, 3 + 14, 6 + 14, 11 + 14, 12 + 14, 14 + 14, 29
-            ], x, y, 0);
-            WorldGenerator::update_edge_tiles(tile, &[STONE_IDS], self, [
-                7 + 29, 8 + 29, 10 + 29, 1 + 29, 45, 5 + 29, 2 + 29, 4 + 29, 13 + 29, 9 + 29, 3 + 29, 6 + 29, 11 + 29, 12 + 29, 14 + 29, 44
-            ], x, y, 0);
-            
-            WorldGenerator::update_edge_tiles(tile, &[SNOW_IDS], self, [
-                7 + 136, 8 + 136, 10 + 136, 1 + 136, 135, 5 + 136, 2 + 136, 4 + 136, 13 + 136, 9 + 136, 3 + 136, 6 + 136, 11 + 136, 12 + 136, 14 + 136, 29
-            ], x, y, 0);
-            WorldGenerator::update_edge_tiles(tile, &[ICE_IDS], self, [
-                7 + 117, 8 + 117, 10 + 117, 1 + 117, 136, 5 + 117, 2 + 117, 4 + 117, 13 + 117, 9 + 117, 3 + 117, 6 + 117, 11 + 117, 12 + 117, 14 + 117, 132
-            ], x, y, 0);
-            WorldGenerator::update_edge_tiles(tile, &[SAND_IDS], self, [
-                7 + 88, 8 + 88, 10 + 88, 1 + 88, 134, 5 + 88, 2 + 88, 4 + 88, 13 + 88, 9 + 88, 3 + 88, 6 + 88, 11 + 88, 12 + 88, 14 + 88, 117
-            ], x, y, 0);
-            WorldGenerator::update_edge_tiles(tile, &[SAND_STONE_IDS], self, [
-                7 + 102, 8 + 102, 10 + 102, 1 + 102, 133, 5 + 102, 2 + 102, 4 + 102, 13 + 102, 9 + 102, 3 + 102, 6 + 102, 11 + 102, 12 + 102, 14 + 102, 117
-            ], x, y, 0);
-        } Ok(())
+
+        // updating the surrounding tiles (the diagonals matter for the blob tile sets)
+        self.autotile_area(tile_x, tile_y, layer);
+        Ok(())
     }
     
     // todo! fix the bug here that happens when zooming where the tiles jump around a bit, not sure where it is tbh
-    pub fn get_render_slice(&mut self, camera_transform: &CameraTransform, window_size: (u32, u32)) -> (Vec<[u64; 4]>, CameraTransform, (u32, u32)) {
+    pub fn get_render_slice(&mut self, camera_transform: &CameraTransform, window_size: (u32, u32), world_generator: &WorldGenerator, sky_brightness: f32) -> (Vec<[u64; 4]>, CameraTransform, (u32, u32), Vec<u32>) {
         // don't even try to read this or the math, it's a mess, but seems to work for now
         
         // the plus 2 is to make sure blocks at the very edge aren't cut off
@@ -275,35 +284,43 @@ impl TileMap {
                 x: 0.0,
                 y: 0.0,
                 zoom: camera_transform.zoom,
-            }, (0, 0));
+            }, (0, 0), vec![]);
         }
         
+        // the background color of each visible row (based on which layer it's within)
+        let map_height = self.get_map_height();
+        let row_backgrounds = (start_y..end_y)
+            .map(|y| {
+                let layer = world_generator.get_layer(y, map_height);
+                // the sky darkens at night along with the sky light
+                match layer.layer {
+                    Layer::Surface => layer.get_packed_background_scaled(sky_brightness),
+                    _ => layer.get_packed_background(),
+                }
+            })
+            .collect::<Vec<u32>>();
+        
+        // the entity lights are spread over the whole visible area up front, rather than every tile checking every light
+        let entity_lighting = self.get_entity_lighting((start_x, start_y), (end_x, end_y));
+
         let mut visible_tiles: Vec<[u64; 4]> = Vec::with_capacity(((end_y - start_y) * (end_x - start_x)).max(0).min(1024 * 1024));
         for y in start_y..end_y {
+            let ambient_light = world_generator.get_layer(y, map_height).ambient_light;
             for x in start_x..end_x {
-                let mut light = [self.lighting[y][x][0], self.lighting[y][x][1], self.lighting[y][x][2]];
-                // going through entity lights and modifying it based on those
-                for (_ident, light_obj) in &self.entity_lights {
-                    let dif_x = light_obj.position.0 - (x as f32 * 8.0 + 4.0);
-                    let dif_y = light_obj.position.1 - (y as f32 * 8.0 + 4.0);
-                    if dif_x.abs() > 100.0 || dif_y.abs() > 100.0 { continue; }  // the light strength would be 0
-                    let distance = (dif_x * dif_x + dif_y * dif_y) * 0.00025;
-                    let light_strength = (light_obj.color.3 - distance).max(0.0);
-                    light = [
-                        light[0].max((light_obj.color.0 as f32 * light_strength) as u8),
-                        light[1].max((light_obj.color.1 as f32 * light_strength) as u8),
-                        light[2].max((light_obj.color.2 as f32 * light_strength) as u8),
-                    ];
-                }
-                
-                // computing the light contribution from the sky
-                let mut sky_light = 0;
-                for x_offset in (-10isize)..10isize {
-                    let sky_light_new = 10usize.saturating_sub(y.saturating_sub(self.sky_light[((x as isize + x_offset).max(0) as usize).min(self.get_map_width() - 1)] as usize));
-                    // the pow is to create an easing curve to make it less diamond shaped, but idk how I feel about it. But, for now, it works
-                    let sky_light_new = ((sky_light_new as f32 / 10.0 * 255.0) as u8).saturating_sub(((x_offset as f32 * 0.1).powi(2) * 255.0) as u8);
-                    sky_light = sky_light.max(sky_light_new);
-                }
+                let mut light = [
+                    self.lighting[y][x][0].max(ambient_light[0]),
+                    self.lighting[y][x][1].max(ambient_light[1]),
+                    self.lighting[y][x][2].max(ambient_light[2]),
+                ];
+                let entity_light = entity_lighting[(y - start_y) * (end_x - start_x) + (x - start_x)];
+                light = [
+                    light[0].max(entity_light[0]),
+                    light[1].max(entity_light[1]),
+                    light[2].max(entity_light[2]),
+                ];
+
+                // the sky light is scaled by the time of day
+                let sky_light = (self.sky_lighting[y][x] as f32 * sky_brightness) as u8;
 
                 // getting the final lighting for the location
                 light = [
@@ -313,15 +330,23 @@ impl TileMap {
                 ];
                 self.mini_map.update_light_value(light, x, y);
 
+                // the liquid is packed into the unused upper bits of the light (16 bits of texture, then 8 for the fill level)
+                let liquid = self.liquids.get(x, y);
+                let liquid_bits = match get_liquid_info(liquid.liquid) {
+                    Some(info) if !liquid.is_empty() => ((info.texture as u64) << 32) | ((liquid.level as u64) << 48),
+                    _ => 0,
+                };
+
+                // the tile data goes in the upper 32 bits of each layer
                 visible_tiles.push([
-                    self.tiles[y][x][0] as u64,
-                    self.tiles[y][x][1] as u64,
-                    self.tiles[y][x][2] as u64,
+                    self.tiles[y][x][0] as u64 | ((self.tile_data[y][x][0] as u64) << 32),
+                    self.tiles[y][x][1] as u64 | ((self.tile_data[y][x][1] as u64) << 32),
+                    self.tiles[y][x][2] as u64 | ((self.tile_data[y][x][2] as u64) << 32),
                     (
                         light[0] as u32 |
                         ((light[1] as u32) << 8) |
                         ((light[2] as u32) << 16)
-                    ) as u64,
+                    ) as u64 | liquid_bits,
                 ]);
             }
         }
@@ -332,7 +357,32 @@ impl TileMap {
             x: edge_offset_x - cell_offset_x,
             y: edge_offset_y - cell_offset_y,
             zoom: camera_transform.zoom,
-        }, ((end_x - start_x) as u32, (end_y - start_y) as u32))
+        }, ((end_x - start_x) as u32, (end_y - start_y) as u32), row_backgrounds)
+    }
+}
+
+impl LightMap for TileMap {
+    fn light_size(&self) -> (usize, usize) {
+        (self.get_map_width(), self.get_map_height())
+    }
+
+    fn get_light(&self, x: usize, y: usize) -> [u8; 3] {
+        self.lighting[y][x]
+    }
+
+    fn set_light(&mut self, x: usize, y: usize, light: [u8; 3]) {
+        self.lighting[y][x] = light;
+    }
+
+    fn light_falloff(&self, x: usize, y: usize) -> u8 {
+        match self.is_solid(x, y) {
+            true => LIGHT_FALLOFF_SOLID,
+            false => LIGHT_FALLOFF_AIR,
+        }
+    }
+
+    fn light_emission(&self, x: usize, y: usize) -> [u8; 3] {
+        self.get_light_emission(x, y).unwrap_or([0, 0, 0])
     }
 }
 
@@ -340,7 +390,7 @@ fn fract(value: f32) -> f32 {
     value - value.floor()
 }
 
-#[derive(bincode::Encode, bincode::Decode)]
+/// Holds the tile map of every dimension (each one is saved on its own, and only once it's been visited)
 pub struct TileMapManager {
     tile_maps: [Option<TileMap>; Dimension::TOTAL as usize],
 }
@@ -348,7 +398,7 @@ pub struct TileMapManager {
 impl TileMapManager {
     pub fn new() -> Self {
         TileMapManager {
-            tile_maps: [None; Dimension::TOTAL as usize],
+            tile_maps: [const { None }; Dimension::TOTAL as usize],
         }
     }
 
@@ -369,9 +419,125 @@ impl TileMapManager {
 }
 
 #[repr(u32)]
-#[derive(bincode::Encode, bincode::Decode)]
+#[derive(bincode::Encode, bincode::Decode, Clone, Copy, PartialEq, Debug)]
 pub enum Dimension {
     Overworld = 0,
+    SkyIslands = 1,
     
-    TOTAL = 1,
+    TOTAL = 2,
+}
+
+impl Dimension {
+    /// Every dimension that can be travelled to
+    pub const ALL: [Dimension; Dimension::TOTAL as usize] = [Dimension::Overworld, Dimension::SkyIslands];
+
+    /// The name of the folder the dimension is saved into
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Dimension::Overworld => "overworld",
+            Dimension::SkyIslands => "sky_islands",
+            Dimension::TOTAL => unreachable!("Dimension::TOTAL only counts the dimensions"),
+        }
+    }
+
+    pub fn get_map_size(&self) -> (usize, usize) {
+        match self {
+            Dimension::Overworld => (4095, 1024),
+            Dimension::SkyIslands => (2047, 512),
+            Dimension::TOTAL => unreachable!("Dimension::TOTAL only counts the dimensions"),
+        }
+    }
+
+    /// Where a portal within this dimension leads to
+    pub fn get_portal_destination(&self) -> Dimension {
+        match self {
+            Dimension::Overworld => Dimension::SkyIslands,
+            Dimension::SkyIslands => Dimension::Overworld,
+            Dimension::TOTAL => unreachable!("Dimension::TOTAL only counts the dimensions"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_manager::entities::player::player::MAX_STEP_HEIGHT;
+    use crate::game_manager::world::tile_map::random_ticks::smother_grass;
+    use crate::game_manager::world::tile_map::registry::TileType;
+
+    /// Hammers the tile until it's the shape (the shapes go round in a fixed order)
+    fn hammer_into(tile_map: &mut TileMap, x: usize, y: usize, shape: TileShape) {
+        while tile_map.get_tile_shape(x, y) != shape {
+            assert!(tile_map.cycle_tile_shape(x, y));
+        }
+    }
+
+    #[test]
+    fn smothered_grass_keeps_its_hammered_shape() {
+        let mut tile_map = TileMap::test_tile_map(8, 8);
+        tile_map.change_tile(4, 4, 0, GRASS_IDS[0]).unwrap();
+        hammer_into(&mut tile_map, 4, 4, TileShape::SlopeUpRight);
+
+        tile_map.change_tile(4, 3, 0, STONE_IDS[0]).unwrap();
+        smother_grass(&mut tile_map, 4, 4, &mut rand::rng()).unwrap();
+        assert!(get_tile_properties(tile_map.get_tile(4, 4, 0)).tile_type == TileType::Dirt);
+        assert_eq!(tile_map.get_tile_shape(4, 4), TileShape::SlopeUpRight);
+
+        // anything that isn't a full block doesn't keep it
+        tile_map.change_tile(4, 4, 0, 0).unwrap();
+        assert_eq!(tile_map.get_tile_shape(4, 4), TileShape::Full);
+    }
+
+    #[test]
+    fn hammered_tiles_stop_being_solid() {
+        let mut tile_map = TileMap::test_tile_map(8, 8);
+        tile_map.change_tile(4, 4, 0, STONE_IDS[0]).unwrap();
+        assert!(tile_map.is_solid(4, 4));
+        hammer_into(&mut tile_map, 4, 4, TileShape::HalfBlock);
+        assert!(!tile_map.is_solid(4, 4));
+        tile_map.reset_tile_shape(4, 4);
+        assert!(tile_map.is_solid(4, 4));
+    }
+
+    #[test]
+    fn wire_devices_cant_be_hammered() {
+        let mut tile_map = TileMap::test_tile_map(8, 8);
+        tile_map.change_tile(4, 4, 0, 375).unwrap();  // a closed door
+        assert!(!tile_map.cycle_tile_shape(4, 4));
+        assert_eq!(tile_map.get_tile_shape(4, 4), TileShape::Full);
+    }
+
+    #[test]
+    fn slopes_only_collide_with_their_filled_half() {
+        let mut tile_map = TileMap::test_tile_map(8, 8);
+        tile_map.change_tile(4, 4, 0, STONE_IDS[0]).unwrap();
+        hammer_into(&mut tile_map, 4, 4, TileShape::SlopeUpRight);
+        // the tile spans 32 to 40 on both axes, with the bottom right half filled in
+        assert!(!tile_map.check_aabb_collision(32.5, 32.5, 2.0, 2.0));
+        assert!(tile_map.check_aabb_collision(37.5, 37.5, 2.0, 2.0));
+
+        hammer_into(&mut tile_map, 4, 4, TileShape::CeilingLeft);
+        assert!(tile_map.check_aabb_collision(32.5, 32.5, 2.0, 2.0));
+        assert!(!tile_map.check_aabb_collision(37.5, 37.5, 2.0, 2.0));
+    }
+
+    #[test]
+    fn only_half_blocks_and_slopes_are_stepped_up() {
+        let mut tile_map = TileMap::test_tile_map(8, 8);
+        tile_map.change_tile(4, 4, 0, STONE_IDS[0]).unwrap();
+        // a box standing on the floor below the tile (at 40) and pushed a pixel into it from the left
+        let (x, y, width, height) = (25.0, 24.0, 8.0, 16.0);
+        let full_step = tile_map.get_step_height(x, y, width, height);
+        assert_eq!(full_step, 8.0);
+        assert!(full_step > MAX_STEP_HEIGHT);
+
+        hammer_into(&mut tile_map, 4, 4, TileShape::HalfBlock);
+        let half_step = tile_map.get_step_height(x, y, width, height);
+        assert_eq!(half_step, 4.0);
+        assert!(half_step <= MAX_STEP_HEIGHT);
+
+        // walking into the low end of a slope only the first pixel is under the box, so it barely has to rise at all
+        hammer_into(&mut tile_map, 4, 4, TileShape::SlopeUpRight);
+        assert!(tile_map.get_step_height(x, y, width, height) <= 1.0);
+    }
 }
@@ -0,0 +1,45 @@
+use crate::game_manager::world::tile_map::{STONE_IDS, TileMap, TileMapError};
+use crate::game_manager::world::tile_map::registry::{TileType, get_tile_properties};
+
+/// The tile that sends the player to the next dimension when they step into it
+pub static PORTAL_TILE: u32 = 367;
+
+impl TileMap {
+    pub fn is_portal(&self, x: usize, y: usize) -> bool {
+        get_tile_properties(self.get_tile(x, y, 0)).tile_type == TileType::Portal
+    }
+
+    /// Finds the nearest column to x with some ground in it, returning (the column, the first solid row)
+    fn find_portal_landing(&self, x: usize) -> Option<(usize, usize)> {
+        let width = self.get_map_width();
+        let x = x.min(width - 1);
+        for offset in 0..width {
+            // searching outwards on both sides so it ends up as close as possible
+            for column in [x.checked_add(offset), x.checked_sub(offset)].into_iter().flatten() {
+                if column >= width { continue; }
+                // starting a couple rows down so there's always room above for the player
+                if let Some(ground) = (2..self.get_map_height()).find(|y| self.is_solid(column, *y)) {
+                    return Some((column, ground));
+                }
+            }
+        } None
+    }
+
+    /// Makes sure there's a portal to arrive at near the column, returning the tile the player should stand in
+    pub fn place_arrival_portal(&mut self, x: usize) -> Result<(usize, usize), TileMapError> {
+        let (column, ground) = match self.find_portal_landing(x) {
+            Some(landing) => landing,
+            None => {
+                // there's nothing to stand on anywhere, so a block is put down to land on
+                let ground = self.get_map_height() / 2;
+                let column = x.min(self.get_map_width() - 1);
+                self.change_tile(column, ground, 0, STONE_IDS[0])?;
+                (column, ground)
+            },
+        };
+        if !self.is_portal(column, ground - 1) {
+            self.change_tile(column, ground - 1, 0, PORTAL_TILE)?;
+        }
+        Ok((column, ground - 1))
+    }
+}
@@ -0,0 +1,201 @@
+use rand::Rng;
+
+use crate::game_manager::world::tile_map::{DIRT_IDS, GRASS_IDS, TileMap, TileMapError};
+use crate::game_manager::world::tile_map::liquids::{LiquidCell, LiquidType, MAX_LIQUID_LEVEL};
+use crate::game_manager::world::tile_map::registry::{TileType, get_tile_properties};
+
+/// The number of tiles along each side of a random tick chunk (each chunk gets the same number of ticks no matter what's in it)
+pub static RANDOM_TICK_CHUNK_SIZE: usize = 32;
+/// The sky light the tile above needs before grass will spread onto the dirt
+static GRASS_MIN_SKY_LIGHT: u8 = 160;
+/// How far away a light source can be and still melt ice
+static ICE_MELT_RADIUS: usize = 3;
+/// The tick rate new tile maps start out with
+static DEFAULT_TICKS_PER_SECOND: f64 = 10.0;
+static DEFAULT_TILES_PER_CHUNK: u32 = 3;
+
+/// A behaviour run when a random tick lands on the tile
+type RandomTickBehaviour = fn(&mut TileMap, usize, usize, &mut dyn rand::RngCore) -> Result<(), TileMapError>;
+
+/// Which behaviour each type of tile runs when it gets randomly ticked (anything not listed ignores the tick)
+static RANDOM_TICK_BEHAVIOURS: &[(TileType, RandomTickBehaviour)] = &[
+    (TileType::Dirt, spread_grass),
+    (TileType::Grass, smother_grass),
+    (TileType::Ice, melt_ice),
+    (TileType::Sapling, grow_sapling),
+];
+
+/// How often the random ticks happen and how many tiles each one touches
+#[derive(bincode::Encode, bincode::Decode)]
+pub struct RandomTicks {
+    ticks_per_second: f64,
+    tiles_per_chunk: u32,  // the number of tiles picked from every chunk each tick
+    tick_timer: f64,
+}
+
+impl Default for RandomTicks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RandomTicks {
+    pub fn new() -> Self {
+        Self::with_rate(DEFAULT_TICKS_PER_SECOND, DEFAULT_TILES_PER_CHUNK)
+    }
+
+    /// A scheduler ticking at the given rate (a rate of 0 stops the random ticks altogether)
+    pub fn with_rate(ticks_per_second: f64, tiles_per_chunk: u32) -> Self {
+        RandomTicks {
+            ticks_per_second,
+            tiles_per_chunk,
+            tick_timer: 0.0,
+        }
+    }
+}
+
+/// Dirt with open sky above it slowly turns to grass when there's grass right next to it
+fn spread_grass(tile_map: &mut TileMap, x: usize, y: usize, _rand_state: &mut dyn rand::RngCore) -> Result<(), TileMapError> {
+    if y == 0 || tile_map.is_solid(x, y - 1) { return Ok(()); }
+    if tile_map.sky_lighting[y - 1][x] < GRASS_MIN_SKY_LIGHT { return Ok(()); }
+    for neighbour_y in y.saturating_sub(1)..=(y + 1).min(tile_map.get_map_height() - 1) {
+        for neighbour_x in x.saturating_sub(1)..=(x + 1).min(tile_map.get_map_width() - 1) {
+            if get_tile_properties(tile_map.get_tile(neighbour_x, neighbour_y, 0)).tile_type == TileType::Grass {
+                // the autotiling picks the right edge once it's placed
+                return tile_map.change_tile(x, y, 0, GRASS_IDS[0]);
+            }
+        }
+    } Ok(())
+}
+
+/// Grass covered over by a solid tile dies back to dirt
+pub(super) fn smother_grass(tile_map: &mut TileMap, x: usize, y: usize, _rand_state: &mut dyn rand::RngCore) -> Result<(), TileMapError> {
+    if y > 0 && tile_map.is_solid(x, y - 1) {
+        return tile_map.change_tile(x, y, 0, DIRT_IDS[0]);
+    } Ok(())
+}
+
+/// Saplings eventually grow into a full tree (if there's room)
+fn grow_sapling(tile_map: &mut TileMap, x: usize, y: usize, rand_state: &mut dyn rand::RngCore) -> Result<(), TileMapError> {
+    tile_map.grow_sapling(x, y, rand_state)
+}
+
+/// Ice close to a light source (torches, lava, etc...) melts into water
+fn melt_ice(tile_map: &mut TileMap, x: usize, y: usize, _rand_state: &mut dyn rand::RngCore) -> Result<(), TileMapError> {
+    for neighbour_y in y.saturating_sub(ICE_MELT_RADIUS)..=(y + ICE_MELT_RADIUS).min(tile_map.get_map_height() - 1) {
+        for neighbour_x in x.saturating_sub(ICE_MELT_RADIUS)..=(x + ICE_MELT_RADIUS).min(tile_map.get_map_width() - 1) {
+            if tile_map.get_light_emission(neighbour_x, neighbour_y).is_some() {
+                tile_map.change_tile(x, y, 0, 0)?;
+                tile_map.liquids.set(x, y, LiquidCell::new(LiquidType::Water, MAX_LIQUID_LEVEL));
+                tile_map.liquids.wake_area(x, y);
+                return Ok(());
+            }
+        }
+    } Ok(())
+}
+
+impl TileMap {
+    /// Changes how often the random ticks happen and how many tiles each one touches
+    pub fn set_random_tick_rate(&mut self, ticks_per_second: f64, tiles_per_chunk: u32) {
+        self.random_ticks = RandomTicks::with_rate(ticks_per_second, tiles_per_chunk);
+    }
+
+    /// Picks random tiles out of every chunk at a fixed rate and runs whatever behaviour they have (grass spreading and such)
+    pub fn update_random_ticks(&mut self, delta_time: f64, rand_state: &mut dyn rand::RngCore) -> Result<(), TileMapError> {
+        if self.random_ticks.ticks_per_second <= 0.0 { return Ok(()); }
+        let tick_length = 1.0 / self.random_ticks.ticks_per_second;
+        self.random_ticks.tick_timer += delta_time;
+        // capping it so a long frame doesn't stack up a huge number of ticks
+        self.random_ticks.tick_timer = self.random_ticks.tick_timer.min(4.0 * tick_length);
+        while self.random_ticks.tick_timer >= tick_length {
+            self.random_ticks.tick_timer -= tick_length;
+            self.random_tick(rand_state)?;
+        } Ok(())
+    }
+
+    fn random_tick(&mut self, rand_state: &mut dyn rand::RngCore) -> Result<(), TileMapError> {
+        let (width, height) = (self.get_map_width(), self.get_map_height());
+        for chunk_y in (0..height).step_by(RANDOM_TICK_CHUNK_SIZE) {
+            for chunk_x in (0..width).step_by(RANDOM_TICK_CHUNK_SIZE) {
+                for _ in 0..self.random_ticks.tiles_per_chunk {
+                    let x = rand_state.random_range(chunk_x..(chunk_x + RANDOM_TICK_CHUNK_SIZE).min(width));
+                    let y = rand_state.random_range(chunk_y..(chunk_y + RANDOM_TICK_CHUNK_SIZE).min(height));
+                    let tile_type = get_tile_properties(self.get_tile(x, y, 0)).tile_type;
+                    if let Some((_, behaviour)) = RANDOM_TICK_BEHAVIOURS.iter().find(|(behaviour_type, _)| *behaviour_type == tile_type) {
+                        behaviour(self, x, y, rand_state)?;
+                    }
+                }
+            }
+        } Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_manager::world::tile_map::{ICE_IDS, STONE_IDS};
+
+    static TORCH: u32 = 173;
+
+    fn tile_type_at(tile_map: &TileMap, x: usize, y: usize) -> TileType {
+        get_tile_properties(tile_map.get_tile(x, y, 0)).tile_type
+    }
+
+    #[test]
+    fn grass_spreads_onto_dirt_under_the_open_sky() {
+        let mut tile_map = TileMap::test_tile_map(8, 8);
+        tile_map.change_tile(3, 6, 0, GRASS_IDS[0]).unwrap();
+        tile_map.change_tile(4, 6, 0, DIRT_IDS[0]).unwrap();
+        tile_map.flood_sky_light(|_| true);
+        spread_grass(&mut tile_map, 4, 6, &mut rand::rng()).unwrap();
+        assert_eq!(tile_type_at(&tile_map, 4, 6), TileType::Grass);
+
+        // but not once it's covered
+        tile_map.change_tile(5, 6, 0, DIRT_IDS[0]).unwrap();
+        tile_map.change_tile(5, 5, 0, STONE_IDS[0]).unwrap();
+        spread_grass(&mut tile_map, 5, 6, &mut rand::rng()).unwrap();
+        assert_eq!(tile_type_at(&tile_map, 5, 6), TileType::Dirt);
+    }
+
+    #[test]
+    fn covered_grass_dies_back_to_dirt() {
+        let mut tile_map = TileMap::test_tile_map(8, 8);
+        tile_map.change_tile(4, 6, 0, GRASS_IDS[0]).unwrap();
+        smother_grass(&mut tile_map, 4, 6, &mut rand::rng()).unwrap();
+        assert_eq!(tile_type_at(&tile_map, 4, 6), TileType::Grass);
+
+        tile_map.change_tile(4, 5, 0, STONE_IDS[0]).unwrap();
+        smother_grass(&mut tile_map, 4, 6, &mut rand::rng()).unwrap();
+        assert_eq!(tile_type_at(&tile_map, 4, 6), TileType::Dirt);
+    }
+
+    #[test]
+    fn ice_melts_into_water_near_lights() {
+        let mut tile_map = TileMap::test_tile_map(8, 8);
+        tile_map.change_tile(1, 6, 0, ICE_IDS[0]).unwrap();
+        melt_ice(&mut tile_map, 1, 6, &mut rand::rng()).unwrap();
+        assert_eq!(tile_type_at(&tile_map, 1, 6), TileType::Ice);
+
+        tile_map.change_tile(4, 6, 0, TORCH).unwrap();
+        melt_ice(&mut tile_map, 1, 6, &mut rand::rng()).unwrap();
+        assert_eq!(tile_map.get_tile(1, 6, 0), 0);
+        assert_eq!(tile_map.liquids.get(1, 6).liquid, LiquidType::Water);
+    }
+
+    #[test]
+    fn a_zero_tick_rate_stops_the_random_ticks() {
+        let mut tile_map = TileMap::test_tile_map(8, 8);
+        tile_map.change_tile(4, 6, 0, GRASS_IDS[0]).unwrap();
+        tile_map.change_tile(4, 5, 0, STONE_IDS[0]).unwrap();
+        tile_map.set_random_tick_rate(0.0, 64);
+        tile_map.update_random_ticks(10.0, &mut rand::rng()).unwrap();
+        assert_eq!(tile_type_at(&tile_map, 4, 6), TileType::Grass);
+
+        // while a fast enough rate is all but sure to land on it (64 picks from the 64 tiles, 100 times over)
+        tile_map.set_random_tick_rate(100.0, 64);
+        for _ in 0..25 {
+            tile_map.update_random_ticks(0.04, &mut rand::rng()).unwrap();
+        }
+        assert_eq!(tile_type_at(&tile_map, 4, 6), TileType::Dirt);
+    }
+}
@@ -0,0 +1,465 @@
+use crate::game_manager::entities::player::{inventory::TileDrop, items::{ItemGenerator, ItemType}};
+use crate::game_manager::world::tile_map::autotile::blob_tile_ids;
+use crate::game_manager::world::tile_map::{ASH_IDS, CACTUS_IDS, DIRT_IDS, GRASS_IDS, ICE_IDS, SAND_IDS, SAND_STONE_IDS, SNOW_IDS, STONE_IDS, WOOD_IDS};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TileType {
+    Air,
+    Grass,
+    Dirt,
+    Stone,
+    Sand,
+    SandStone,
+    Ice,
+    Snow,
+    Cactus,
+    Wood,
+    Ash,
+    Light,
+    Torch,
+    Sapling,
+    Portal,
+    Bed,
+    Lever,
+    Button,
+    PressurePlate,
+    Door,
+    OpenDoor,
+    Lamp,
+    LitLamp,
+    Trap,
+    Platform,
+    Ladder,
+    Rope,
+}
+
+/// How a tile gets in the way of anything moving through it
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CollisionShape {
+    Empty,
+    Full,
+    Platform,   // only stood on from above (and dropped through while holding down)
+    Climbable,  // walked through, but lets the player climb up and down it
+}
+
+/// Everything the game needs to know about a type of tile
+pub struct TileProperties {
+    pub tile_type: TileType,
+    pub tile_ids: &'static [u32],  // every texture variant of the tile (the autotiled edges and such)
+    pub collision: CollisionShape,  // only Full tiles count as solid (see is_solid), so platforms and such can be stood on without blocking light
+    pub light: Option<[u8; 3]>,
+    pub drops: Option<TileDrop>,
+    pub hardness: (f32, u32),  // (the seconds it takes to mine with a mining speed of 1, the tool tier needed to break it)
+    /// The edge variants, indexed by which neighbours are open (the order is in update_edge_tiles)
+    pub autotile: Option<[u32; 16]>,
+    pub blob_autotile: Option<u32>,  // the first id of a 47 tile blob set (used over the 16 tile set when there is one)
+    pub blends: &'static [(TileType, u32)],  // (the material it merges into, the first id of the 47 transition tiles)
+    pub map_color: [u8; 3],  // used on the mini-map when the tile doesn't have its own icon
+    pub gravity: Option<(u32, u32)>,  // (the tile placed once it lands, the entity texture while falling)
+}
+
+impl TileProperties {
+    /// Whether it blocks light and liquids, which is only ever the tiles with a full collision box
+    pub fn is_solid(&self) -> bool {
+        self.collision == CollisionShape::Full
+    }
+}
+
+/// Anything not in the registry (decorations like bushes and tree leaves); it's walked through and breaks almost instantly
+static DEFAULT_PROPERTIES: TileProperties = TileProperties {
+    tile_type: TileType::Air,
+    tile_ids: &[0],
+    collision: CollisionShape::Empty,
+    light: None,
+    drops: None,
+    hardness: (0.05, 0),
+    autotile: None,
+    blob_autotile: None,
+    blends: &[],
+    map_color: [0, 0, 0],
+    gravity: None,
+};
+
+pub static TILE_REGISTRY: &[TileProperties] = &[
+    TileProperties {
+        tile_type: TileType::Grass,
+        tile_ids: GRASS_IDS,
+        collision: CollisionShape::Full,
+        light: None,
+        drops: Some(TileDrop::new(&[1.0], &[ItemGenerator::new((1, 1), 5, Some(ItemType::Block(1)), "Dirt", 512)], &[7])),
+        hardness: (0.35, 0),
+        autotile: Some([7, 8, 10, 1, 47, 5, 2, 4, 13, 9, 3, 6, 11, 12, 14, 29]),
+        blob_autotile: None,
+        blends: &[],
+        map_color: [74, 160, 60],
+        gravity: None,
+    },
+    TileProperties {
+        tile_type: TileType::Dirt,
+        tile_ids: DIRT_IDS,
+        collision: CollisionShape::Full,
+        light: None,
+        drops: Some(TileDrop::new(&[1.0], &[ItemGenerator::new((1, 1), 5, Some(ItemType::Block(1)), "Dirt", 512)], &[7])),
+        hardness: (0.35, 0),
+        autotile: Some([7 + 14, 8 + 14, 10 + 14, 1 + 14, 46, 5 + 14, 2 + 14, 4 + 14, 13 + 14, 9 + 14, 3 + 14, 6 + 14, 11 + 14, 12 + 14, 14 + 14, 29]),
+        blob_autotile: Some(224),
+        blends: &[(TileType::Stone, 271)],
+        map_color: [120, 85, 58],
+        gravity: None,
+    },
+    TileProperties {
+        tile_type: TileType::Stone,
+        tile_ids: STONE_IDS,
+        collision: CollisionShape::Full,
+        light: None,
+        drops: Some(TileDrop::new(&[1.0], &[ItemGenerator::new((1, 1), 6, Some(ItemType::Block(45)), "Stone", 512)], &[8])),
+        hardness: (0.9, 1),
+        autotile: Some([7 + 29, 8 + 29, 10 + 29, 1 + 29, 45, 5 + 29, 2 + 29, 4 + 29, 13 + 29, 9 + 29, 3 + 29, 6 + 29, 11 + 29, 12 + 29, 14 + 29, 44]),
+        blob_autotile: Some(177),
+        blends: &[],
+        map_color: [110, 110, 118],
+        gravity: None,
+    },
+    TileProperties {
+        tile_type: TileType::Sand,
+        tile_ids: SAND_IDS,
+        collision: CollisionShape::Full,
+        light: None,
+        drops: Some(TileDrop::new(&[1.0], &[ItemGenerator::new((1, 1), 11, Some(ItemType::Block(89)), "Sand", 512)], &[13])),
+        hardness: (0.3, 0),
+        autotile: Some([7 + 88, 8 + 88, 10 + 88, 1 + 88, 134, 5 + 88, 2 + 88, 4 + 88, 13 + 88, 9 + 88, 3 + 88, 6 + 88, 11 + 88, 12 + 88, 14 + 88, 117]),
+        blob_autotile: None,
+        blends: &[],
+        map_color: [220, 200, 130],
+        gravity: Some((89, 16)),
+    },
+    TileProperties {
+        tile_type: TileType::SandStone,
+        tile_ids: SAND_STONE_IDS,
+        collision: CollisionShape::Full,
+        light: None,
+        drops: Some(TileDrop::new(&[1.0], &[ItemGenerator::new((1, 1), 12, Some(ItemType::Block(103)), "Sandstone", 512)], &[14])),
+        hardness: (0.8, 1),
+        autotile: Some([7 + 102, 8 + 102, 10 + 102, 1 + 102, 133, 5 + 102, 2 + 102, 4 + 102, 13 + 102, 9 + 102, 3 + 102, 6 + 102, 11 + 102, 12 + 102, 14 + 102, 117]),
+        blob_autotile: None,
+        blends: &[],
+        map_color: [190, 160, 100],
+        gravity: None,
+    },
+    TileProperties {
+        tile_type: TileType::Ice,
+        tile_ids: ICE_IDS,
+        collision: CollisionShape::Full,
+        light: None,
+        drops: Some(TileDrop::new(&[1.0], &[ItemGenerator::new((1, 1), 10, Some(ItemType::Block(118)), "Ice", 512)], &[12])),
+        hardness: (0.7, 1),
+        autotile: Some([7 + 117, 8 + 117, 10 + 117, 1 + 117, 136, 5 + 117, 2 + 117, 4 + 117, 13 + 117, 9 + 117, 3 + 117, 6 + 117, 11 + 117, 12 + 117, 14 + 117, 132]),
+        blob_autotile: None,
+        blends: &[],
+        map_color: [150, 200, 235],
+        gravity: None,
+    },
+    TileProperties {
+        tile_type: TileType::Snow,
+        tile_ids: SNOW_IDS,
+        collision: CollisionShape::Full,
+        light: None,
+        drops: Some(TileDrop::new(&[1.0], &[ItemGenerator::new((1, 1), 9, Some(ItemType::Block(137)), "Snow", 512)], &[11])),
+        hardness: (0.3, 0),
+        autotile: Some([7 + 136, 8 + 136, 10 + 136, 1 + 136, 135, 5 + 136, 2 + 136, 4 + 136, 13 + 136, 9 + 136, 3 + 136, 6 + 136, 11 + 136, 12 + 136, 14 + 136, 29]),
+        blob_autotile: None,
+        blends: &[],
+        map_color: [235, 240, 250],
+        gravity: None,
+    },
+    TileProperties {
+        tile_type: TileType::Cactus,
+        tile_ids: CACTUS_IDS,
+        collision: CollisionShape::Empty,
+        light: None,
+        drops: None,
+        hardness: (0.4, 0),
+        autotile: None,
+        blob_autotile: None,
+        blends: &[],
+        map_color: [70, 140, 60],
+        gravity: None,
+    },
+    TileProperties {
+        tile_type: TileType::Wood,
+        tile_ids: WOOD_IDS,
+        collision: CollisionShape::Full,
+        light: None,
+        drops: None,
+        hardness: (0.6, 0),
+        autotile: None,
+        blob_autotile: None,
+        blends: &[],
+        map_color: [130, 95, 60],
+        gravity: None,
+    },
+    TileProperties {
+        tile_type: TileType::Ash,
+        tile_ids: ASH_IDS,
+        collision: CollisionShape::Full,
+        light: None,
+        drops: Some(TileDrop::new(&[1.0], &[ItemGenerator::new((1, 1), 13, Some(ItemType::Block(174)), "Ash", 512)], &[15])),
+        hardness: (1.2, 2),
+        autotile: None,
+        blob_autotile: Some(318),
+        blends: &[],
+        map_color: [80, 72, 70],
+        gravity: None,
+    },
+    TileProperties {
+        tile_type: TileType::Light,
+        tile_ids: &[88],
+        collision: CollisionShape::Empty,
+        light: Some([255, 255, 128]),
+        drops: Some(TileDrop::new(&[1.0], &[ItemGenerator::new((1, 1), 7, Some(ItemType::Block(88)), "Light", 512)], &[9])),
+        hardness: (0.05, 0),
+        autotile: None,
+        blob_autotile: None,
+        blends: &[],
+        map_color: [255, 255, 128],
+        gravity: None,
+    },
+    TileProperties {
+        tile_type: TileType::Torch,
+        tile_ids: &[173],
+        collision: CollisionShape::Empty,
+        light: Some([200, 200, 128]),
+        drops: Some(TileDrop::new(&[1.0], &[ItemGenerator::new((1, 1), 8, Some(ItemType::Block(173)), "Torch", 512)], &[10])),
+        hardness: (0.05, 0),
+        autotile: None,
+        blob_autotile: None,
+        blends: &[],
+        map_color: [200, 200, 128],
+        gravity: None,
+    },
+    TileProperties {
+        tile_type: TileType::Sapling,
+        tile_ids: &[365],
+        collision: CollisionShape::Empty,
+        light: None,
+        drops: Some(TileDrop::new(&[1.0], &[ItemGenerator::new((1, 1), 15, Some(ItemType::Block(365)), "Acorn", 512)], &[18])),
+        hardness: (0.05, 0),
+        autotile: None,
+        blob_autotile: None,
+        blends: &[],
+        map_color: [90, 160, 60],
+        gravity: None,
+    },
+    TileProperties {
+        tile_type: TileType::Sapling,  // the cactus sapling (sharing the type so it grows on the same random ticks)
+        tile_ids: &[366],
+        collision: CollisionShape::Empty,
+        light: None,
+        drops: Some(TileDrop::new(&[1.0], &[ItemGenerator::new((1, 1), 16, Some(ItemType::Block(366)), "Cactus Seed", 512)], &[19])),
+        hardness: (0.05, 0),
+        autotile: None,
+        blob_autotile: None,
+        blends: &[],
+        map_color: [90, 160, 60],
+        gravity: None,
+    },
+    TileProperties {
+        tile_type: TileType::Portal,
+        tile_ids: &[367],
+        collision: CollisionShape::Empty,
+        light: Some([170, 90, 255]),
+        drops: Some(TileDrop::new(&[1.0], &[ItemGenerator::new((1, 1), 17, Some(ItemType::Block(367)), "Portal", 64)], &[20])),
+        hardness: (0.5, 0),
+        autotile: None,
+        blob_autotile: None,
+        blends: &[],
+        map_color: [150, 80, 230],
+        gravity: None,
+    },
+    TileProperties {
+        tile_type: TileType::Bed,
+        tile_ids: &[368],
+        collision: CollisionShape::Empty,
+        light: None,
+        drops: Some(TileDrop::new(&[1.0], &[ItemGenerator::new((1, 1), 18, Some(ItemType::Block(368)), "Bed", 16)], &[21])),
+        hardness: (0.4, 0),
+        autotile: None,
+        blob_autotile: None,
+        blends: &[],
+        map_color: [180, 50, 50],
+        gravity: None,
+    },
+    TileProperties {
+        tile_type: TileType::Lever,
+        tile_ids: &[369, 370],
+        collision: CollisionShape::Empty,
+        light: None,
+        drops: Some(TileDrop::new(&[1.0], &[ItemGenerator::new((1, 1), 21, Some(ItemType::Block(369)), "Lever", 64)], &[24])),
+        hardness: (0.2, 0),
+        autotile: None,
+        blob_autotile: None,
+        blends: &[],
+        map_color: [150, 110, 70],
+        gravity: None,
+    },
+    TileProperties {
+        tile_type: TileType::Button,
+        tile_ids: &[371, 372],
+        collision: CollisionShape::Empty,
+        light: None,
+        drops: Some(TileDrop::new(&[1.0], &[ItemGenerator::new((1, 1), 22, Some(ItemType::Block(371)), "Button", 64)], &[25])),
+        hardness: (0.2, 0),
+        autotile: None,
+        blob_autotile: None,
+        blends: &[],
+        map_color: [180, 50, 50],
+        gravity: None,
+    },
+    TileProperties {
+        tile_type: TileType::PressurePlate,
+        tile_ids: &[373, 374],
+        collision: CollisionShape::Empty,
+        light: None,
+        drops: Some(TileDrop::new(&[1.0], &[ItemGenerator::new((1, 1), 23, Some(ItemType::Block(373)), "Pressure Plate", 64)], &[26])),
+        hardness: (0.2, 0),
+        autotile: None,
+        blob_autotile: None,
+        blends: &[],
+        map_color: [150, 150, 158],
+        gravity: None,
+    },
+    TileProperties {
+        tile_type: TileType::Door,
+        tile_ids: &[375],
+        collision: CollisionShape::Full,
+        light: None,
+        drops: Some(TileDrop::new(&[1.0], &[ItemGenerator::new((1, 1), 24, Some(ItemType::Block(375)), "Door", 16)], &[27])),
+        hardness: (0.4, 0),
+        autotile: None,
+        blob_autotile: None,
+        blends: &[],
+        map_color: [130, 90, 50],
+        gravity: None,
+    },
+    TileProperties {
+        tile_type: TileType::OpenDoor,
+        tile_ids: &[376],
+        collision: CollisionShape::Empty,
+        light: None,
+        drops: Some(TileDrop::new(&[1.0], &[ItemGenerator::new((1, 1), 24, Some(ItemType::Block(375)), "Door", 16)], &[27])),
+        hardness: (0.4, 0),
+        autotile: None,
+        blob_autotile: None,
+        blends: &[],
+        map_color: [130, 90, 50],
+        gravity: None,
+    },
+    TileProperties {
+        tile_type: TileType::Lamp,
+        tile_ids: &[377],
+        collision: CollisionShape::Empty,
+        light: None,
+        drops: Some(TileDrop::new(&[1.0], &[ItemGenerator::new((1, 1), 25, Some(ItemType::Block(377)), "Lamp", 64)], &[28])),
+        hardness: (0.2, 0),
+        autotile: None,
+        blob_autotile: None,
+        blends: &[],
+        map_color: [100, 95, 80],
+        gravity: None,
+    },
+    TileProperties {
+        tile_type: TileType::LitLamp,
+        tile_ids: &[378],
+        collision: CollisionShape::Empty,
+        light: Some([255, 230, 150]),
+        drops: Some(TileDrop::new(&[1.0], &[ItemGenerator::new((1, 1), 25, Some(ItemType::Block(377)), "Lamp", 64)], &[28])),
+        hardness: (0.2, 0),
+        autotile: None,
+        blob_autotile: None,
+        blends: &[],
+        map_color: [255, 240, 140],
+        gravity: None,
+    },
+    TileProperties {
+        tile_type: TileType::Trap,
+        tile_ids: &[379, 380],
+        collision: CollisionShape::Full,
+        light: None,
+        drops: Some(TileDrop::new(&[1.0], &[ItemGenerator::new((1, 1), 26, Some(ItemType::Block(379)), "Spike Trap", 64)], &[29])),
+        hardness: (0.9, 1),
+        autotile: None,
+        blob_autotile: None,
+        blends: &[],
+        map_color: [90, 90, 96],
+        gravity: None,
+    },
+    TileProperties {
+        tile_type: TileType::Platform,
+        tile_ids: &[381],
+        collision: CollisionShape::Platform,
+        light: None,
+        drops: Some(TileDrop::new(&[1.0], &[ItemGenerator::new((1, 1), 27, Some(ItemType::Block(381)), "Platform", 256)], &[30])),
+        hardness: (0.25, 0),
+        autotile: None,
+        blob_autotile: None,
+        blends: &[],
+        map_color: [150, 100, 55],
+        gravity: None,
+    },
+    TileProperties {
+        tile_type: TileType::Ladder,
+        tile_ids: &[382],
+        collision: CollisionShape::Climbable,
+        light: None,
+        drops: Some(TileDrop::new(&[1.0], &[ItemGenerator::new((1, 1), 28, Some(ItemType::Block(382)), "Ladder", 256)], &[31])),
+        hardness: (0.25, 0),
+        autotile: None,
+        blob_autotile: None,
+        blends: &[],
+        map_color: [130, 90, 50],
+        gravity: None,
+    },
+    TileProperties {
+        tile_type: TileType::Rope,
+        tile_ids: &[383],
+        collision: CollisionShape::Climbable,
+        light: None,
+        drops: Some(TileDrop::new(&[1.0], &[ItemGenerator::new((1, 1), 29, Some(ItemType::Block(383)), "Rope", 256)], &[32])),
+        hardness: (0.25, 0),
+        autotile: None,
+        blob_autotile: None,
+        blends: &[],
+        map_color: [190, 160, 100],
+        gravity: None,
+    },
+];
+
+lazy_static::lazy_static! {
+    /// Maps every tile id to its entry in the registry so the lookups (done for nearly every tile, every frame) don't search each set
+    static ref TILE_LOOKUP: Vec<Option<usize>> = {
+        let max_id = TILE_REGISTRY.iter().flat_map(|properties| properties.tile_ids.iter()).copied().max().unwrap_or(0);
+        let mut lookup = vec![None; max_id as usize + 1];
+        for (index, properties) in TILE_REGISTRY.iter().enumerate() {
+            let blob_ids = properties.blob_autotile.into_iter()
+                .chain(properties.blends.iter().map(|(_, blend_start)| *blend_start))
+                .flat_map(blob_tile_ids);
+            for tile_id in properties.tile_ids.iter().copied().chain(blob_ids) {
+                if tile_id as usize >= lookup.len() { lookup.resize(tile_id as usize + 1, None); }
+                lookup[tile_id as usize] = Some(index);
+            }
+        } lookup
+    };
+}
+
+/// Gets the properties of the tile with the given id (unregistered tiles get the default properties)
+pub fn get_tile_properties(tile_id: u32) -> &'static TileProperties {
+    match TILE_LOOKUP.get(tile_id as usize) {
+        Some(Some(index)) => &TILE_REGISTRY[*index],
+        _ => &DEFAULT_PROPERTIES,
+    }
+}
+
+/// Gets the properties of the given type of tile
+pub fn get_tile_type_properties(tile_type: TileType) -> &'static TileProperties {
+    TILE_REGISTRY.iter().find(|properties| properties.tile_type == tile_type).unwrap_or(&DEFAULT_PROPERTIES)
+}
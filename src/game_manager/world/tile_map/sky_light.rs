@@ -0,0 +1,72 @@
+use crate::game_manager::world::tile_map::TileMap;
+use crate::game_manager::world::tile_map::light_engine::{self, LightMap, LIGHT_FALLOFF_AIR, LIGHT_FALLOFF_SOLID};
+
+/// The light given off by open sky (before the day cycle scales it)
+pub static SKY_LIGHT_LEVEL: u8 = 255;
+
+/// The sky light as its own single channel for the light engine
+/// Every open tile above the first solid tile in its column is lit directly, and the light spreads outwards from there
+struct SkyLightMap<'a> {
+    tile_map: &'a mut TileMap,
+}
+
+impl LightMap for SkyLightMap<'_> {
+    fn light_size(&self) -> (usize, usize) {
+        (self.tile_map.get_map_width(), self.tile_map.get_map_height())
+    }
+
+    fn get_light(&self, x: usize, y: usize) -> [u8; 3] {
+        [self.tile_map.sky_lighting[y][x]; 3]
+    }
+
+    fn set_light(&mut self, x: usize, y: usize, light: [u8; 3]) {
+        // the channels always match, so clearing any one of them (the removal goes a channel at a time) clears the tile
+        self.tile_map.sky_lighting[y][x] = light[0].min(light[1]).min(light[2]);
+    }
+
+    fn light_falloff(&self, x: usize, y: usize) -> u8 {
+        match self.tile_map.is_solid(x, y) {
+            true => LIGHT_FALLOFF_SOLID,
+            false => LIGHT_FALLOFF_AIR,
+        }
+    }
+
+    fn light_emission(&self, x: usize, y: usize) -> [u8; 3] {
+        match y < self.tile_map.sky_light[x] as usize {
+            true => [SKY_LIGHT_LEVEL; 3],
+            false => [0; 3],
+        }
+    }
+}
+
+impl TileMap {
+    /// Finds the first solid tile of every column and lights the whole map from the sky
+    /// The progress is passed along to the light engine, which stops early (returning false) if it returns false
+    pub fn flood_sky_light(&mut self, progress: impl FnMut(f32) -> bool) -> bool {
+        for x in 0..self.get_map_width() {
+            self.sky_light[x] = (0..self.get_map_height())
+                .find(|y| self.is_solid(x, *y))
+                .unwrap_or(self.get_map_height()) as u32;
+        }
+        light_engine::flood_all_lights_with_progress(&mut SkyLightMap { tile_map: self }, progress)
+    }
+
+    /// Updates the sky light after the solidity of a tile changed
+    pub(crate) fn update_sky_light(&mut self, tile_x: usize, tile_y: usize, is_solid: bool) {
+        let old_top = self.sky_light[tile_x] as usize;
+        let mut changed = vec![(tile_x, tile_y)];
+        if is_solid && tile_y < old_top {
+            // this now shades everything that was open to the sky below it
+            self.sky_light[tile_x] = tile_y as u32;
+            changed.extend((tile_y + 1..old_top).map(|y| (tile_x, y)));
+        } else if !is_solid && tile_y == old_top {
+            // the column is open down to the next solid tile (only scanning down from here rather than the whole column)
+            let new_top = (tile_y + 1..self.get_map_height())
+                .find(|y| self.is_solid(tile_x, *y))
+                .unwrap_or(self.get_map_height());
+            self.sky_light[tile_x] = new_top as u32;
+            changed.extend((tile_y + 1..new_top).map(|y| (tile_x, y)));
+        }
+        light_engine::refresh_lights(&mut SkyLightMap { tile_map: self }, &changed);
+    }
+}
@@ -0,0 +1,48 @@
+use crate::game_manager::world::tile_map::{STONE_IDS, TileMap, TileMapError};
+use crate::game_manager::world::tile_map::registry::{TileType, get_tile_properties};
+
+impl TileMap {
+    /// Beds set the respawn point of whoever right clicks them
+    pub fn is_bed(&self, x: usize, y: usize) -> bool {
+        get_tile_properties(self.get_tile(x, y, 0)).tile_type == TileType::Bed
+    }
+
+    /// The tile new players start in (found when the map is generated)
+    pub fn get_spawn_point(&self) -> (usize, usize) {
+        self.spawn_point
+    }
+
+    /// Checks the column for somewhere the player can stand on the surface, returning the tile they'd stand in
+    fn find_safe_surface(&self, x: usize) -> Option<(usize, usize)> {
+        // the first thing hit coming down from the sky on the main layer (water, torches, etc... don't make good ground)
+        let ground = (0..self.get_map_height()).find(|y| self.get_tile(x, *y, 0) != 0 || !self.liquids.get(x, *y).is_empty())?;
+        if ground < 2 || !self.is_solid(x, ground) || !self.liquids.get(x, ground).is_empty() { return None; }
+        // the player is two tiles tall, so both need to be open
+        for y in ground - 2..ground {
+            if self.get_tile(x, y, 0) != 0 || !self.liquids.get(x, y).is_empty() { return None; }
+        }
+        Some((x, ground - 1))
+    }
+
+    /// Picks the world spawn by searching outwards from the center of the map for a safe bit of surface
+    pub fn choose_spawn_point(&mut self) -> Result<(), TileMapError> {
+        let width = self.get_map_width();
+        let center = width / 2;
+        for offset in 0..width / 4 {
+            for column in [center + offset, center.saturating_sub(offset)] {
+                if column >= width { continue; }
+                if let Some(spawn_point) = self.find_safe_surface(column) {
+                    self.spawn_point = spawn_point;
+                    return Ok(());
+                }
+            }
+        }
+        // there's nowhere safe near the center, so a block is put down to stand on (with room cleared above it)
+        let ground = self.get_map_height() / 2;
+        self.change_tile(center, ground, 0, STONE_IDS[0])?;
+        self.change_tile(center, ground - 1, 0, 0)?;
+        self.change_tile(center, ground - 2, 0, 0)?;
+        self.spawn_point = (center, ground - 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,39 @@
+/// A section of the 32 bits of data stored alongside every tile (the upper half of the 64 bit tile word the gpu receives)
+#[derive(Clone, Copy, Debug)]
+pub struct TileDataField {
+    pub offset: u32,
+    pub bits: u32,
+}
+
+impl TileDataField {
+    pub const fn new(offset: u32, bits: u32) -> Self {
+        TileDataField { offset, bits }
+    }
+
+    pub fn mask(&self) -> u32 {
+        (((1u64 << self.bits) - 1) as u32) << self.offset
+    }
+
+    pub fn get(&self, data: u32) -> u32 {
+        (data & self.mask()) >> self.offset
+    }
+
+    /// Writes the value into the field (anything too large for the field is cut off)
+    pub fn set(&self, data: u32, value: u32) -> u32 {
+        (data & !self.mask()) | ((value << self.offset) & self.mask())
+    }
+}
+
+// the layout of the tile data; the shader reads the same offsets, so keep them in sync with shader.metal
+/// How far along mining the tile is
+pub static BREAK_DAMAGE: TileDataField = TileDataField::new(0, 8);
+/// 0 is normal, 1 is flipped horizontally, 2 is flipped vertically, and 3 is both
+pub static ORIENTATION: TileDataField = TileDataField::new(8, 2);
+/// One bit per wire color
+pub static WIRING: TileDataField = TileDataField::new(10, 4);
+/// The shape the tile has been hammered into (see TileShape in collision.rs)
+pub static SHAPE: TileDataField = TileDataField::new(14, 3);
+// bits 17 through 31 are still free
+
+/// The fields describing the tile itself, which are cleared when it's replaced (the rest, like the wiring, belong to the spot it's in)
+pub static TILE_FIELDS: &[TileDataField] = &[BREAK_DAMAGE];
@@ -0,0 +1,190 @@
+use rand::Rng;
+
+use crate::game_manager::entities::player::{inventory::TileDrop, items::{ItemGenerator, ItemType}};
+use crate::game_manager::world::tile_map::{TileMap, TileMapError};
+use crate::game_manager::world::tile_map::registry::{TileType, get_tile_properties};
+
+/// The most tiles a single tree can be made of (stops the search running off through a forest of touching trees)
+static MAX_TREE_TILES: usize = 64;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TreeType {
+    Oak,
+    Cactus,
+}
+
+/// A multi tile tree drawn on the background layer
+pub struct TreeInfo {
+    pub tree_type: TreeType,
+    /// (x offset, y offset, tile id) from the ground tile under the trunk (the row at 0 sits behind the ground itself)
+    pub tiles: &'static [(isize, isize, u32)],
+    pub trunk: &'static [u32],  // the tiles that can be chopped to fell the tree (the canopy can't be reached)
+    pub ground: &'static [TileType],  // what the tree grows on
+    pub sapling: u32,  // the tile its seed (an acorn or cactus seed) becomes when planted on the ground
+    pub hardness: f32,  // the seconds it takes to chop down with a mining speed of 1
+    pub drops: TileDrop,  // dropped once for the whole tree
+    pub growth_chance: f32,  // the chance for the sapling to grow each time it's randomly ticked
+}
+
+// the layout of the tree sprite sheet (+ 48)
+// _  0  1  2  3
+// 4  5  6  7  8
+// 9  10 11 12 13
+// _  14 15 16 17
+// _  18 19 20 _
+// _  21 22 23 _
+// _  _  24 _  _
+// _  _  28 29 _
+// _  _  34 35 _
+static OAK_TILES: &[(isize, isize, u32)] = &[
+    (-1, -8, 48     ), ( 0, -8, 48 + 1 ), ( 1, -8, 48 + 2 ), ( 2, -8, 48 + 3 ),
+    (-2, -7, 48 + 4 ), (-1, -7, 48 + 5 ), ( 0, -7, 48 + 6 ), ( 1, -7, 48 + 7 ), ( 2, -7, 48 + 8 ),
+    (-2, -6, 48 + 9 ), (-1, -6, 48 + 10), ( 0, -6, 48 + 11), ( 1, -6, 48 + 12), ( 2, -6, 48 + 13),
+    (-1, -5, 48 + 14), ( 0, -5, 48 + 15), ( 1, -5, 48 + 16), ( 2, -5, 48 + 17),
+    (-1, -4, 48 + 18), ( 0, -4, 48 + 19), ( 1, -4, 48 + 20),
+    (-1, -3, 48 + 21), ( 0, -3, 48 + 22), ( 1, -3, 48 + 23),
+    ( 0, -2, 48 + 24),
+    ( 0, -1, 48 + 28), ( 1, -1, 48 + 29),
+    ( 0,  0, 48 + 34), ( 1,  0, 48 + 35),
+];
+
+static CACTUS_TILES: &[(isize, isize, u32)] = &[
+    (0, -5, 161),
+    (0, -4, 153),
+    (0, -3, 155),
+    (0, -2, 154),
+    (0, -1, 156),
+];
+
+pub static TREES: &[TreeInfo] = &[
+    TreeInfo {
+        tree_type: TreeType::Oak,
+        tiles: OAK_TILES,
+        trunk: &[48 + 18, 48 + 19, 48 + 20, 48 + 21, 48 + 22, 48 + 23, 48 + 24, 48 + 28, 48 + 29, 48 + 34, 48 + 35],
+        ground: &[TileType::Grass, TileType::Dirt, TileType::Snow],
+        sapling: 365,
+        hardness: 2.5,
+        drops: TileDrop::new(&[1.0, 0.75], &[
+            ItemGenerator::new((4, 8), 14, Some(ItemType::Block(163)), "Wood", 512),
+            ItemGenerator::new((1, 2), 15, Some(ItemType::Block(365)), "Acorn", 512),
+        ], &[17, 18]),
+        growth_chance: 0.15,
+    },
+    TreeInfo {
+        tree_type: TreeType::Cactus,
+        tiles: CACTUS_TILES,
+        trunk: &[153, 154, 155, 156, 161],
+        ground: &[TileType::Sand],
+        sapling: 366,
+        hardness: 1.0,
+        drops: TileDrop::new(&[0.75], &[ItemGenerator::new((1, 2), 16, Some(ItemType::Block(366)), "Cactus Seed", 512)], &[19]),
+        growth_chance: 0.1,
+    },
+];
+
+pub fn get_tree(tree_type: TreeType) -> &'static TreeInfo {
+    TREES.iter().find(|tree| tree.tree_type == tree_type).unwrap_or(&TREES[0])
+}
+
+/// Finds the tree that the tile is part of (checking the canopy as well as the trunk)
+fn get_tree_info(tile: u32) -> Option<&'static TreeInfo> {
+    TREES.iter().find(|tree| tree.tiles.iter().any(|(_, _, tree_tile)| *tree_tile == tile))
+}
+
+impl TileMap {
+    /// Gets the tree if the tile is the chopable part of one (trees sit on the background layer)
+    pub fn get_tree_trunk(&self, x: usize, y: usize) -> Option<&'static TreeInfo> {
+        let tile = self.get_tile(x, y, 1);
+        TREES.iter().find(|tree| tree.trunk.contains(&tile))
+    }
+
+    /// Finds every tile of the tree connected to the tile (on the background layer), or nothing if it isn't part of a tree
+    pub fn get_tree_tiles(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        let tree = match get_tree_info(self.get_tile(x, y, 1)) {
+            Some(tree) => tree,
+            None => return Vec::new(),
+        };
+        // searching out through the connected tiles of the same tree, rather than trusting the layout, as the edges of the world can cut bits off
+        let mut found = vec![(x, y)];
+        let mut index = 0;
+        while index < found.len() && found.len() < MAX_TREE_TILES {
+            let (tile_x, tile_y) = found[index];
+            index += 1;
+            for (neighbour_x, neighbour_y) in [
+                (tile_x.wrapping_sub(1), tile_y), (tile_x + 1, tile_y),
+                (tile_x, tile_y.wrapping_sub(1)), (tile_x, tile_y + 1),
+            ] {
+                if neighbour_x >= self.get_map_width() || neighbour_y >= self.get_map_height() { continue; }
+                if found.contains(&(neighbour_x, neighbour_y)) { continue; }
+                let tile = self.get_tile(neighbour_x, neighbour_y, 1);
+                if tree.tiles.iter().any(|(_, _, tree_tile)| *tree_tile == tile) {
+                    found.push((neighbour_x, neighbour_y));
+                }
+            }
+        }
+        found
+    }
+
+    /// Removes every tile of the tree connected to the tile, returning what kind of tree it was
+    pub fn fell_tree(&mut self, x: usize, y: usize) -> Option<&'static TreeInfo> {
+        let tree = get_tree_info(self.get_tile(x, y, 1))?;
+        for (tile_x, tile_y) in self.get_tree_tiles(x, y) {
+            *self.get_tile_mut(tile_x, tile_y, 1) = 0;
+        }
+        // the chopping cracked the (empty) main layer tile in front of the trunk
+        self.clear_tile_damage(x, y);
+        Some(tree)
+    }
+
+    /// Places the tree with its trunk on top of the ground tile (doesn't check if there's room, see can_grow_tree)
+    pub fn place_tree(&mut self, tree: &TreeInfo, ground_x: usize, ground_y: usize) {
+        for (offset_x, offset_y, tile) in tree.tiles {
+            let x = ground_x as isize + offset_x;
+            let y = ground_y as isize + offset_y;
+            if x < 0 || y < 0 || x as usize >= self.get_map_width() || y as usize >= self.get_map_height() { continue; }
+            *self.get_tile_mut(x as usize, y as usize, 1) = *tile;
+        }
+    }
+
+    /// Whether the tree has the space to grow from the ground tile (the sapling itself is ignored as it's replaced)
+    pub fn can_grow_tree(&self, tree: &TreeInfo, ground_x: usize, ground_y: usize) -> bool {
+        if !tree.ground.contains(&get_tile_properties(self.get_tile(ground_x, ground_y, 0)).tile_type) { return false; }
+        let sapling = ground_y.checked_sub(1).map(|sapling_y| (ground_x, sapling_y));
+        tree.tiles.iter().all(|(offset_x, offset_y, _)| {
+            let x = ground_x as isize + offset_x;
+            let y = ground_y as isize + offset_y;
+            if x < 0 || y < 0 || x as usize >= self.get_map_width() || y as usize >= self.get_map_height() { return false; }
+            let (x, y) = (x as usize, y as usize);
+            let blocked = *offset_y < 0 && self.is_solid(x, y) && Some((x, y)) != sapling;
+            !blocked && self.get_tile(x, y, 1) == 0
+        })
+    }
+
+    /// Whether the sapling can be planted at the position (its tree has to be able to grow on the ground below)
+    pub fn can_plant_sapling(&self, x: usize, y: usize, sapling: u32) -> bool {
+        if y + 1 >= self.get_map_height() || self.get_tile(x, y, 0) != 0 { return false; }
+        let ground = get_tile_properties(self.get_tile(x, y + 1, 0)).tile_type;
+        TREES.iter().any(|tree| tree.sapling == sapling && tree.ground.contains(&ground))
+    }
+
+    /// Plants the sapling, returning false (and leaving the tile alone) if it can't be planted there
+    pub fn plant_sapling(&mut self, x: usize, y: usize, sapling: u32) -> Result<bool, TileMapError> {
+        if !self.can_plant_sapling(x, y, sapling) { return Ok(false); }
+        self.change_tile(x, y, 0, sapling)?;
+        Ok(true)
+    }
+
+    /// Has a chance of growing the sapling into a full tree (run through the random ticks)
+    pub(crate) fn grow_sapling(&mut self, x: usize, y: usize, rand_state: &mut dyn rand::RngCore) -> Result<(), TileMapError> {
+        let sapling = self.get_tile(x, y, 0);
+        let tree = match TREES.iter().find(|tree| tree.sapling == sapling) {
+            Some(tree) => tree,
+            None => return Ok(()),
+        };
+        if rand_state.random_range::<f32, _>(0.0..1.0) >= tree.growth_chance { return Ok(()); }
+        if y + 1 >= self.get_map_height() || !self.can_grow_tree(tree, x, y + 1) { return Ok(()); }
+        self.change_tile(x, y, 0, 0)?;
+        self.place_tree(tree, x, y + 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::game_manager::world::tile_map::{TileMap, TileMapError};
+use crate::game_manager::world::tile_map::tile_data::WIRING;
+
+/// How many colors of wire there are (one bit each in the wiring tile data)
+pub static WIRE_COLORS: u32 = 4;
+/// How long a button stays pressed down for (in seconds)
+static BUTTON_PRESS_TIME: f64 = 1.0;
+/// How fast a spike trap throws the player upwards when it's triggered under them
+pub static TRAP_LAUNCH_SPEED: f32 = -450.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum DeviceKind {
+    Lever,
+    Button,
+    PressurePlate,
+    Door,
+    Lamp,
+    Trap,
+}
+
+impl DeviceKind {
+    /// Sources power the wires they sit on while they're on, consumers turn on while any of their wires are powered
+    fn is_source(&self) -> bool {
+        matches!(self, DeviceKind::Lever | DeviceKind::Button | DeviceKind::PressurePlate)
+    }
+}
+
+/// A tile that takes part in the wiring (swapping between its off and on tiles)
+struct WireDevice {
+    kind: DeviceKind,
+    off: u32,
+    on: u32,
+}
+
+static WIRE_DEVICES: &[WireDevice] = &[
+    WireDevice { kind: DeviceKind::Lever,         off: 369, on: 370 },
+    WireDevice { kind: DeviceKind::Button,        off: 371, on: 372 },
+    WireDevice { kind: DeviceKind::PressurePlate, off: 373, on: 374 },
+    WireDevice { kind: DeviceKind::Door,          off: 375, on: 376 },  // open while powered
+    WireDevice { kind: DeviceKind::Lamp,          off: 377, on: 378 },
+    WireDevice { kind: DeviceKind::Trap,          off: 379, on: 380 },
+];
+
+/// Gets the device the tile is, and whether it's currently on
+fn get_wire_device(tile: u32) -> Option<(&'static WireDevice, bool)> {
+    WIRE_DEVICES.iter().find_map(|device| match tile {
+        tile if tile == device.off => Some((device, false)),
+        tile if tile == device.on => Some((device, true)),
+        _ => None,
+    })
+}
+
+/// The tiles joined together by a single color of wire
+#[derive(bincode::Encode, bincode::Decode)]
+struct WireNetwork {
+    sources: Vec<(usize, usize)>,
+    powered: bool,
+}
+
+/// The graph of wire networks, along with the switches currently held down
+/// The graph is only rebuilt when a wire or device is placed or removed, and the power is re-checked every update
+#[derive(bincode::Encode, bincode::Decode)]
+pub struct Wiring {
+    wired_tiles: BTreeSet<(usize, usize)>,  // every tile with any wire on it (so the rebuild doesn't have to search the whole map)
+    networks: Vec<WireNetwork>,
+    consumers: BTreeMap<(usize, usize), Vec<usize>>,  // the networks feeding into each consumer
+    needs_rebuild: bool,
+    pressed_buttons: Vec<((usize, usize), f64)>,  // (the button, the seconds until it pops back up)
+    pressed_plates: Vec<(usize, usize)>,
+}
+
+impl Default for Wiring {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Wiring {
+    pub fn new() -> Self {
+        Wiring {
+            wired_tiles: BTreeSet::new(),
+            networks: Vec::new(),
+            consumers: BTreeMap::new(),
+            needs_rebuild: false,
+            pressed_buttons: Vec::new(),
+            pressed_plates: Vec::new(),
+        }
+    }
+}
+
+impl TileMap {
+    /// The wire colors running through the tile (one bit per color)
+    pub fn get_wires(&self, x: usize, y: usize) -> u32 {
+        self.get_tile_data_field(x, y, 0, WIRING)
+    }
+
+    pub fn place_wire(&mut self, x: usize, y: usize, color: u32) {
+        if color >= WIRE_COLORS { return; }
+        let wires = self.get_wires(x, y) | (1 << color);
+        self.set_tile_data_field(x, y, 0, WIRING, wires);
+    }
+
+    pub fn cut_wire(&mut self, x: usize, y: usize, color: u32) {
+        if color >= WIRE_COLORS { return; }
+        let wires = self.get_wires(x, y) & !(1 << color);
+        self.set_tile_data_field(x, y, 0, WIRING, wires);
+    }
+
+    /// Keeps track of which tiles are wired (called by set_tile_data whenever the wires on a tile change)
+    pub(crate) fn wires_changed(&mut self, x: usize, y: usize, wires: u32) {
+        match wires {
+            0 => { self.wiring.wired_tiles.remove(&(x, y)); },
+            _ => { self.wiring.wired_tiles.insert((x, y)); },
+        }
+        self.wiring.needs_rebuild = true;
+    }
+
+    /// Rebuilds the graph if a device was placed or removed (called by change_tile; a device only switching on or off is left alone)
+    pub(crate) fn check_wire_devices(&mut self, old_tile: u32, new_tile: u32) {
+        let old_device = get_wire_device(old_tile).map(|(device, _)| device.kind);
+        let new_device = get_wire_device(new_tile).map(|(device, _)| device.kind);
+        if old_device != new_device {
+            self.wiring.needs_rebuild = true;
+        }
+    }
+
+    /// Levers and buttons are flipped by right clicking them
+    pub fn is_switch(&self, x: usize, y: usize) -> bool {
+        get_wire_device(self.get_tile(x, y, 0)).is_some_and(|(device, _)| matches!(device.kind, DeviceKind::Lever | DeviceKind::Button))
+    }
+
+    /// Consumers on a wire are switched by the circuit rather than by hand
+    pub(crate) fn is_wire_controlled(&self, x: usize, y: usize) -> bool {
+        self.wiring.consumers.contains_key(&(x, y))
+    }
+
+    /// Any tile that takes part in the wiring (levers, doors, traps and so on)
+    pub(crate) fn is_wire_device(&self, x: usize, y: usize) -> bool {
+        get_wire_device(self.get_tile(x, y, 0)).is_some()
+    }
+
+    pub fn is_active_trap(&self, x: usize, y: usize) -> bool {
+        get_wire_device(self.get_tile(x, y, 0)).is_some_and(|(device, on)| device.kind == DeviceKind::Trap && on)
+    }
+
+    /// Flips a lever or presses a button
+    pub fn use_switch(&mut self, x: usize, y: usize) -> Result<(), TileMapError> {
+        match get_wire_device(self.get_tile(x, y, 0)) {
+            Some((device, on)) if device.kind == DeviceKind::Lever => self.set_device_state(x, y, !on),
+            Some((device, _)) if device.kind == DeviceKind::Button => {
+                // pressing it again while it's down just holds it down for longer
+                self.wiring.pressed_buttons.retain(|(button, _)| *button != (x, y));
+                self.wiring.pressed_buttons.push(((x, y), BUTTON_PRESS_TIME));
+                self.set_device_state(x, y, true)
+            },
+            _ => Ok(()),
+        }
+    }
+
+    fn set_device_state(&mut self, x: usize, y: usize, on: bool) -> Result<(), TileMapError> {
+        if let Some((device, current)) = get_wire_device(self.get_tile(x, y, 0)) && current != on {
+            self.change_tile(x, y, 0, if on { device.on } else { device.off })?;
+        } Ok(())
+    }
+
+    /// Sets a consumer's state, except doors are left open while something is in the way of them closing (they close once it moves)
+    fn set_consumer_state(&mut self, x: usize, y: usize, on: bool, blocked_tiles: &[(usize, usize)]) -> Result<(), TileMapError> {
+        let closing_door = !on && get_wire_device(self.get_tile(x, y, 0)).is_some_and(|(device, _)| device.kind == DeviceKind::Door);
+        if closing_door && blocked_tiles.contains(&(x, y)) { return Ok(()); }
+        self.set_device_state(x, y, on)
+    }
+
+    /// Finds every network by flooding along each color of wire, returning the consumers that lost all their wires
+    fn rebuild_wire_graph(&mut self) -> Vec<(usize, usize)> {
+        let mut networks = Vec::new();
+        let mut consumers: BTreeMap<(usize, usize), Vec<usize>> = BTreeMap::new();
+        for color in 0..WIRE_COLORS {
+            let mut visited = BTreeSet::new();
+            for start in &self.wiring.wired_tiles {
+                if self.get_wires(start.0, start.1) & (1 << color) == 0 || visited.contains(start) { continue; }
+                let index = networks.len();
+                let mut network = WireNetwork { sources: Vec::new(), powered: false };
+                let mut stack = vec![*start];
+                visited.insert(*start);
+                while let Some((x, y)) = stack.pop() {
+                    if let Some((device, _)) = get_wire_device(self.get_tile(x, y, 0)) {
+                        match device.kind.is_source() {
+                            true => network.sources.push((x, y)),
+                            false => consumers.entry((x, y)).or_default().push(index),
+                        }
+                    }
+                    // off the map wraps around to a huge index, which has no wires
+                    for neighbour in [(x.wrapping_sub(1), y), (x + 1, y), (x, y.wrapping_sub(1)), (x, y + 1)] {
+                        if self.get_wires(neighbour.0, neighbour.1) & (1 << color) != 0 && visited.insert(neighbour) {
+                            stack.push(neighbour);
+                        }
+                    }
+                }
+                networks.push(network);
+            }
+        }
+        let disconnected = self.wiring.consumers.keys().filter(|consumer| !consumers.contains_key(consumer)).copied().collect();
+        self.wiring.networks = networks;
+        self.wiring.consumers = consumers;
+        self.wiring.needs_rebuild = false;
+        disconnected
+    }
+
+    /// Lets go of any buttons and pressure plates, then powers the consumers on each network
+    /// The occupied tiles are the ones with something standing in them, the player or any entity (to weigh down the pressure plates)
+    /// and the blocked tiles are every tile the player overlaps (so a door doesn't close on them)
+    pub fn update_wiring(&mut self, delta_time: f64, occupied_tiles: &[(usize, usize)], blocked_tiles: &[(usize, usize)]) -> Result<(), TileMapError> {
+        for button in &mut self.wiring.pressed_buttons {
+            button.1 -= delta_time;
+        }
+        let released_buttons = self.wiring.pressed_buttons.iter().filter(|(_, time_left)| *time_left <= 0.0).map(|(button, _)| *button).collect::<Vec<_>>();
+        self.wiring.pressed_buttons.retain(|(_, time_left)| *time_left > 0.0);
+        for (x, y) in released_buttons {
+            self.set_device_state(x, y, false)?;
+        }
+
+        for (x, y) in std::mem::take(&mut self.wiring.pressed_plates) {
+            if !occupied_tiles.contains(&(x, y)) {
+                self.set_device_state(x, y, false)?;
+            }
+        }
+        for &(x, y) in occupied_tiles {
+            let is_plate = get_wire_device(self.get_tile(x, y, 0)).is_some_and(|(device, _)| device.kind == DeviceKind::PressurePlate);
+            if is_plate && !self.wiring.pressed_plates.contains(&(x, y)) {
+                self.set_device_state(x, y, true)?;
+                self.wiring.pressed_plates.push((x, y));
+            }
+        }
+
+        if self.wiring.needs_rebuild {
+            for (x, y) in self.rebuild_wire_graph() {
+                self.set_consumer_state(x, y, false, blocked_tiles)?;
+            }
+        }
+
+        let powered = self.wiring.networks.iter()
+            .map(|network| network.sources.iter().any(|(x, y)| get_wire_device(self.get_tile(*x, *y, 0)).is_some_and(|(_, on)| on)))
+            .collect::<Vec<_>>();
+        for (network, powered) in self.wiring.networks.iter_mut().zip(powered) {
+            network.powered = powered;
+        }
+        let consumer_states = self.wiring.consumers.iter()
+            .map(|(consumer, networks)| (*consumer, networks.iter().any(|network| self.wiring.networks[*network].powered)))
+            .collect::<Vec<_>>();
+        for ((x, y), powered) in consumer_states {
+            self.set_consumer_state(x, y, powered, blocked_tiles)?;
+        } Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static RED: u32 = 0;
+    static BLUE: u32 = 1;
+    static LEVER: u32 = 369;
+    static LAMP: u32 = 377;
+    static LAMP_ON: u32 = 378;
+
+    /// A lever at (1, 4) wired up in red to a lamp at (5, 4)
+    fn wired_lamp() -> TileMap {
+        let mut tile_map = TileMap::test_tile_map(8, 8);
+        tile_map.change_tile(1, 4, 0, LEVER).unwrap();
+        tile_map.change_tile(5, 4, 0, LAMP).unwrap();
+        for x in 1..=5 {
+            tile_map.place_wire(x, 4, RED);
+        }
+        tile_map.update_wiring(0.1, &[], &[]).unwrap();
+        tile_map
+    }
+
+    #[test]
+    fn a_lever_powers_the_lamp() {
+        let mut tile_map = wired_lamp();
+        assert_eq!(tile_map.get_tile(5, 4, 0), LAMP);
+
+        tile_map.use_switch(1, 4).unwrap();
+        tile_map.update_wiring(0.1, &[], &[]).unwrap();
+        assert_eq!(tile_map.get_tile(5, 4, 0), LAMP_ON);
+    }
+
+    #[test]
+    fn cutting_the_wire_turns_the_lamp_off() {
+        let mut tile_map = wired_lamp();
+        tile_map.use_switch(1, 4).unwrap();
+        tile_map.update_wiring(0.1, &[], &[]).unwrap();
+
+        tile_map.cut_wire(3, 4, RED);
+        tile_map.update_wiring(0.1, &[], &[]).unwrap();
+        assert_eq!(tile_map.get_tile(5, 4, 0), LAMP);
+    }
+
+    #[test]
+    fn other_colors_crossing_the_wire_stay_unpowered() {
+        let mut tile_map = wired_lamp();
+        // a blue wire running down through the middle of the red one, with its own lamp
+        tile_map.change_tile(3, 7, 0, LAMP).unwrap();
+        for y in 1..=7 {
+            tile_map.place_wire(3, y, BLUE);
+        }
+        tile_map.use_switch(1, 4).unwrap();
+        tile_map.update_wiring(0.1, &[], &[]).unwrap();
+
+        assert_eq!(tile_map.get_tile(5, 4, 0), LAMP_ON);
+        assert_eq!(tile_map.get_tile(3, 7, 0), LAMP);
+    }
+}
@@ -0,0 +1,346 @@
+use rand::{Rng, SeedableRng};
+
+use crate::game_manager::entities::player::player::CameraTransform;
+use crate::game_manager::world::tile_map::TileMap;
+use crate::game_manager::world::world_gen::Biom;
+
+/// How long the sky light and precipitation take to fade from one weather to the next (in seconds)
+static WEATHER_FADE_TIME: f64 = 20.0;
+/// The most rain drops and snow flakes falling at once (kept well under the entity buffer's size)
+static MAX_PRECIPITATION: usize = 384;
+/// The entity textures for the falling rain and snow
+static RAIN_TEXTURE: u32 = 22;
+static SNOW_TEXTURE: u32 = 23;
+
+#[derive(bincode::Encode, bincode::Decode, Clone, Copy, PartialEq, Debug)]
+pub enum WeatherState {
+    Clear,
+    Rain,
+    Storm,
+    Snow,  // a gentler, longer fall (only actually snow in the tundra, see PrecipitationKind)
+}
+
+/// What actually falls from the sky, which depends on the biom rather than the weather
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PrecipitationKind {
+    Rain,
+    Snow,
+}
+
+impl PrecipitationKind {
+    /// Only the tundra is cold enough for snow, anywhere else it all falls as rain
+    pub fn for_biom(biom: Biom) -> Self {
+        match biom {
+            Biom::Tundra => PrecipitationKind::Snow,
+            // the sky islands are above the clouds, so they never actually get any
+            Biom::Forest | Biom::Desert | Biom::Jungle | Biom::Sky => PrecipitationKind::Rain,
+        }
+    }
+
+    /// The entity texture and how fast it falls compared to rain
+    fn get_texture_and_speed(&self) -> (u32, f32) {
+        match self {
+            PrecipitationKind::Rain => (RAIN_TEXTURE, 1.0),
+            PrecipitationKind::Snow => (SNOW_TEXTURE, 0.35),
+        }
+    }
+}
+
+/// How each kind of weather behaves
+struct WeatherInfo {
+    state: WeatherState,
+    duration: (f64, f64),  // the range of seconds it lasts
+    transitions: &'static [(WeatherState, f32)],  // the weights of what it can turn into next
+    sky_brightness: f32,   // a multiplier on the sky light
+    precipitation: f32,    // the drops spawned per visible column each second
+    fall_speed: (f32, f32),  // (sideways, down) in pixels per second
+    mob_spawn_rate: f32,   // a multiplier on how often mobs spawn
+}
+
+static WEATHER_INFO: &[WeatherInfo] = &[
+    WeatherInfo {
+        state: WeatherState::Clear,
+        duration: (300.0, 900.0),
+        transitions: &[(WeatherState::Rain, 0.6), (WeatherState::Storm, 0.15), (WeatherState::Snow, 0.25)],
+        sky_brightness: 1.0,
+        precipitation: 0.0,
+        fall_speed: (0.0, 0.0),
+        mob_spawn_rate: 1.0,
+    },
+    WeatherInfo {
+        state: WeatherState::Rain,
+        duration: (120.0, 360.0),
+        transitions: &[(WeatherState::Clear, 0.7), (WeatherState::Storm, 0.3)],
+        sky_brightness: 0.75,
+        precipitation: 4.0,
+        fall_speed: (-20.0, 260.0),
+        mob_spawn_rate: 1.25,
+    },
+    WeatherInfo {
+        state: WeatherState::Storm,
+        duration: (60.0, 240.0),
+        transitions: &[(WeatherState::Rain, 0.6), (WeatherState::Clear, 0.4)],
+        sky_brightness: 0.45,
+        precipitation: 10.0,
+        fall_speed: (-90.0, 340.0),
+        mob_spawn_rate: 1.75,
+    },
+    WeatherInfo {
+        state: WeatherState::Snow,
+        duration: (180.0, 480.0),
+        transitions: &[(WeatherState::Clear, 0.8), (WeatherState::Rain, 0.2)],
+        sky_brightness: 0.85,
+        precipitation: 2.5,
+        fall_speed: (-10.0, 120.0),
+        mob_spawn_rate: 1.1,
+    },
+];
+
+fn get_weather_info(state: WeatherState) -> &'static WeatherInfo {
+    WEATHER_INFO.iter().find(|info| info.state == state).unwrap_or(&WEATHER_INFO[0])
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// The weather of the overworld, which changes after a while based on the world's seed
+#[derive(bincode::Encode, bincode::Decode)]
+pub struct Weather {
+    state: WeatherState,
+    previous_state: WeatherState,  // faded out of over the first part of the current weather
+    time: f64,       // seconds into the current weather
+    duration: f64,   // how long the current weather lasts
+    rng_state: u64,  // re-seeded on each change so the same world always has the same weather
+}
+
+impl Weather {
+    pub fn new(seed: f32) -> Self {
+        Weather {
+            state: WeatherState::Clear,
+            previous_state: WeatherState::Clear,
+            time: 0.0,
+            duration: get_weather_info(WeatherState::Clear).duration.0,
+            rng_state: seed.to_bits() as u64,
+        }
+    }
+
+    /// Moves the weather along, returning the new weather when it changes
+    pub fn update(&mut self, delta_time: f64) -> Option<WeatherState> {
+        self.time += delta_time;
+        if self.time < self.duration { return None; }
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(self.rng_state);
+        let info = get_weather_info(self.state);
+        let total_weight: f32 = info.transitions.iter().map(|(_, weight)| *weight).sum();
+        let mut roll = rng.random_range(0.0..total_weight.max(f32::EPSILON));
+        let mut next_state = info.transitions.last().map(|(state, _)| *state).unwrap_or(WeatherState::Clear);
+        for (state, weight) in info.transitions {
+            if roll < *weight {
+                next_state = *state;
+                break;
+            }
+            roll -= weight;
+        }
+
+        let (min_duration, max_duration) = get_weather_info(next_state).duration;
+        self.previous_state = self.state;
+        self.state = next_state;
+        self.time = 0.0;
+        self.duration = rng.random_range(min_duration..=max_duration);
+        self.rng_state = rng.random();
+        Some(next_state)
+    }
+
+    pub fn get_state(&self) -> WeatherState {
+        self.state
+    }
+
+    /// What the current weather is called in the biom (None when it's clear)
+    pub fn get_name_at(&self, biom: Biom) -> Option<&'static str> {
+        match (self.state, PrecipitationKind::for_biom(biom)) {
+            (WeatherState::Clear, _) => None,
+            (WeatherState::Rain, PrecipitationKind::Rain) => Some("rain"),
+            (WeatherState::Rain, PrecipitationKind::Snow) => Some("snow"),
+            (WeatherState::Storm, PrecipitationKind::Rain) => Some("storm"),
+            (WeatherState::Storm, PrecipitationKind::Snow) => Some("blizzard"),
+            (WeatherState::Snow, PrecipitationKind::Rain) => Some("drizzle"),
+            (WeatherState::Snow, PrecipitationKind::Snow) => Some("snow"),
+        }
+    }
+
+    /// How far the fade from the previous weather has gotten (0 to 1)
+    fn get_fade(&self) -> f32 {
+        (self.time / WEATHER_FADE_TIME).min(1.0) as f32
+    }
+
+    /// The multiplier on the sky light (clouds dim it down)
+    pub fn get_sky_brightness(&self) -> f32 {
+        lerp(get_weather_info(self.previous_state).sky_brightness, get_weather_info(self.state).sky_brightness, self.get_fade())
+    }
+
+    /// The drops spawned per visible column each second
+    fn get_precipitation(&self) -> f32 {
+        lerp(get_weather_info(self.previous_state).precipitation, get_weather_info(self.state).precipitation, self.get_fade())
+    }
+
+    /// How fast the drops fall (clearing weather keeps the speed of whatever is fading out)
+    fn get_fall_speed(&self) -> (f32, f32) {
+        match get_weather_info(self.state).precipitation > 0.0 {
+            true => get_weather_info(self.state).fall_speed,
+            false => get_weather_info(self.previous_state).fall_speed,
+        }
+    }
+
+    /// The multiplier on how often mobs spawn (more come out in bad weather)
+    pub fn get_mob_spawn_rate(&self) -> f32 {
+        get_weather_info(self.state).mob_spawn_rate
+    }
+}
+
+/// A single rain drop or snow flake
+struct Drop {
+    position: (f32, f32),  // in pixels (the same space as the falling tiles)
+    velocity: (f32, f32),
+    texture: u32,
+}
+
+/// The rain and snow falling around the camera (purely visual, so it isn't saved)
+pub struct Precipitation {
+    drops: Vec<Drop>,
+    spawn_timer: f32,  // the fraction of a drop carried over between frames
+}
+
+impl Default for Precipitation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Precipitation {
+    pub fn new() -> Self {
+        Precipitation {
+            drops: Vec::new(),
+            spawn_timer: 0.0,
+        }
+    }
+
+    /// Clears out anything still falling (such as when leaving the overworld)
+    pub fn clear(&mut self) {
+        self.drops.clear();
+        self.spawn_timer = 0.0;
+    }
+
+    /// Spawns new drops along the top of the screen and moves the rest, removing any that hit the ground
+    pub fn update(
+        &mut self,
+        delta_time: f64,
+        weather: &Weather,
+        tile_map: &TileMap,
+        camera: &CameraTransform,
+        screen_size: (u32, u32),
+        rand_state: &mut dyn rand::RngCore,
+    ) {
+        let edge_x = screen_size.0 as f32 * 0.5 * camera.zoom;
+        let edge_y = screen_size.1 as f32 * 0.5 * camera.zoom;
+        let map_width = tile_map.get_map_width();
+
+        // the drops stop once they reach the first solid tile of their column (or fall out of view)
+        self.drops.retain_mut(|drop| {
+            drop.position.0 += drop.velocity.0 * delta_time as f32;
+            drop.position.1 += drop.velocity.1 * delta_time as f32;
+            let column = (drop.position.0 / 8.0).max(0.0) as usize;
+            if column >= map_width { return false; }
+            drop.position.1 < tile_map.sky_light[column] as f32 * 8.0 && drop.position.1 < camera.y + edge_y + 8.0
+        });
+
+        let fall_speed = weather.get_fall_speed();
+        let visible_columns = edge_x * 2.0 / 8.0;
+        self.spawn_timer += weather.get_precipitation() * visible_columns * delta_time as f32;
+        let spawn_y = camera.y - edge_y - 8.0;
+        while self.spawn_timer >= 1.0 && self.drops.len() < MAX_PRECIPITATION {
+            self.spawn_timer -= 1.0;
+            // starting a bit upwind so the slanted rain still covers the whole screen
+            let x = camera.x + rand_state.random_range(-edge_x..edge_x + 8.0) - fall_speed.0 * edge_y * 2.0 / fall_speed.1.max(1.0);
+            let column = (x / 8.0).max(0.0) as usize;
+            // only columns open to the sky get any (nothing falls in caves or under a roof)
+            if column >= map_width || spawn_y >= tile_map.sky_light[column] as f32 * 8.0 { continue; }
+            let row = (spawn_y / 8.0).max(0.0) as usize;
+            let Some(biom) = tile_map.biome_at(column, row) else { continue; };
+            let (texture, speed_scale) = PrecipitationKind::for_biom(biom).get_texture_and_speed();
+            // a little variation so they don't all fall in lock step
+            let variation = rand_state.random_range(0.85..1.15);
+            self.drops.push(Drop {
+                position: (x, spawn_y),
+                velocity: (fall_speed.0 * speed_scale * variation, fall_speed.1 * speed_scale * variation),
+                texture,
+            });
+        }
+        self.spawn_timer = self.spawn_timer.min(1.0);
+    }
+
+    pub fn get_render(&self, camera: &CameraTransform, screen_size: (u32, u32)) -> Vec<(u32, u16, i16, i16, u16, u32)> {
+        let edge_x = screen_size.0 as f32 * 0.5 * camera.zoom;
+        let edge_y = screen_size.1 as f32 * 0.5 * camera.zoom;
+        let mut render_data = Vec::with_capacity(self.drops.len());
+        for drop in &self.drops {
+            // offset by a tile to line up with how the tiles themselves are drawn (the same as the falling tiles)
+            let position = (drop.position.0 + 8.0 - camera.x, drop.position.1 + 8.0 - camera.y);
+            if position.0 < -edge_x - 8.0 || position.1 < -edge_y - 8.0 || position.0 > edge_x || position.1 > edge_y {
+                continue;
+            }
+            render_data.push((drop.texture, 0, (position.0 * 100.0) as i16, (position.1 * 100.0) as i16, 0, 0));
+        } render_data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs the weather through the given number of changes, recording each new weather and how long it lasts
+    fn run_changes(weather: &mut Weather, changes: usize) -> Vec<(WeatherState, f64)> {
+        (0..changes)
+            .map(|_| {
+                let state = weather.update(weather.duration - weather.time).expect("the weather should change once it runs out");
+                (state, weather.duration)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn the_same_seed_always_gives_the_same_weather() {
+        let first = run_changes(&mut Weather::new(123456.234), 32);
+        let second = run_changes(&mut Weather::new(123456.234), 32);
+        assert_eq!(first, second);
+        assert_ne!(first, run_changes(&mut Weather::new(42.0), 32));
+    }
+
+    #[test]
+    fn the_weather_only_changes_along_its_transitions() {
+        let mut weather = Weather::new(7.5);
+        assert_eq!(weather.update(get_weather_info(WeatherState::Clear).duration.0 - 1.0), None);
+        let mut previous = weather.get_state();
+        for (state, duration) in run_changes(&mut weather, 64) {
+            assert!(get_weather_info(previous).transitions.iter().any(|(next_state, _)| *next_state == state));
+            let (min_duration, max_duration) = get_weather_info(state).duration;
+            assert!(duration >= min_duration && duration <= max_duration);
+            previous = state;
+        }
+    }
+
+    #[test]
+    fn bad_weather_spawns_more_mobs_and_dims_the_sky() {
+        let mut weather = Weather::new(1.0);
+        assert_eq!(weather.get_mob_spawn_rate(), 1.0);
+        assert_eq!(weather.get_sky_brightness(), 1.0);
+
+        weather.state = WeatherState::Storm;
+        weather.time = 0.0;
+        assert_eq!(weather.get_mob_spawn_rate(), 1.75);
+        // the sky fades over rather than snapping to the new weather
+        assert_eq!(weather.get_sky_brightness(), 1.0);
+        weather.time = WEATHER_FADE_TIME;
+        assert_eq!(weather.get_sky_brightness(), 0.45);
+    }
+}
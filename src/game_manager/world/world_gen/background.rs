@@ -0,0 +1,115 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::game_manager::world::tile_map::{Dimension, TileMap, TileMapError};
+use crate::game_manager::world::world_gen::WorldGenerator;
+use crate::game_manager::world::world_gen::progress::{GenerationMessage, GenerationPass, GenerationReporter};
+use crate::logging::logging::{LoggingError, Logs};
+
+/// A world being generated on its own thread, so the window keeps responding while it runs
+pub struct GenerationJob {
+    receiver: crossbeam::channel::Receiver<GenerationMessage>,
+    cancelled: Arc<AtomicBool>,
+    pass: GenerationPass,
+    progress: f32,  // the progress through the current pass
+    started: std::time::Instant,
+    pub seed: f32,
+}
+
+impl GenerationJob {
+    /// Starts generating the dimension (the empty map is made up front, as creating it needs the logs)
+    pub fn start(seed: f32, dimension: Dimension, (width, height): (usize, usize), logs: &mut Logs) -> Result<Self, TileMapError> {
+        let mut tile_map = TileMap::new(width, height, None, logs)?;
+        let (sender, receiver) = crossbeam::channel::unbounded();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let reporter = GenerationReporter::new(sender.clone(), cancelled.clone());
+        std::thread::Builder::new()
+            .name(String::from("world generation"))
+            .spawn(move || {
+                let world_generator = WorldGenerator::new(seed, dimension);
+                let message = match world_generator.generate_tile_map(&mut tile_map, &reporter) {
+                    Ok(()) => GenerationMessage::Finished(Box::new(tile_map)),
+                    Err(error) => GenerationMessage::Failed(error),
+                };
+                // the menu may have already given up on this world
+                let _ = sender.send(message);
+            })
+            .map_err(|e| TileMapError {
+                message: format!("Failed to start the world generation thread: {:?}", e),
+                level: LoggingError::Error,
+            })?;
+        Ok(GenerationJob {
+            receiver,
+            cancelled,
+            pass: GenerationPass::Terrain,
+            progress: 0.0,
+            started: std::time::Instant::now(),
+            seed,
+        })
+    }
+
+    /// Catches up on the messages from the generation thread, returning the map once it's done (or the error if it failed)
+    pub fn poll(&mut self) -> Option<Result<TileMap, TileMapError>> {
+        while let Ok(message) = self.receiver.try_recv() {
+            match message {
+                GenerationMessage::Progress(pass, progress) => {
+                    self.pass = pass;
+                    self.progress = progress;
+                },
+                GenerationMessage::Finished(tile_map) => return Some(Ok(*tile_map)),
+                GenerationMessage::Failed(error) => return Some(Err(error)),
+            }
+        } None
+    }
+
+    /// Tells the generation thread to stop (it finishes up at its next progress report)
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// How long it's been running for
+    pub fn get_elapsed(&self) -> std::time::Duration {
+        self.started.elapsed()
+    }
+
+    pub fn get_pass(&self) -> GenerationPass {
+        self.pass
+    }
+
+    /// How far through the whole generation it is (0 to 1)
+    pub fn get_total_progress(&self) -> f32 {
+        self.pass.get_total_progress(self.progress)
+    }
+}
+
+impl Drop for GenerationJob {
+    /// Whatever was waiting on the world is gone, so there's no point finishing it
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::logging::Logging;
+
+    #[test]
+    fn cancelled_jobs_return_the_cancel_error() {
+        let (_concluded_sender, concluded_receiver) = crossbeam::channel::bounded(1);
+        let mut logs = Logs::new(concluded_receiver, Logging::Nothing);
+        let mut job = GenerationJob::start(1.0, Dimension::Overworld, (512, 256), &mut logs).unwrap();
+        job.cancel();
+
+        let started = std::time::Instant::now();
+        let result = loop {
+            if let Some(result) = job.poll() { break result; }
+            assert!(started.elapsed().as_secs() < 30, "the generation never stopped");
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        };
+        match result {
+            Ok(_) => panic!("the generation should have been cancelled"),
+            Err(error) => assert_eq!(error.message, "World generation was cancelled"),
+        }
+    }
+}
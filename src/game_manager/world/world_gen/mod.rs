@@ -1,19 +1,86 @@
 use fastnoise_lite::{FastNoiseLite, NoiseType, FractalType};
 use rand::random_range;
 
-use crate::{game_manager::world::tile_map::{DIRT_IDS, GRASS_IDS, ICE_IDS, SAND_IDS, SAND_STONE_IDS, SNOW_IDS, SOLID_TILES, STONE_IDS, TILE_LIGHTS, TileMapError}, logging::logging::LoggingError};
+use crate::game_manager::world::tile_map::{DIRT_IDS, Dimension, TileMap, TileMapError};
+use crate::game_manager::world::tile_map::registry::{TileType, get_tile_properties, get_tile_type_properties};
+use crate::game_manager::world::tile_map::liquids::{LiquidCell, LiquidType, MAX_LIQUID_LEVEL};
+use crate::game_manager::world::tile_map::trees::{TreeType, get_tree};
+use crate::game_manager::world::world_gen::progress::{GenerationPass, GenerationReporter};
 
 
+pub mod sky_islands;
+pub mod progress;
+pub mod background;
+
 #[derive(bincode::Encode, bincode::Decode)]
 pub struct WorldGenerator {
-    seed: f32
+    seed: f32,
+    dimension: Dimension,  // which dimension's terrain this generates
+    layers: Vec<LayerInfo>,
+}
+
+/// The depth based layers of the world, going from the surface down to the underworld
+#[derive(bincode::Encode, bincode::Decode, Clone, Copy, PartialEq, Debug)]
+pub enum Layer {
+    Surface,
+    Underground,
+    Cavern,
+    Underworld,
+}
+
+/// Where a layer begins; some layers are tied to the top of the map and others to the bottom (so smaller maps still get an underworld)
+#[derive(bincode::Encode, bincode::Decode, Clone, Copy)]
+pub enum LayerDepth {
+    FromTop(usize),
+    FromBottom(usize),
+}
+
+impl LayerDepth {
+    pub fn get_row(&self, map_height: usize) -> usize {
+        match self {
+            LayerDepth::FromTop(rows) => *rows,
+            LayerDepth::FromBottom(rows) => map_height.saturating_sub(*rows),
+        }
+    }
 }
 
+/// The generation and visual settings for a single layer
+#[derive(bincode::Encode, bincode::Decode, Clone)]
+pub struct LayerInfo {
+    pub layer: Layer,
+    pub start: LayerDepth,
+    pub cave_scale: (f32, f32),  // scales the cave noise's sample position (stretching it horizontally gives wide, flat caverns)
+    pub cave_threshold_offset: f32,  // negative values carve out more open space
+    pub stone_tile: u32,  // what the base stone gets replaced with inside this layer
+    pub cave_fill: Option<(LiquidType, usize)>,  // a liquid that fills open caves within the given number of rows from the bottom of the map (lava lakes)
+    pub pools: Option<(LiquidType, f32)>,  // the liquid and chance of a pool forming on any given cave floor
+    pub ambient_light: [u8; 3],  // the minimum light level anywhere in the layer
+    pub background_color: [u8; 3],
+}
+
+impl LayerInfo {
+    /// The background color packed the same way the lighting is for the gpu
+    pub fn get_packed_background(&self) -> u32 {
+        self.background_color[0] as u32 |
+        ((self.background_color[1] as u32) << 8) |
+        ((self.background_color[2] as u32) << 16)
+    }
+
+    /// The background color dimmed by the brightness (for the sky at night)
+    pub fn get_packed_background_scaled(&self, brightness: f32) -> u32 {
+        (self.background_color[0] as f32 * brightness) as u32 |
+        (((self.background_color[1] as f32 * brightness) as u32) << 8) |
+        (((self.background_color[2] as f32 * brightness) as u32) << 16)
+    }
+}
+
+#[derive(bincode::Encode, bincode::Decode, Clone, Copy, PartialEq, Debug)]
 pub enum Biom {
     Forest,
     Desert,
     Jungle,
     Tundra,
+    Sky,  // the whole of the sky islands (never picked by the biom noise)
 }
 
 struct BiomInfo {
@@ -224,6 +291,34 @@ fn lerp(a: f32, b: f32, t: f32) -> f32 {
     a * t + b * (1.0 - t)
 }
 
+/// A cheap, deterministic random value in [0, 1) for a position (so the same seed always places features in the same spots)
+fn hash_position(x: usize, y: usize, seed: f32) -> f32 {
+    let mut hash = (x as u32).wrapping_mul(374761393) ^ (y as u32).wrapping_mul(668265263) ^ seed.to_bits().wrapping_mul(2246822519);
+    hash = (hash ^ (hash >> 13)).wrapping_mul(1274126177);
+    hash ^= hash >> 16;
+    hash as f32 / u32::MAX as f32
+}
+
+/// Fills the pocket of open tiles below the water line with liquid, giving up if it leaks into too large of a space
+fn fill_liquid_pocket(tile_map: &mut TileMap, start: (usize, usize), water_line: usize, liquid: LiquidType, max_cells: usize) -> bool {
+    let mut visited = std::collections::HashSet::new();
+    let mut queue = vec![start];
+    let mut cells = vec![];
+    while let Some((x, y)) = queue.pop() {
+        if y <= water_line || y >= tile_map.get_map_height() || x >= tile_map.get_map_width() { continue; }
+        if tile_map.is_solid(x, y) || !tile_map.liquids.get(x, y).is_empty() || !visited.insert((x, y)) { continue; }
+        cells.push((x, y));
+        if cells.len() > max_cells { return false; }  // it's leaking out into a large cave
+        queue.push((x + 1, y));
+        queue.push((x.saturating_sub(1), y));
+        queue.push((x, y + 1));
+        queue.push((x, y - 1));
+    }
+    for (x, y) in cells {
+        tile_map.liquids.set(x, y, LiquidCell::new(liquid, MAX_LIQUID_LEVEL));
+    } true
+}
+
 fn sample_land_noise(x: f32, y: f32, biom: f32, seed: f32) -> f32 {
     let (main_biom, blending_to_biom, weight) = get_biom(biom);
     let noise_main = main_biom.generation_parameters.land_noise.get_noise_3d(x, y, seed);
@@ -246,78 +341,231 @@ fn sample_cave_threshold_noise(x: f32, y: f32, biom: f32, seed: f32) -> f32 {
 }
 
 impl WorldGenerator {
-    pub fn new(seed: f32) -> Self {
-        WorldGenerator {
-            seed
+    pub fn new(seed: f32, dimension: Dimension) -> Self {
+        let layers = match dimension {
+            Dimension::Overworld => Self::overworld_layers(),
+            Dimension::SkyIslands => Self::sky_island_layers(),
+            Dimension::TOTAL => unreachable!("Dimension::TOTAL only counts the dimensions"),
+        };
+        WorldGenerator { seed, dimension, layers }
+    }
+
+    pub fn get_seed(&self) -> f32 {
+        self.seed
+    }
+
+    /// The layers going from the surface down to the underworld
+    fn overworld_layers() -> Vec<LayerInfo> {
+        vec![
+            LayerInfo {
+                layer: Layer::Surface,
+                start: LayerDepth::FromTop(0),
+                cave_scale: (1.4, 1.4),  // small, winding tunnels
+                cave_threshold_offset: 0.1,
+                stone_tile: 29,  // dirt (the stone only starts in the underground)
+                cave_fill: None,
+                pools: None,  // the surface gets proper lakes instead
+                ambient_light: [0, 0, 0],
+                background_color: [204, 204, 229],
+            },
+            LayerInfo {
+                layer: Layer::Underground,
+                start: LayerDepth::FromTop(200),
+                cave_scale: (1.0, 1.0),
+                cave_threshold_offset: 0.0,
+                stone_tile: 44,
+                cave_fill: None,
+                pools: Some((LiquidType::Water, 0.04)),
+                ambient_light: [0, 0, 0],
+                background_color: [92, 70, 54],
+            },
+            LayerInfo {
+                layer: Layer::Cavern,
+                start: LayerDepth::FromTop(400),
+                cave_scale: (0.6, 0.6),  // larger open caves
+                cave_threshold_offset: -0.15,
+                stone_tile: 44,
+                cave_fill: None,
+                pools: Some((LiquidType::Water, 0.06)),
+                ambient_light: [10, 10, 16],
+                background_color: [64, 60, 72],
+            },
+            LayerInfo {
+                layer: Layer::Underworld,
+                start: LayerDepth::FromBottom(150),
+                cave_scale: (0.35, 1.25),  // wide, flat caverns
+                cave_threshold_offset: -0.35,
+                stone_tile: 174,  // ash
+                cave_fill: Some((LiquidType::Lava, 40)),
+                pools: Some((LiquidType::Lava, 0.05)),
+                ambient_light: [64, 24, 12],
+                background_color: [96, 36, 24],
+            },
+        ]
+    }
+
+    /// Gets the layer that the given row of the map is within
+    pub fn get_layer(&self, tile_y: usize, map_height: usize) -> &LayerInfo {
+        // going by the deepest start rather than the order, so overlapping layers on small maps still resolve sensibly
+        self.layers.iter()
+            .filter(|layer| layer.start.get_row(map_height) <= tile_y)
+            .max_by_key(|layer| layer.start.get_row(map_height))
+            .unwrap_or(&self.layers[0])
+    }
+
+    /// Whether any of the four sides of the tile are open
+    fn is_exposed(tile_map: &TileMap, x: usize, y: usize) -> bool {
+        [
+            (x.saturating_sub(1), y),
+            ((x + 1).min(tile_map.get_map_width() - 1), y),
+            (x, y.saturating_sub(1)),
+            (x, (y + 1).min(tile_map.get_map_height() - 1)),
+        ].iter().any(|(side_x, side_y)| !tile_map.is_solid(*side_x, *side_y))
+    }
+
+    /// Picks the edge variant of the tile from the 16 tile autotile set based on which of its neighbours are open
+    pub fn update_edge_tiles(tile_set: [u32; 16], tile_map: &mut TileMap, x: usize, y: usize, layer: usize) {
+        let tiles_outside = [
+            tile_map.get_tile(x.saturating_sub(1), y, layer),
+            tile_map.get_tile((x + 1).min(tile_map.get_map_width() - 1), y, layer),
+            tile_map.get_tile(x, y.saturating_sub(1), layer),
+            tile_map.get_tile(x, (y + 1).min(tile_map.get_map_height() - 1), layer),
+        ];
+        let tile_edges = [
+            !get_tile_properties(tiles_outside[0]).is_solid(),
+            !get_tile_properties(tiles_outside[1]).is_solid(),
+            !get_tile_properties(tiles_outside[2]).is_solid(),
+            !get_tile_properties(tiles_outside[3]).is_solid(),
+        ];
+        // left right up down
+        let new_tile = match tile_edges {
+            [true, false, false, false] => tile_set[0],  // empty to left (wall facing to left)
+            [false, true, false, false] => tile_set[1],  // empty to right (wall facing to right)
+            [true, true, false, false]  => tile_set[2],  // empty to left and right (column)
+            [false, false, true, false] => tile_set[3],  // empty above (normal)
+            [false, false, false, true] => tile_set[4],  // empty below (upsidedown of normal)
+            [false, false, true, true]  => tile_set[5],  // empty above and below (ceiling ig)
+
+            [true, false, true, false]  => tile_set[6],  // empty to left and above (corner)
+            [true, false, false, true]  => tile_set[7],  // empty to left and below (corner)
+            [true, false, true, true]   => tile_set[8],  // empty to left above and below (cap facing left)
+
+            [false, true, false, true]  => tile_set[9],  // empty to right and below (corner)
+            [false, true, true, false]  => tile_set[10],  // empty to right and above (corner)
+            [false, true, true, true]   => tile_set[11],  // empty to right above and below (cap facing right)
+
+            [true, true, true, false]   => tile_set[12],  // empty to left, right and above (cap facing up)
+            [true, true, false, true]   => tile_set[13],  // empty to left, right and below (cap facing down)
+            [true, true, true, true]    => tile_set[14],  // surrounded
+
+            _ => tile_set[15],
+        };
+        *tile_map.get_tile_mut(x, y, layer) = new_tile;
+    }
+    
+    /// Carves out lakes along the surface and fills pockets in the caves with pools of water or lava
+    fn generate_liquids(&self, tile_map: &mut TileMap) {
+        let map_width = tile_map.get_map_width();
+        let map_height = tile_map.get_map_height();
+
+        // surface lakes (carving out a bowl, then filling it up to the lowest of its two rims)
+        for center_x in (64..map_width.saturating_sub(64)).step_by(97) {
+            if hash_position(center_x, 0, self.seed) > 0.25 { continue; }
+            let width = 12 + (hash_position(center_x, 1, self.seed) * 12.0) as usize;
+            let depth = 4 + (hash_position(center_x, 2, self.seed) * 4.0) as usize;
+            let surface = |tile_map: &TileMap, x: usize| (0..map_height).find(|y| tile_map.is_solid(x, *y)).unwrap_or(map_height);
+            let start_x = center_x - width / 2;
+            let end_x = center_x + width / 2;
+            let water_line = surface(tile_map, start_x - 1).max(surface(tile_map, end_x + 1));
+            for x in start_x..=end_x {
+                let distance = (x as f32 - center_x as f32) / (width as f32 * 0.5);
+                let bowl_depth = (depth as f32 * (1.0 - distance * distance)) as usize;
+                for y in surface(tile_map, x)..=(water_line + bowl_depth).min(map_height - 1) {
+                    *tile_map.get_tile_mut(x, y, 0) = 0;
+                }
+            }
+            fill_liquid_pocket(tile_map, (center_x, water_line + 1), water_line, LiquidType::Water, width * (depth + 4));
+        }
+
+        // pools sitting on the floors of caves
+        for x in (0..map_width).step_by(5) {
+            for y in 0..map_height - 1 {
+                let (liquid, chance) = match self.get_layer(y, map_height).pools {
+                    Some(pools) => pools,
+                    None => continue,
+                };
+                if tile_map.is_solid(x, y) || !tile_map.is_solid(x, y + 1) || !tile_map.liquids.get(x, y).is_empty() { continue; }
+                if hash_position(x, y, self.seed) > chance { continue; }
+                fill_liquid_pocket(tile_map, (x, y), y.saturating_sub(3), liquid, 256);
+            }
         }
     }
 
-    pub fn update_edge_tiles(tile: u32, tile_type: &[&'static [u32]], tile_map: &mut crate::game_manager::world::tile_map::TileMap, tile_set: [u32; 16], x: usize, y: usize, layer: usize) {
-        if tile_type.iter().any(|set| set.contains(&tile)) {  // stone
-            let tiles_outside = [
-                tile_map.get_tile(x.saturating_sub(1), y, layer),
-                tile_map.get_tile((x + 1).min(tile_map.get_map_width() - 1), y, layer),
-                tile_map.get_tile(x, y.saturating_sub(1), layer),
-                tile_map.get_tile(x, (y + 1).min(tile_map.get_map_height() - 1), layer),
-            ];
-            let tile_edges = [
-                !SOLID_TILES.iter().any(|t| t.contains(&&tiles_outside[0])),
-                !SOLID_TILES.iter().any(|t| t.contains(&&tiles_outside[1])),
-                !SOLID_TILES.iter().any(|t| t.contains(&&tiles_outside[2])),
-                !SOLID_TILES.iter().any(|t| t.contains(&&tiles_outside[3])),
-            ];
-            // left right up down
-            let new_tile = match tile_edges {
-                [true, false, false, false] => tile_set[0],  // empty to left (wall facing to left)
-                [false, true, false, false] => tile_set[1],  // empty to right (wall facing to right)
-                [true, true, false, false]  => tile_set[2],  // empty to left and right (column)
-                [false, false, true, false] => tile_set[3],  // empty above (normal)
-                [false, false, false, true] => tile_set[4],  // empty below (upsidedown of normal)
-                [false, false, true, true]  => tile_set[5],  // empty above and below (ceiling ig)
-
-                [true, false, true, false]  => tile_set[6],  // empty to left and above (corner)
-                [true, false, false, true]  => tile_set[7],  // empty to left and below (corner)
-                [true, false, true, true]   => tile_set[8],  // empty to left above and below (cap facing left)
-
-                [false, true, false, true]  => tile_set[9],  // empty to right and below (corner)
-                [false, true, true, false]  => tile_set[10],  // empty to right and above (corner)
-                [false, true, true, true]   => tile_set[11],  // empty to right above and below (cap facing right)
-
-                [true, true, true, false]   => tile_set[12],  // empty to left, right and above (cap facing up)
-                [true, true, false, true]   => tile_set[13],  // empty to left, right and below (cap facing down)
-                [true, true, true, true]    => tile_set[14],  // surrounded
-
-                _ => tile_set[15],
-            };
-            *tile_map.get_tile_mut(x, y, layer) = new_tile;
+    /// Generates the terrain of the dimension into the tile map
+    /// Fills in the empty tile map, reporting the progress of each pass as it goes (and stopping early if it's cancelled)
+    pub fn generate_tile_map(&self, tile_map: &mut TileMap, reporter: &GenerationReporter) -> Result<(), TileMapError> {
+        // remembering the main biom of each column so the game can look it up later (the blending between bioms is only for the terrain)
+        tile_map.set_bioms(self.get_column_bioms(tile_map.get_map_width()));
+        match self.dimension {
+            Dimension::Overworld => self.generate_overworld(tile_map, reporter),
+            Dimension::SkyIslands => self.generate_sky_islands(tile_map, reporter),
+            Dimension::TOTAL => unreachable!("Dimension::TOTAL only counts the dimensions"),
         }
     }
-    
+
     // todo! add perlin noise and stuff
-    pub fn generate_tile_map(&self, tile_map: &mut crate::game_manager::world::tile_map::TileMap) -> Result<(), TileMapError> {
+    fn create_biom_noise(&self) -> FastNoiseLite {
         let mut biom_noise = FastNoiseLite::new();
         biom_noise.set_seed(Some(1234 + self.seed as i32));
         biom_noise.set_noise_type(Some(NoiseType::Perlin));
         biom_noise.set_frequency(Some(0.00075));
-        for x in 0..tile_map.get_map_width() {
+        biom_noise
+    }
+
+    /// The main biom of the column (bioms only change going across the world, not down it)
+    pub fn get_biom_at(&self, x: usize) -> Biom {
+        match self.dimension {
+            Dimension::Overworld => get_biom(self.create_biom_noise().get_noise_2d(x as f32, 256.0)).0.biom,
+            Dimension::SkyIslands => Biom::Sky,
+            Dimension::TOTAL => unreachable!("Dimension::TOTAL only counts the dimensions"),
+        }
+    }
+
+    /// The main biom of every column across the map (building the noise once rather than for each column)
+    pub fn get_column_bioms(&self, map_width: usize) -> Vec<Biom> {
+        match self.dimension {
+            Dimension::Overworld => {
+                let biom_noise = self.create_biom_noise();
+                (0..map_width).map(|x| get_biom(biom_noise.get_noise_2d(x as f32, 256.0)).0.biom).collect()
+            },
+            Dimension::SkyIslands => vec![Biom::Sky; map_width],
+            Dimension::TOTAL => unreachable!("Dimension::TOTAL only counts the dimensions"),
+        }
+    }
+
+    fn generate_overworld(&self, tile_map: &mut TileMap, reporter: &GenerationReporter) -> Result<(), TileMapError> {
+        let biom_noise = self.create_biom_noise();
+        let map_height = tile_map.get_map_height();
+        let map_width = tile_map.get_map_width();
+        for x in 0..map_width {
+            reporter.report_every(GenerationPass::Terrain, x, map_width)?;
             let biom = biom_noise.get_noise_2d(x as f32, 256.0);
             let dirt_depth = ((sample_land_noise(x as f32, 25.0, biom, self.seed) * 0.5 + 0.5) * 10.0) as usize;
-            let mut in_sky = true;
 
-            for y in 0..tile_map.get_map_height() {
+            for y in 0..map_height {
                 let height = ((sample_land_noise(x as f32, y as f32, biom, self.seed) * 0.5 + 0.5) * 50.0 + 100.0) as usize;
-                let cave_noise = sample_cave_noise(x as f32, y as f32, biom, self.seed);
-
-                if cave_noise > sample_cave_threshold_noise(x as f32, y as f32, biom, self.seed) + 1.5 + ((y as f32 - height as f32) * -0.1).max(-0.75) {
-                    if in_sky {
-                        tile_map.sky_light[x] = y as u32;
+                let layer = self.get_layer(y, map_height);
+                let (cave_x, cave_y) = (x as f32 * layer.cave_scale.0, y as f32 * layer.cave_scale.1);
+                let cave_noise = sample_cave_noise(cave_x, cave_y, biom, self.seed);
+
+                if cave_noise > sample_cave_threshold_noise(cave_x, cave_y, biom, self.seed) + 1.5 + layer.cave_threshold_offset + ((y as f32 - height as f32) * -0.1).max(-0.75) {
+                    // flooding the bottom most caves (lava lakes in the underworld)
+                    if let Some((liquid, rows)) = layer.cave_fill && y + rows >= map_height {
+                        tile_map.liquids.set(x, y, LiquidCell::new(liquid, MAX_LIQUID_LEVEL));
                     }
                     continue;
                 }
                 
-                if in_sky {
-                    tile_map.sky_light[x] = y as u32;
-                }
                 let (main_biom, blending_to_biom, weight) = get_biom(biom);
                 let biom_height = (sample_land_noise(x as f32, y as f32 - 256.0, biom, self.seed) * 0.5 + 0.5) * 50.0 + 145.0;
                 let in_biom = y < biom_height as usize;
@@ -326,57 +574,52 @@ impl WorldGenerator {
                     main_biom
                 } else { blending_to_biom };
                 if y == height {
-                    in_sky = false;
                     *tile_map.get_tile_mut(x, y, 0) = match in_biom {
                         true => (current_biom.generation_parameters.tile_mapping)(1),
                         false => 1,
                     };
                 } else if y > height && y <= height + dirt_depth {
-                    in_sky = false;
                     *tile_map.get_tile_mut(x, y, 0) = match in_biom {
                         true => (current_biom.generation_parameters.tile_mapping)(29),
                         false => 29,
                     };  // dirt
                 } else if y > height + dirt_depth {
-                    in_sky = false;
                     *tile_map.get_tile_mut(x, y, 0) = match in_biom {
                         true => (current_biom.generation_parameters.tile_mapping)(44),
-                        false => 44,
+                        false => layer.stone_tile,
                     }; // stone
                 }
             }
         }
 
+        self.generate_liquids(tile_map);
+        Self::finish_terrain(tile_map, reporter)
+    }
+
+    /// Autotiles the terrain, grows the trees and lights the map (the last steps of generating any dimension)
+    fn finish_terrain(tile_map: &mut TileMap, reporter: &GenerationReporter) -> Result<(), TileMapError> {
+        let map_width = tile_map.get_map_width();
         // post processing the dirt and grass to make them prettier
         // this should work better as it should support things like cave cutouts and stuff
-        for x in 0..tile_map.get_map_width() {
+        for x in 0..map_width {
+            reporter.report_every(GenerationPass::Autotile, x, map_width)?;
             for y in 0..tile_map.get_map_height() {
                 let tile = tile_map.get_tile(x, y, 0);
-                Self::update_edge_tiles(tile, &[DIRT_IDS, GRASS_IDS], tile_map, [
-                    7, 8, 10, 1, 47, 5, 2, 4, 13, 9, 3, 6, 11, 12, 14, 29
-                ], x, y, 0);
-                Self::update_edge_tiles(tile, &[STONE_IDS], tile_map, [
-                    7 + 29, 8 + 29, 10 + 29, 1 + 29, 45, 5 + 29, 2 + 29, 4 + 29, 13 + 29, 9 + 29, 3 + 29, 6 + 29, 11 + 29, 12 + 29, 14 + 29, 44
-                ], x, y, 0);
-
-                Self::update_edge_tiles(tile, &[SNOW_IDS], tile_map, [
-                    7 + 136, 8 + 136, 10 + 136, 1 + 136, 135, 5 + 136, 2 + 136, 4 + 136, 13 + 136, 9 + 136, 3 + 136, 6 + 136, 11 + 136, 12 + 136, 14 + 136, 29
-                ], x, y, 0);
-                Self::update_edge_tiles(tile, &[ICE_IDS], tile_map, [
-                    7 + 117, 8 + 117, 10 + 117, 1 + 117, 136, 5 + 117, 2 + 117, 4 + 117, 13 + 117, 9 + 117, 3 + 117, 6 + 117, 11 + 117, 12 + 117, 14 + 117, 132
-                ], x, y, 0);
-                Self::update_edge_tiles(tile, &[SAND_IDS], tile_map, [
-                    7 + 88, 8 + 88, 10 + 88, 1 + 88, 134, 5 + 88, 2 + 88, 4 + 88, 13 + 88, 9 + 88, 3 + 88, 6 + 88, 11 + 88, 12 + 88, 14 + 88, 117
-                ], x, y, 0);
-                Self::update_edge_tiles(tile, &[SAND_STONE_IDS], tile_map, [
-                    7 + 102, 8 + 102, 10 + 102, 1 + 102, 133, 5 + 102, 2 + 102, 4 + 102, 13 + 102, 9 + 102, 3 + 102, 6 + 102, 11 + 102, 12 + 102, 14 + 102, 117
-                ], x, y, 0);
+                // any dirt left exposed at generation gets grown over with grass
+                if DIRT_IDS.contains(&tile) && Self::is_exposed(tile_map, x, y) {
+                    if let Some(tile_set) = get_tile_type_properties(TileType::Grass).autotile {
+                        Self::update_edge_tiles(tile_set, tile_map, x, y, 0);
+                    }
+                    continue;
+                }
+                tile_map.autotile(x, y, 0);
             }
         }
 
         // trying to generate trees and bushes in flat sections
         // todo! make a much better system for predefined structures or multi-block objects
-        for x in 4..tile_map.get_map_width() - 4 {
+        for x in 4..map_width - 4 {
+            reporter.report_every(GenerationPass::Trees, x, map_width)?;
             let tree_chance = x % 25;
             if tree_chance != 10 && tree_chance != 20 { continue; }
             // locating the top top surface
@@ -406,88 +649,27 @@ impl WorldGenerator {
                         *tile_map.get_tile_mut(x + 0, y, 1) = 37 + 48;
                         *tile_map.get_tile_mut(x + 1, y, 1) = 38 + 48;
                         *tile_map.get_tile_mut(x + 2, y, 1) = 39 + 48;
-                    } else {
-                        // tree + 48
-                        // _  0  1  2  3
-                        // 4  5  6  7  8
-                        // 9  10 11 12 13
-                        // _  14 15 16 17
-                        // _  18 19 20 _
-                        // _  21 22 23 _
-                        // _  _  24 _  _
-                        // _  _  28 29 _
-                        // _  _  34 35 _
-                        *tile_map.get_tile_mut(x - 1, y - 8, 1) = 48 + 0;
-                        *tile_map.get_tile_mut(x + 0, y - 8, 1) = 48 + 1;
-                        *tile_map.get_tile_mut(x + 1, y - 8, 1) = 48 + 2;
-                        *tile_map.get_tile_mut(x + 2, y - 8, 1) = 48 + 3;
-
-                        *tile_map.get_tile_mut(x - 2, y - 7, 1) = 48 + 4;
-                        *tile_map.get_tile_mut(x - 1, y - 7, 1) = 48 + 5;
-                        *tile_map.get_tile_mut(x + 0, y - 7, 1) = 48 + 6;
-                        *tile_map.get_tile_mut(x + 1, y - 7, 1) = 48 + 7;
-                        *tile_map.get_tile_mut(x + 2, y - 7, 1) = 48 + 8;
-
-                        *tile_map.get_tile_mut(x - 2, y - 6, 1) = 48 + 9;
-                        *tile_map.get_tile_mut(x - 1, y - 6, 1) = 48 + 10;
-                        *tile_map.get_tile_mut(x + 0, y - 6, 1) = 48 + 11;
-                        *tile_map.get_tile_mut(x + 1, y - 6, 1) = 48 + 12;
-                        *tile_map.get_tile_mut(x + 2, y - 6, 1) = 48 + 13;
-
-                        *tile_map.get_tile_mut(x - 1, y - 5, 1) = 48 + 14;
-                        *tile_map.get_tile_mut(x + 0, y - 5, 1) = 48 + 15;
-                        *tile_map.get_tile_mut(x + 1, y - 5, 1) = 48 + 16;
-                        *tile_map.get_tile_mut(x + 2, y - 5, 1) = 48 + 17;
-
-                        *tile_map.get_tile_mut(x - 1, y - 4, 1) = 48 + 18;
-                        *tile_map.get_tile_mut(x + 0, y - 4, 1) = 48 + 19;
-                        *tile_map.get_tile_mut(x + 1, y - 4, 1) = 48 + 20;
-
-                        *tile_map.get_tile_mut(x - 1, y - 3, 1) = 48 + 21;
-                        *tile_map.get_tile_mut(x + 0, y - 3, 1) = 48 + 22;
-                        *tile_map.get_tile_mut(x + 1, y - 3, 1) = 48 + 23;
-
-                        *tile_map.get_tile_mut(x + 0, y - 2, 1) = 48 + 24;
-
-                        *tile_map.get_tile_mut(x + 0, y - 1, 1) = 48 + 28;
-                        *tile_map.get_tile_mut(x + 1, y - 1, 1) = 48 + 29;
-
-                        *tile_map.get_tile_mut(x + 0, y - 0, 1) = 48 + 34;
-                        *tile_map.get_tile_mut(x + 1, y - 0, 1) = 48 + 35;
+                    } else if tile_map.can_grow_tree(get_tree(TreeType::Oak), x, y) {
+                        tile_map.place_tree(get_tree(TreeType::Oak), x, y);
+                    }
+                } else if tree_chance == 20 && get_tile_properties(tile).tile_type == TileType::Sand {
+                    // cacti in the deserts
+                    if tile_map.can_grow_tree(get_tree(TreeType::Cactus), x, y) {
+                        tile_map.place_tree(get_tree(TreeType::Cactus), x, y);
                     }
                 }
                 if tile != 0 { break; }
             }
         }
 
-        for x in 0..tile_map.get_map_width() {
-            for y in 0..tile_map.get_map_height() {
-                if TILE_LIGHTS.iter().any(|(tile_id, _)| *tile_id == tile_map.get_tile(x, y as usize, 0)) {
-                    *tile_map.get_light_mut(x, y as usize) = TILE_LIGHTS
-                        .iter()
-                        .find(|(tile_id, _)| *tile_id == tile_map
-                            .get_tile(x, y as usize, 0))
-                        .ok_or_else(|| TileMapError {
-                            message: format!("[WorldGen Error] Unable to locate light for tile at ({}, {}) while generating tile map", x, y),
-                            level: LoggingError::Error
-                        })?.1;
-                }
-            }
-        }
-        for _ in 0..16 {
-            for x in 0..tile_map.get_map_width() {
-                for y in 0..tile_map.get_map_height() {
-                    let left = tile_map.get_light_mut(x.saturating_sub(1), y).iter().map(|f| f.saturating_sub(25)).collect::<Vec<u8>>();
-                    let right = tile_map.get_light_mut((x + 1).min(tile_map.get_map_width() - 1), y).iter().map(|f| f.saturating_sub(25)).collect::<Vec<u8>>();
-                    let up = tile_map.get_light_mut(x, y.saturating_sub(1)).iter().map(|f| f.saturating_sub(25)).collect::<Vec<u8>>();
-                    let down = tile_map.get_light_mut(x, (y + 1).min(tile_map.get_map_height() - 1)).iter().map(|f| f.saturating_sub(25)).collect::<Vec<u8>>();
-                    let self_light = tile_map.get_light_mut(x, y);
-                    let light_level = left.iter().enumerate().map(|(i, f)| (*f).max(right[i].max(up[i].max(down[i].max(self_light[i]))))).collect::<Vec<u8>>();
-                    //let light_level = left.max(right.max(up.max(down.max(self_light))));
-                    *tile_map.get_light_mut(x, y) = [light_level[0], light_level[1], light_level[2]];
-                }
-            }
-        } Ok(())
+        // the trees are on the background layer (which the spawn search ignores), so they never get in the way of it
+        tile_map.choose_spawn_point()?;
+
+        tile_map.flood_block_lights(reporter.progress_callback(GenerationPass::Lights));
+        // this also bails out if the lights were cancelled part way through
+        reporter.report(GenerationPass::Propagation, 0.0)?;
+        tile_map.flood_sky_light(reporter.progress_callback(GenerationPass::Propagation));
+        reporter.report(GenerationPass::Propagation, 1.0)
     }
 }
 
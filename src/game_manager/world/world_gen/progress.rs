@@ -0,0 +1,130 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::game_manager::world::tile_map::{TileMap, TileMapError};
+use crate::logging::logging::LoggingError;
+
+/// How many steps of a loop go by between each progress report
+static REPORT_INTERVAL: usize = 64;
+
+/// The steps of generating a world, in the order they run
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GenerationPass {
+    Terrain,
+    Autotile,
+    Trees,
+    Lights,
+    Propagation,  // spreading the sky light down into the caves
+}
+
+impl GenerationPass {
+    pub const ALL: [GenerationPass; 5] = [
+        GenerationPass::Terrain, GenerationPass::Autotile, GenerationPass::Trees, GenerationPass::Lights, GenerationPass::Propagation,
+    ];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            GenerationPass::Terrain => "Shaping terrain",
+            GenerationPass::Autotile => "Smoothing edges",
+            GenerationPass::Trees => "Growing trees",
+            GenerationPass::Lights => "Placing lights",
+            GenerationPass::Propagation => "Spreading light",
+        }
+    }
+
+    /// How far through the whole generation this is (0 to 1), given the progress through this pass
+    pub fn get_total_progress(&self, progress: f32) -> f32 {
+        let index = GenerationPass::ALL.iter().position(|pass| pass == self).unwrap_or(0);
+        (index as f32 + progress.clamp(0.0, 1.0)) / GenerationPass::ALL.len() as f32
+    }
+}
+
+/// What the generation thread sends back
+pub enum GenerationMessage {
+    Progress(GenerationPass, f32),  // the progress through the pass (0 to 1)
+    Finished(Box<TileMap>),  // boxed as the map is far bigger than the other messages
+    Failed(TileMapError),
+}
+
+/// Handed to the generator so it can report how far along it is, and so it can be told to stop part way through
+pub struct GenerationReporter {
+    sender: Option<crossbeam::channel::Sender<GenerationMessage>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl GenerationReporter {
+    pub fn new(sender: crossbeam::channel::Sender<GenerationMessage>, cancelled: Arc<AtomicBool>) -> Self {
+        GenerationReporter { sender: Some(sender), cancelled }
+    }
+
+    /// For generating on the spot, with nothing waiting on the progress
+    pub fn none() -> Self {
+        GenerationReporter { sender: None, cancelled: Arc::new(AtomicBool::new(false)) }
+    }
+
+    /// Sends the progress along, returning an error once the generation has been cancelled so it can bail out early
+    pub fn report(&self, pass: GenerationPass, progress: f32) -> Result<(), TileMapError> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Err(TileMapError {
+                message: String::from("World generation was cancelled"),
+                level: LoggingError::Info,
+            });
+        }
+        if let Some(sender) = &self.sender {
+            // nothing listening anymore just means nobody is watching the progress
+            let _ = sender.send(GenerationMessage::Progress(pass, progress));
+        } Ok(())
+    }
+
+    /// Reports the progress every so many steps of a loop (so the channel isn't flooded)
+    pub fn report_every(&self, pass: GenerationPass, step: usize, total: usize) -> Result<(), TileMapError> {
+        if !step.is_multiple_of(REPORT_INTERVAL) { return Ok(()); }
+        self.report(pass, step as f32 / total.max(1) as f32)
+    }
+
+    /// Wraps the reporter up for the passes that track their own progress (it returns false once cancelled so they can stop)
+    pub fn progress_callback(&self, pass: GenerationPass) -> impl FnMut(f32) -> bool + '_ {
+        move |progress| self.report(pass, progress).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_is_reported_once_every_64_steps() {
+        let (sender, receiver) = crossbeam::channel::unbounded();
+        let reporter = GenerationReporter::new(sender, Arc::new(AtomicBool::new(false)));
+        for step in 0..200 {
+            reporter.report_every(GenerationPass::Trees, step, 200).unwrap();
+        }
+        let reported = receiver.try_iter()
+            .map(|message| match message {
+                GenerationMessage::Progress(pass, progress) => (pass, progress),
+                _ => panic!("only progress should be reported"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(reported, vec![
+            (GenerationPass::Trees, 0.0),
+            (GenerationPass::Trees, 0.32),
+            (GenerationPass::Trees, 0.64),
+            (GenerationPass::Trees, 0.96),
+        ]);
+    }
+
+    #[test]
+    fn cancelled_reporters_stop_the_generation() {
+        let (sender, receiver) = crossbeam::channel::unbounded();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let reporter = GenerationReporter::new(sender, cancelled.clone());
+        assert!(reporter.report(GenerationPass::Terrain, 0.5).is_ok());
+
+        cancelled.store(true, Ordering::Relaxed);
+        let error = reporter.report(GenerationPass::Terrain, 0.6).unwrap_err();
+        assert_eq!(error.message, "World generation was cancelled");
+        assert!(!reporter.progress_callback(GenerationPass::Lights)(0.7));
+        // nothing gets sent once it's been cancelled
+        assert_eq!(receiver.try_iter().count(), 1);
+    }
+}
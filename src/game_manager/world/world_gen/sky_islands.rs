@@ -0,0 +1,67 @@
+use fastnoise_lite::{FastNoiseLite, NoiseType};
+
+use crate::game_manager::world::tile_map::{TileMap, TileMapError};
+use crate::game_manager::world::world_gen::{Layer, LayerDepth, LayerInfo, WorldGenerator, hash_position};
+use crate::game_manager::world::world_gen::progress::{GenerationPass, GenerationReporter};
+
+/// The rough gap between the centers of each island
+static ISLAND_SPACING: usize = 72;
+
+impl WorldGenerator {
+    /// Open sky the whole way down (there's no ground to go beneath)
+    pub(crate) fn sky_island_layers() -> Vec<LayerInfo> {
+        vec![
+            LayerInfo {
+                layer: Layer::Surface,
+                start: LayerDepth::FromTop(0),
+                cave_scale: (1.0, 1.0),
+                cave_threshold_offset: 0.0,
+                stone_tile: 44,
+                cave_fill: None,
+                pools: None,
+                ambient_light: [24, 24, 40],  // the undersides of the islands are never fully dark
+                background_color: [150, 190, 240],
+            },
+        ]
+    }
+
+    /// Scatters floating islands of grass, dirt and stone across an empty sky
+    pub(crate) fn generate_sky_islands(&self, tile_map: &mut TileMap, reporter: &GenerationReporter) -> Result<(), TileMapError> {
+        let mut edge_noise = FastNoiseLite::new();
+        edge_noise.set_seed(Some(4321 + self.seed as i32));
+        edge_noise.set_noise_type(Some(NoiseType::OpenSimplex2));
+        edge_noise.set_frequency(Some(0.08));
+
+        let map_width = tile_map.get_map_width();
+        let map_height = tile_map.get_map_height();
+        for spacing_x in (ISLAND_SPACING / 2..map_width.saturating_sub(ISLAND_SPACING / 2)).step_by(ISLAND_SPACING) {
+            reporter.report(GenerationPass::Terrain, spacing_x as f32 / map_width as f32)?;
+            // nudging each island around so they don't line up in a grid
+            let center_x = spacing_x + (hash_position(spacing_x, 0, self.seed) * ISLAND_SPACING as f32 * 0.5) as usize - ISLAND_SPACING / 4;
+            let center_y = map_height / 4 + (hash_position(spacing_x, 1, self.seed) * map_height as f32 * 0.5) as usize;
+            let radius = 10 + (hash_position(spacing_x, 2, self.seed) * 18.0) as usize;
+            let depth = 8 + (hash_position(spacing_x, 3, self.seed) * 12.0) as usize;
+
+            for x in center_x.saturating_sub(radius)..=(center_x + radius).min(map_width - 1) {
+                let distance = (x as f32 - center_x as f32) / radius as f32;
+                let edge = edge_noise.get_noise_2d(x as f32, center_y as f32);
+                // a mostly flat top that rounds off at the edges, with a tapered underside hanging below
+                let top = center_y as f32 - (1.0 - distance * distance) * 3.0 + edge * 1.5;
+                let bottom = center_y as f32 + (1.0 - distance.abs()).sqrt() * depth as f32 * (0.75 + edge * 0.25);
+                if bottom <= top { continue; }
+                let top = top.max(1.0) as usize;
+                let bottom = (bottom as usize).min(map_height - 1);
+                let dirt_depth = 2 + ((edge * 0.5 + 0.5) * 3.0) as usize;
+                for y in top..=bottom {
+                    *tile_map.get_tile_mut(x, y, 0) = match y - top {
+                        0 => 1,  // grass
+                        depth if depth <= dirt_depth => 29,  // dirt
+                        _ => 44,  // stone
+                    };
+                }
+            }
+        }
+
+        Self::finish_terrain(tile_map, reporter)
+    }
+}
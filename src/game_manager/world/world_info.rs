@@ -0,0 +1,79 @@
+use datetime::{DatePiece, LocalDateTime, TimePiece};
+
+use crate::game_manager::entities::player::player::GameMode;
+use crate::game_manager::game::Game;
+
+/// The summary of a saved world shown in the world list (kept in world_saves/<name>/world_info.json so it can be read without loading the world)
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct WorldInfo {
+    pub seed: f32,
+    pub size: (usize, usize),  // the size of the overworld in tiles
+    pub created: u64,      // seconds since the unix epoch
+    pub last_played: u64,  // seconds since the unix epoch
+    pub playtime: f64,     // the total seconds spent in the world
+    pub game_version: String,
+    pub game_mode: GameMode,
+}
+
+fn get_unix_time() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+impl WorldInfo {
+    pub fn new(game: &Game, game_version: &str) -> Self {
+        let now = get_unix_time();
+        WorldInfo {
+            seed: game.get_seed(),
+            size: game.get_world_size(),
+            created: now,
+            last_played: now,
+            playtime: 0.0,
+            game_version: String::from(game_version),
+            game_mode: game.player.game_mode,
+        }
+    }
+
+    /// Reads the world's info, returning None if it's missing (worlds saved before the file existed) or unreadable
+    pub fn load(world_path: &str) -> Option<Self> {
+        let data = std::fs::read_to_string(format!("{}/world_info.json", world_path)).ok()?;
+        serde_json::from_str(&data).ok()
+    }
+
+    pub fn save(&self, world_path: &str) -> Result<(), std::io::Error> {
+        let data = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(format!("{}/world_info.json", world_path), data)
+    }
+
+    /// Brings the info up to date with the game after a session of the given length (in seconds)
+    pub fn update(&mut self, game: &Game, game_version: &str, session_length: f64) {
+        self.seed = game.get_seed();
+        self.size = game.get_world_size();
+        self.last_played = get_unix_time();
+        self.playtime += session_length;
+        self.game_version = String::from(game_version);
+        self.game_mode = game.player.game_mode;
+    }
+
+    /// A single line summary for the world list
+    pub fn get_summary(&self) -> String {
+        let playtime_minutes = (self.playtime / 60.0) as u64;
+        format!(
+            "{:?} | {}x{} | seed {} | played {}h {}m | last {} | created {} | v{}",
+            self.game_mode, self.size.0, self.size.1, self.seed,
+            playtime_minutes / 60, playtime_minutes % 60,
+            format_date(self.last_played), format_date(self.created), self.game_version,
+        )
+    }
+}
+
+/// year-month-day hour:minute (in utc, matching the log timestamps)
+fn format_date(unix_time: u64) -> String {
+    let date = LocalDateTime::at(unix_time as i64);
+    format!(
+        "{}-{:02}-{:02} {:02}:{:02}",
+        date.year(), date.month().months_from_january() + 1, date.day(), date.hour(), date.minute()
+    )
+}
@@ -0,0 +1,8 @@
+// the game itself, kept apart from main.rs so the tools in src/bin/ (like worldgen) can be built on top of it
+
+pub mod shaders;
+pub mod game_manager;
+pub mod textures;
+pub mod logging;
+pub mod core;
+pub mod utill;
@@ -16,14 +16,8 @@ well, ig it maybe only needs to be run after restart? Or did this just fix it? i
 
 */
 
-use crate::logging::logging::{Log, LogType, LoggingError, Logs};
-
-mod shaders;
-mod game_manager;
-mod textures;
-mod logging;
-mod core;
-mod utill;
+use voxel_game::core;
+use voxel_game::logging::{self, logging::{Log, LogType, LoggingError, Logs}};
 
 fn main() {
     let logging_level = logging::logging::Logging::Everything;
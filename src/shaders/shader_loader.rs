@@ -3,6 +3,7 @@ use metal::Device;
 use crate::{logging::logging::{Log, LoggingError, Logs}, shaders::shader_handler::Shader, textures::textures::{get_font_atlas, get_texture_atlas}};
 
 pub static MAX_ENTITIES: usize = 1024;
+pub static MAX_VISIBLE_ROWS: usize = 1024;
 static MAX_PARTICLES: usize = 2048;
 static MAX_TEXTS: usize = 1024;
 
@@ -38,6 +39,7 @@ pub fn load_game_shaders(device: &Device, max_screen_size: (u32, u32), logs: &mu
                 (size_of::<bool>() * 64 * u8::MAX as usize) as u64,
                 size_of::<u32>() as u64, // default_font_size
                 (size_of::<u8>() as u32 * max_screen_size.0 * max_screen_size.1 * 3) as u64,
+                (size_of::<u32>() * MAX_VISIBLE_ROWS) as u64, // row_backgrounds (the background color for each visible row of tiles)
             ], "ComputeShader")?;
             
             // loading the textures